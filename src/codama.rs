@@ -0,0 +1,414 @@
+use anchor_lang_idl::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlEnumVariant, IdlField, IdlInstructionAccountItem,
+    IdlSerialization, IdlType, IdlTypeDefTy,
+};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+use crate::generate::to_camel_case;
+
+/// Codama standard version the root node declares
+const CODAMA_VERSION: &str = "1.0.0";
+
+/// Map an Anchor IDL to a Codama `rootNode` holding a single `programNode`
+///
+/// Covers accounts, instructions (accounts and arguments, with the discriminator as a leading
+/// field), defined types and errors. Events, constants and PDA seeds have no counterpart in
+/// the subset mapped here and are left out; generics, zero-copy or custom serialization and
+/// 256-bit integers are rejected.
+pub fn root_node(idl: &Idl) -> Result<Value> {
+    let defined_types = idl
+        .types
+        .iter()
+        .map(|def| {
+            if !def.generics.is_empty() {
+                return Err(anyhow!("Type '{}' is generic, which Codama can't represent", def.name));
+            }
+            if !matches!(def.serialization, IdlSerialization::Borsh) {
+                return Err(anyhow!(
+                    "Type '{}' isn't Borsh-serialized, which this mapping doesn't support",
+                    def.name
+                ));
+            }
+            let ty = type_def_node(&def.ty)
+                .with_context(|| format!("Failed to map type '{}'", def.name))?;
+            Ok(json!({
+                "kind": "definedTypeNode",
+                "name": to_camel_case(&def.name),
+                "docs": def.docs,
+                "type": ty,
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    let accounts = idl
+        .accounts
+        .iter()
+        .map(|account| {
+            let def = crate::generate::find_type(idl, &account.name)
+                .ok_or_else(|| anyhow!("Account '{}' has no type definition", account.name))?;
+            let IdlTypeDefTy::Struct { fields } = &def.ty else {
+                return Err(anyhow!("Account '{}' is not a struct", account.name));
+            };
+            let mut data = vec![discriminator_field(&account.discriminator)];
+            data.extend(
+                struct_fields(fields.as_ref())
+                    .with_context(|| format!("Failed to map account '{}'", account.name))?,
+            );
+            Ok(json!({
+                "kind": "accountNode",
+                "name": to_camel_case(&account.name),
+                "docs": def.docs,
+                "data": { "kind": "structTypeNode", "fields": data },
+                "discriminators": [field_discriminator()],
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    let instructions = idl
+        .instructions
+        .iter()
+        .map(|instruction| {
+            let mut arguments = vec![discriminator_argument(&instruction.discriminator)];
+            for arg in &instruction.args {
+                arguments.push(json!({
+                    "kind": "instructionArgumentNode",
+                    "name": to_camel_case(&arg.name),
+                    "docs": arg.docs,
+                    "type": type_node(&arg.ty).with_context(|| {
+                        format!("Failed to map arg '{}' of '{}'", arg.name, instruction.name)
+                    })?,
+                }));
+            }
+            let mut accounts = Vec::new();
+            instruction_accounts(&instruction.accounts, &mut accounts);
+            Ok(json!({
+                "kind": "instructionNode",
+                "name": to_camel_case(&instruction.name),
+                "docs": instruction.docs,
+                "optionalAccountStrategy": "programId",
+                "accounts": accounts,
+                "arguments": arguments,
+                "discriminators": [field_discriminator()],
+            }))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    let errors: Vec<Value> = idl
+        .errors
+        .iter()
+        .map(|error| {
+            json!({
+                "kind": "errorNode",
+                "name": to_camel_case(&error.name),
+                "code": error.code,
+                "message": error.msg.clone().unwrap_or_default(),
+                "docs": [],
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "kind": "rootNode",
+        "standard": "codama",
+        "version": CODAMA_VERSION,
+        "program": {
+            "kind": "programNode",
+            "name": to_camel_case(&idl.metadata.name),
+            "publicKey": idl.address,
+            "version": idl.metadata.version,
+            "origin": "anchor",
+            "docs": idl.docs,
+            "accounts": accounts,
+            "instructions": instructions,
+            "definedTypes": defined_types,
+            "pdas": [],
+            "errors": errors,
+        },
+        "additionalPrograms": [],
+    }))
+}
+
+// Composite groups have no Codama node, so their accounts are listed in place
+fn instruction_accounts(items: &[IdlInstructionAccountItem], out: &mut Vec<Value>) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(account) => {
+                let mut node = json!({
+                    "kind": "instructionAccountNode",
+                    "name": to_camel_case(&account.name),
+                    "isWritable": account.writable,
+                    "isSigner": account.signer,
+                    "isOptional": account.optional,
+                    "docs": account.docs,
+                });
+                if let Some(address) = &account.address {
+                    node["defaultValue"] = json!({ "kind": "publicKeyValueNode", "publicKey": address });
+                }
+                out.push(node);
+            }
+            IdlInstructionAccountItem::Composite(composite) => {
+                instruction_accounts(&composite.accounts, out)
+            }
+        }
+    }
+}
+
+fn type_def_node(ty: &IdlTypeDefTy) -> Result<Value> {
+    match ty {
+        IdlTypeDefTy::Struct { fields } => defined_fields_node(fields.as_ref()),
+        IdlTypeDefTy::Enum { variants } => {
+            let variants = variants
+                .iter()
+                .map(variant_node)
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(json!({
+                "kind": "enumTypeNode",
+                "variants": variants,
+                "size": number_node("u8"),
+            }))
+        }
+        IdlTypeDefTy::Type { alias } => type_node(alias),
+    }
+}
+
+fn variant_node(variant: &IdlEnumVariant) -> Result<Value> {
+    let name = to_camel_case(&variant.name);
+    let node = match &variant.fields {
+        None => json!({ "kind": "enumEmptyVariantTypeNode", "name": name }),
+        Some(IdlDefinedFields::Named(_)) => json!({
+            "kind": "enumStructVariantTypeNode",
+            "name": name,
+            "struct": defined_fields_node(variant.fields.as_ref())?,
+        }),
+        Some(IdlDefinedFields::Tuple(_)) => json!({
+            "kind": "enumTupleVariantTypeNode",
+            "name": name,
+            "tuple": defined_fields_node(variant.fields.as_ref())?,
+        }),
+    };
+    Ok(node)
+}
+
+fn defined_fields_node(fields: Option<&IdlDefinedFields>) -> Result<Value> {
+    match fields {
+        Some(IdlDefinedFields::Tuple(types)) => {
+            let items = types.iter().map(type_node).collect::<Result<Vec<Value>>>()?;
+            Ok(json!({ "kind": "tupleTypeNode", "items": items }))
+        }
+        fields => Ok(json!({ "kind": "structTypeNode", "fields": struct_fields(fields)? })),
+    }
+}
+
+fn struct_fields(fields: Option<&IdlDefinedFields>) -> Result<Vec<Value>> {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => fields.iter().map(struct_field).collect(),
+        Some(IdlDefinedFields::Tuple(_)) => Err(anyhow!("tuple fields can't be mapped to a struct")),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn struct_field(field: &IdlField) -> Result<Value> {
+    Ok(json!({
+        "kind": "structFieldTypeNode",
+        "name": to_camel_case(&field.name),
+        "docs": field.docs,
+        "type": type_node(&field.ty).with_context(|| format!("Field '{}'", field.name))?,
+    }))
+}
+
+/// The Codama type node for an IDL type; lengths and prefixes follow Borsh
+pub fn type_node(ty: &IdlType) -> Result<Value> {
+    let node = match ty {
+        IdlType::Bool => json!({ "kind": "booleanTypeNode", "size": number_node("u8") }),
+        IdlType::U8 => number_node("u8"),
+        IdlType::I8 => number_node("i8"),
+        IdlType::U16 => number_node("u16"),
+        IdlType::I16 => number_node("i16"),
+        IdlType::U32 => number_node("u32"),
+        IdlType::I32 => number_node("i32"),
+        IdlType::F32 => number_node("f32"),
+        IdlType::U64 => number_node("u64"),
+        IdlType::I64 => number_node("i64"),
+        IdlType::F64 => number_node("f64"),
+        IdlType::U128 => number_node("u128"),
+        IdlType::I128 => number_node("i128"),
+        IdlType::String => json!({
+            "kind": "sizePrefixTypeNode",
+            "type": { "kind": "stringTypeNode", "encoding": "utf8" },
+            "prefix": number_node("u32"),
+        }),
+        IdlType::Bytes => json!({
+            "kind": "sizePrefixTypeNode",
+            "type": { "kind": "bytesTypeNode" },
+            "prefix": number_node("u32"),
+        }),
+        IdlType::Pubkey => json!({ "kind": "publicKeyTypeNode" }),
+        IdlType::Option(inner) => json!({
+            "kind": "optionTypeNode",
+            "fixed": false,
+            "item": type_node(inner)?,
+            "prefix": number_node("u8"),
+        }),
+        IdlType::Vec(inner) => json!({
+            "kind": "arrayTypeNode",
+            "item": type_node(inner)?,
+            "count": { "kind": "prefixedCountNode", "prefix": number_node("u32") },
+        }),
+        IdlType::Array(inner, IdlArrayLen::Value(len)) => json!({
+            "kind": "arrayTypeNode",
+            "item": type_node(inner)?,
+            "count": { "kind": "fixedCountNode", "value": len },
+        }),
+        IdlType::Defined { name, generics } if generics.is_empty() => {
+            json!({ "kind": "definedTypeLinkNode", "name": to_camel_case(name) })
+        }
+        other => {
+            return Err(anyhow!(
+                "type {} has no Codama type node",
+                crate::format_type(other)
+            ))
+        }
+    };
+    Ok(node)
+}
+
+fn number_node(format: &str) -> Value {
+    json!({ "kind": "numberTypeNode", "format": format, "endian": "le" })
+}
+
+// Accounts and instructions are told apart by a leading `discriminator` field
+fn field_discriminator() -> Value {
+    json!({ "kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0 })
+}
+
+fn discriminator_type(discriminator: &[u8]) -> Value {
+    json!({
+        "kind": "fixedSizeTypeNode",
+        "size": discriminator.len(),
+        "type": { "kind": "bytesTypeNode" },
+    })
+}
+
+fn discriminator_value(discriminator: &[u8]) -> Value {
+    let hex: String = discriminator.iter().map(|byte| format!("{:02x}", byte)).collect();
+    json!({ "kind": "bytesValueNode", "data": hex, "encoding": "base16" })
+}
+
+fn discriminator_field(discriminator: &[u8]) -> Value {
+    json!({
+        "kind": "structFieldTypeNode",
+        "name": "discriminator",
+        "docs": [],
+        "type": discriminator_type(discriminator),
+        "defaultValue": discriminator_value(discriminator),
+        "defaultValueStrategy": "omitted",
+    })
+}
+
+fn discriminator_argument(discriminator: &[u8]) -> Value {
+    json!({
+        "kind": "instructionArgumentNode",
+        "name": "discriminator",
+        "docs": [],
+        "type": discriminator_type(discriminator),
+        "defaultValue": discriminator_value(discriminator),
+        "defaultValueStrategy": "omitted",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instruction_node_has_its_discriminator_accounts_and_arguments() {
+        let idl: Idl = serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "set_config",
+                "docs": ["Updates the config"],
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 255],
+                "accounts": [
+                    { "name": "authority", "signer": true },
+                    {
+                        "name": "group",
+                        "accounts": [{ "name": "config_account", "writable": true }]
+                    },
+                    { "name": "system_program", "address": "11111111111111111111111111111111" }
+                ],
+                "args": [{ "name": "new_fee", "type": "u16" }]
+            }]
+        }))
+        .unwrap();
+
+        let root = root_node(&idl).unwrap();
+        assert_eq!(
+            root["program"]["instructions"][0],
+            json!({
+                "kind": "instructionNode",
+                "name": "setConfig",
+                "docs": ["Updates the config"],
+                "optionalAccountStrategy": "programId",
+                "accounts": [
+                    {
+                        "kind": "instructionAccountNode",
+                        "name": "authority",
+                        "isWritable": false,
+                        "isSigner": true,
+                        "isOptional": false,
+                        "docs": []
+                    },
+                    {
+                        "kind": "instructionAccountNode",
+                        "name": "configAccount",
+                        "isWritable": true,
+                        "isSigner": false,
+                        "isOptional": false,
+                        "docs": []
+                    },
+                    {
+                        "kind": "instructionAccountNode",
+                        "name": "systemProgram",
+                        "isWritable": false,
+                        "isSigner": false,
+                        "isOptional": false,
+                        "docs": [],
+                        "defaultValue": {
+                            "kind": "publicKeyValueNode",
+                            "publicKey": "11111111111111111111111111111111"
+                        }
+                    }
+                ],
+                "arguments": [
+                    {
+                        "kind": "instructionArgumentNode",
+                        "name": "discriminator",
+                        "docs": [],
+                        "type": {
+                            "kind": "fixedSizeTypeNode",
+                            "size": 8,
+                            "type": { "kind": "bytesTypeNode" }
+                        },
+                        "defaultValue": {
+                            "kind": "bytesValueNode",
+                            "data": "01020304050607ff",
+                            "encoding": "base16"
+                        },
+                        "defaultValueStrategy": "omitted"
+                    },
+                    {
+                        "kind": "instructionArgumentNode",
+                        "name": "newFee",
+                        "docs": [],
+                        "type": { "kind": "numberTypeNode", "format": "u16", "endian": "le" }
+                    }
+                ],
+                "discriminators": [
+                    { "kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0 }
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use serde_json::{json, Value};
+use std::io::IsTerminal;
+
+/// How serious a diagnostic is; any error fails the command that collected it
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            Severity::Error => "[ERROR]",
+            Severity::Warning => "[WARN]",
+            Severity::Info => "[INFO]",
+        }
+    }
+
+    // ANSI foreground colors: red, yellow, blue
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => "\x1b[31m",
+            Severity::Warning => "\x1b[33m",
+            Severity::Info => "\x1b[34m",
+        }
+    }
+}
+
+/// One reported issue; `code` is a stable kebab-case identifier for scripts to match on
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub path: Option<String>,
+}
+
+/// Issues a command collects while it runs and renders once it's done
+#[derive(Default)]
+pub struct Diagnostics {
+    path: Option<String>,
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// A collector whose diagnostics all refer to the file at `path`
+    pub fn for_path(path: impl std::fmt::Display) -> Self {
+        Self {
+            path: Some(path.to_string()),
+            items: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, code: &'static str, message: impl Into<String>) {
+        self.items.push(Diagnostic {
+            severity,
+            code,
+            message: message.into(),
+            path: self.path.clone(),
+        });
+    }
+
+    pub fn error(&mut self, code: &'static str, message: impl Into<String>) {
+        self.push(Severity::Error, code, message);
+    }
+
+    pub fn warning(&mut self, code: &'static str, message: impl Into<String>) {
+        self.push(Severity::Warning, code, message);
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.items
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    pub fn first_error(&self) -> Option<&Diagnostic> {
+        self.errors().next()
+    }
+
+    /// Errors in the order they were reported
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.items
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// `1 error(s), 2 warning(s), 0 info(s)`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} error(s), {} warning(s), {} info(s)",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        )
+    }
+
+    /// Print every diagnostic to stderr behind a `[ERROR]`/`[WARN]`/`[INFO]` tag, errors first,
+    /// then the summary; tags are colored when stderr is a terminal and `NO_COLOR` isn't set
+    pub fn render_human(&self) {
+        let color = std::io::stderr().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+        for line in self.human_lines(color) {
+            eprintln!("{}", line);
+        }
+    }
+
+    // The lines `render_human` prints; nothing at all when there are no diagnostics
+    fn human_lines(&self, color: bool) -> Vec<String> {
+        if self.items.is_empty() {
+            return Vec::new();
+        }
+
+        let mut items: Vec<&Diagnostic> = self.items.iter().collect();
+        items.sort_by_key(|diagnostic| diagnostic.severity);
+        let mut lines: Vec<String> = items
+            .into_iter()
+            .map(|diagnostic| {
+                let tag = diagnostic.severity.tag();
+                if color {
+                    format!("{}{}\x1b[0m {}", diagnostic.severity.color(), tag, diagnostic.message)
+                } else {
+                    format!("{} {}", tag, diagnostic.message)
+                }
+            })
+            .collect();
+        lines.push(self.summary());
+        lines
+    }
+
+    /// `{ "errors": n, "warnings": n, "diagnostics": [{ severity, code, message, path }] }`
+    pub fn to_json(&self) -> Value {
+        let diagnostics: Vec<Value> = self
+            .items
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "severity": diagnostic.severity.as_str(),
+                    "code": diagnostic.code,
+                    "message": diagnostic.message,
+                    "path": diagnostic.path,
+                })
+            })
+            .collect();
+        json!({
+            "errors": self.count(Severity::Error),
+            "warnings": self.count(Severity::Warning),
+            "diagnostics": diagnostics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_every_diagnostic_with_its_path_and_counts() {
+        let mut diagnostics = Diagnostics::for_path("idl.json");
+        diagnostics.warning("unused-type", "type 'Old' is never used");
+        diagnostics.error("missing-address", "IDL has no address");
+        diagnostics.push(Severity::Info, "spec", "IDL spec 0.1.0");
+
+        assert_eq!(
+            diagnostics.to_json(),
+            json!({
+                "errors": 1,
+                "warnings": 1,
+                "diagnostics": [
+                    {
+                        "severity": "warning",
+                        "code": "unused-type",
+                        "message": "type 'Old' is never used",
+                        "path": "idl.json"
+                    },
+                    {
+                        "severity": "error",
+                        "code": "missing-address",
+                        "message": "IDL has no address",
+                        "path": "idl.json"
+                    },
+                    {
+                        "severity": "info",
+                        "code": "spec",
+                        "message": "IDL spec 0.1.0",
+                        "path": "idl.json"
+                    }
+                ]
+            })
+        );
+        assert_eq!(diagnostics.first_error().unwrap().code, "missing-address");
+    }
+
+    #[test]
+    fn diagnostics_without_a_file_have_a_null_path() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.error("bad-input", "not an IDL");
+        assert_eq!(diagnostics.to_json()["diagnostics"][0]["path"], Value::Null);
+    }
+
+    #[test]
+    fn human_output_lists_errors_first_and_ends_with_the_counts() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.warning("unused-type", "type 'Old' is never used");
+        diagnostics.push(Severity::Info, "spec", "IDL spec 0.1.0");
+        diagnostics.error("missing-address", "IDL has no address");
+        diagnostics.warning("empty-docs", "instruction 'init' has no docs");
+
+        assert_eq!(
+            diagnostics.human_lines(false),
+            [
+                "[ERROR] IDL has no address",
+                "[WARN] type 'Old' is never used",
+                "[WARN] instruction 'init' has no docs",
+                "[INFO] IDL spec 0.1.0",
+                "1 error(s), 2 warning(s), 1 info(s)",
+            ]
+        );
+        assert_eq!(
+            diagnostics.human_lines(true)[0],
+            "\x1b[31m[ERROR]\x1b[0m IDL has no address"
+        );
+        assert!(Diagnostics::default().human_lines(false).is_empty());
+    }
+}
@@ -0,0 +1,605 @@
+use anchor_lang_idl::types::Idl;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+/// A single structural difference between two canonicalized IDLs
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+// Top-level sections whose entries are keyed by name and can be sorted freely
+const NAMED_SECTIONS: &[&str] = &["instructions", "accounts", "events", "types", "constants"];
+
+/// Canonicalize an IDL into a JSON value with a stable key and section ordering
+pub fn canonicalize(idl: &Idl) -> Result<Value> {
+    let value = serde_json::to_value(idl).context("Failed to serialize IDL")?;
+    let mut value = sort_keys(value);
+
+    if let Value::Object(map) = &mut value {
+        for section in NAMED_SECTIONS {
+            if let Some(Value::Array(items)) = map.get_mut(*section) {
+                items.sort_by(|a, b| entry_name(a).cmp(&entry_name(b)));
+            }
+        }
+        // Errors are identified by their code rather than their name
+        if let Some(Value::Array(errors)) = map.get_mut("errors") {
+            errors.sort_by_key(|e| e.get("code").and_then(Value::as_u64));
+        }
+    }
+
+    Ok(value)
+}
+
+// Recursively rebuild objects with their keys in lexicographic order
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sort_keys(v)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+fn entry_name(value: &Value) -> Option<&str> {
+    value.get("name").and_then(Value::as_str)
+}
+
+/// Compute the structural differences between two canonicalized IDLs
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_value("", old, new, &mut changes);
+    changes
+}
+
+fn diff_value(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = join_key(path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_value(&child, old_value, new_value, changes),
+                    None => changes.push(Change::Removed {
+                        path: child,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(Change::Added {
+                        path: join_key(path, key),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            // Below the top level, order is the Borsh layout or the account ABI, so position counts
+            let is_section = !path.contains(['.', '[']);
+            if is_section && is_named_list(old_items) && is_named_list(new_items) {
+                diff_named(path, old_items, new_items, changes);
+            } else {
+                diff_indexed(path, old_items, new_items, changes);
+            }
+        }
+        _ => {
+            if old != new {
+                changes.push(Change::Changed {
+                    path: path.to_string(),
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+        }
+    }
+}
+
+// Top-level sections whose entries all carry a unique name are matched by name instead of position
+fn is_named_list(items: &[Value]) -> bool {
+    let mut names = std::collections::HashSet::new();
+    items
+        .iter()
+        .all(|item| entry_name(item).is_some_and(|n| names.insert(n)))
+}
+
+fn diff_named(path: &str, old_items: &[Value], new_items: &[Value], changes: &mut Vec<Change>) {
+    let find = |items: &'_ [Value], name: &str| -> Option<usize> {
+        items.iter().position(|item| entry_name(item) == Some(name))
+    };
+
+    for old_item in old_items {
+        let name = entry_name(old_item).unwrap_or_default();
+        let child = format!("{}[{}]", path, name);
+        match find(new_items, name) {
+            Some(idx) => diff_value(&child, old_item, &new_items[idx], changes),
+            None => changes.push(Change::Removed {
+                path: child,
+                value: old_item.clone(),
+            }),
+        }
+    }
+    for new_item in new_items {
+        let name = entry_name(new_item).unwrap_or_default();
+        if find(old_items, name).is_none() {
+            changes.push(Change::Added {
+                path: format!("{}[{}]", path, name),
+                value: new_item.clone(),
+            });
+        }
+    }
+}
+
+fn diff_indexed(path: &str, old_items: &[Value], new_items: &[Value], changes: &mut Vec<Change>) {
+    for (idx, old_item) in old_items.iter().enumerate() {
+        let child = format!("{}[{}]", path, idx);
+        match new_items.get(idx) {
+            Some(new_item) => diff_value(&child, old_item, new_item, changes),
+            None => changes.push(Change::Removed {
+                path: child,
+                value: old_item.clone(),
+            }),
+        }
+    }
+    for (idx, new_item) in new_items.iter().enumerate().skip(old_items.len()) {
+        changes.push(Change::Added {
+            path: format!("{}[{}]", path, idx),
+            value: new_item.clone(),
+        });
+    }
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Print a human-readable summary of the changes
+pub fn print_changes(changes: &[Change]) {
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+
+    for change in changes {
+        match change {
+            Change::Added { path, value } => {
+                added += 1;
+                println!("  + {}: {}", path, value);
+            }
+            Change::Removed { path, value } => {
+                removed += 1;
+                println!("  - {}: {}", path, value);
+            }
+            Change::Changed { path, old, new } => {
+                changed += 1;
+                println!("  ~ {}: {} -> {}", path, old, new);
+            }
+        }
+    }
+
+    println!(
+        "\n{} change(s): {} added, {} removed, {} changed",
+        changes.len(),
+        added,
+        removed,
+        changed
+    );
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } => {
+                path
+            }
+        }
+    }
+
+    /// Whether existing clients or on-chain data could break under this change
+    ///
+    /// Docs and metadata never break anything, and neither do new top-level entries or sections
+    /// (empty sections aren't serialized, so the first constant adds a whole `constants`),
+    /// appended enum variants or PDA hints. Everything else alters the wire format or an account layout.
+    pub fn is_breaking(&self) -> bool {
+        let path = self.path();
+        let segments: Vec<&str> = path.split('.').collect();
+        let is_cosmetic = segments
+            .iter()
+            .any(|segment| *segment == "docs" || segment.starts_with("docs["))
+            || segments[0] == "metadata"
+            || (segments[0].starts_with("errors[") && segments.last() == Some(&"msg"));
+        if is_cosmetic {
+            return false;
+        }
+
+        match self {
+            Change::Added { .. } => {
+                let last = segments.last().copied().unwrap_or_default();
+                let is_new_entry = segments.len() == 1;
+                let is_new_variant = last.starts_with("variants[");
+                let is_resolution_hint = segments
+                    .iter()
+                    .any(|segment| *segment == "pda" || *segment == "relations");
+                !(is_new_entry || is_new_variant || is_resolution_hint)
+            }
+            Change::Removed { .. } | Change::Changed { .. } => true,
+        }
+    }
+}
+
+/// Print the changes as JSON, each with a `breaking` flag
+pub fn changes_to_json(changes: &[Change]) -> Value {
+    let entries: Vec<Value> = changes
+        .iter()
+        .map(|change| {
+            let mut entry = match change {
+                Change::Added { path, value } => {
+                    serde_json::json!({ "kind": "added", "path": path, "value": value })
+                }
+                Change::Removed { path, value } => {
+                    serde_json::json!({ "kind": "removed", "path": path, "value": value })
+                }
+                Change::Changed { path, old, new } => {
+                    serde_json::json!({ "kind": "changed", "path": path, "old": old, "new": new })
+                }
+            };
+            entry["breaking"] = change.is_breaking().into();
+            entry
+        })
+        .collect();
+
+    serde_json::json!({
+        "breaking": changes.iter().any(Change::is_breaking),
+        "changes": entries,
+    })
+}
+
+// Lines of context around each hunk, as in `diff -u`
+const UNIFIED_CONTEXT: usize = 3;
+
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render a unified diff of the pretty-printed canonical JSON of two IDLs
+pub fn unified(old: &Value, new: &Value, old_label: &str, new_label: &str) -> Result<String> {
+    let old_text = serde_json::to_string_pretty(old)?;
+    let new_text = serde_json::to_string_pretty(new)?;
+    let old_lines: Vec<&str> = old_text.lines().collect();
+    let new_lines: Vec<&str> = new_text.lines().collect();
+
+    let edits = line_edits(&old_lines, &new_lines);
+    let mut out = String::new();
+    if edits.iter().all(|edit| matches!(edit, Edit::Equal)) {
+        return Ok(out);
+    }
+    out.push_str(&format!("--- {}\n+++ {}\n", old_label, new_label));
+
+    // Line positions in both inputs before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changed: Vec<usize> = (0..edits.len())
+        .filter(|&idx| !matches!(edits[idx], Edit::Equal))
+        .collect();
+
+    // Merge changes whose context windows touch into a single hunk
+    let mut idx = 0;
+    while idx < changed.len() {
+        let start = changed[idx].saturating_sub(UNIFIED_CONTEXT);
+        let mut last = changed[idx];
+        while idx + 1 < changed.len() && changed[idx + 1] - last <= 2 * UNIFIED_CONTEXT {
+            idx += 1;
+            last = changed[idx];
+        }
+        let end = (last + 1 + UNIFIED_CONTEXT).min(edits.len());
+        idx += 1;
+
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        for (edit, (old_pos, new_pos)) in edits[start..end].iter().zip(&positions[start..end]) {
+            match edit {
+                Edit::Equal => out.push_str(&format!(" {}\n", old_lines[*old_pos])),
+                Edit::Delete => out.push_str(&format!("-{}\n", old_lines[*old_pos])),
+                Edit::Insert => out.push_str(&format!("+{}\n", new_lines[*new_pos])),
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // An empty range names the line before it, per the unified format
+    let first = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        first.to_string()
+    } else {
+        format!("{},{}", first, len)
+    }
+}
+
+// Myers' shortest edit script between two line sequences
+fn line_edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max as usize;
+    let mut frontier = vec![0isize; 2 * offset + 2];
+    // Only the diagonals reachable within `d` edits are kept for each step
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(frontier[offset - d as usize..=offset + d as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = |k: isize| frontier[(k + offset as isize) as usize];
+            let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                at(k + 1)
+            } else {
+                at(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            frontier[(k + offset as isize) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, snapshot) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| snapshot[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert);
+            } else {
+                edits.push(Edit::Delete);
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn canonical(value: Value) -> Value {
+        let idl: Idl = serde_json::from_value(value).unwrap();
+        canonicalize(&idl).unwrap()
+    }
+
+    fn base_idl() -> Value {
+        json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "init",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    { "name": "payer", "writable": true, "signer": true },
+                    { "name": "state", "writable": true }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "bump", "type": "u8" }
+                ]
+            }],
+            "accounts": [{ "name": "State", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+            "types": [
+                {
+                    "name": "State",
+                    "type": { "kind": "struct", "fields": [
+                        { "name": "owner", "type": "pubkey" },
+                        { "name": "count", "type": "u64" }
+                    ] }
+                },
+                {
+                    "name": "Mode",
+                    "type": { "kind": "enum", "variants": [{ "name": "A" }, { "name": "B" }] }
+                }
+            ]
+        })
+    }
+
+    fn changes(old: Value, new: Value) -> Vec<Change> {
+        diff(&canonical(old), &canonical(new))
+    }
+
+    fn reversed(value: &mut Value) {
+        value.as_array_mut().unwrap().reverse();
+    }
+
+    #[test]
+    fn identical_idls_have_no_changes() {
+        assert!(changes(base_idl(), base_idl()).is_empty());
+    }
+
+    #[test]
+    fn top_level_entries_are_matched_by_name() {
+        let mut new = base_idl();
+        reversed(&mut new["types"]);
+        assert!(changes(base_idl(), new).is_empty());
+    }
+
+    #[test]
+    fn reordered_fields_are_reported() {
+        let mut new = base_idl();
+        reversed(&mut new["types"][0]["type"]["fields"]);
+        let changes = changes(base_idl(), new);
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|c| c.path().starts_with("types[State].type.fields[")));
+    }
+
+    #[test]
+    fn reordered_args_are_reported() {
+        let mut new = base_idl();
+        reversed(&mut new["instructions"][0]["args"]);
+        let changes = changes(base_idl(), new);
+        assert!(changes.iter().any(|c| c.path() == "instructions[init].args[0].name"));
+    }
+
+    #[test]
+    fn reordered_accounts_are_reported() {
+        let mut new = base_idl();
+        reversed(&mut new["instructions"][0]["accounts"]);
+        let changes = changes(base_idl(), new);
+        assert!(changes.iter().any(|c| c.path() == "instructions[init].accounts[0].name"));
+    }
+
+    fn breaking(old: Value, new: Value) -> Vec<String> {
+        changes(old, new)
+            .iter()
+            .filter(|change| change.is_breaking())
+            .map(|change| change.path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reorders_are_breaking() {
+        for pointer in [
+            "/types/0/type/fields",
+            "/instructions/0/args",
+            "/instructions/0/accounts",
+        ] {
+            let mut new = base_idl();
+            reversed(new.pointer_mut(pointer).unwrap());
+            assert!(!breaking(base_idl(), new).is_empty(), "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn variant_inserted_mid_list_is_breaking() {
+        let mut new = base_idl();
+        let variants = new["types"][1]["type"]["variants"].as_array_mut().unwrap();
+        variants.insert(0, json!({ "name": "C" }));
+        assert!(!breaking(base_idl(), new).is_empty());
+    }
+
+    #[test]
+    fn appended_variant_is_not_breaking() {
+        let mut new = base_idl();
+        let variants = new["types"][1]["type"]["variants"].as_array_mut().unwrap();
+        variants.push(json!({ "name": "C" }));
+        let changes = changes(base_idl(), new);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].is_breaking());
+    }
+
+    #[test]
+    fn first_constant_is_not_breaking() {
+        let mut new = base_idl();
+        new["constants"] = json!([{ "name": "MAX", "type": "u8", "value": "8" }]);
+        let changes = changes(base_idl(), new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path(), "constants");
+        assert!(!changes[0].is_breaking());
+    }
+
+    #[test]
+    fn removed_instruction_is_breaking() {
+        let mut old = base_idl();
+        old["instructions"].as_array_mut().unwrap().push(json!({
+            "name": "close",
+            "discriminator": [8, 7, 6, 5, 4, 3, 2, 1],
+            "accounts": [],
+            "args": []
+        }));
+        assert_eq!(breaking(old, base_idl()), ["instructions[close]"]);
+    }
+
+    #[test]
+    fn json_lists_an_added_instruction() {
+        let mut new = base_idl();
+        new["instructions"].as_array_mut().unwrap().push(json!({
+            "name": "close",
+            "discriminator": [8, 7, 6, 5, 4, 3, 2, 1],
+            "accounts": [],
+            "args": []
+        }));
+        let json = changes_to_json(&changes(base_idl(), new));
+        assert_eq!(json["breaking"], false);
+        let entries = json["changes"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["kind"], "added");
+        assert_eq!(entries[0]["path"], "instructions[close]");
+        assert_eq!(entries[0]["breaking"], false);
+        assert_eq!(entries[0]["value"]["name"], "close");
+    }
+
+    #[test]
+    fn unified_marks_changed_lines() {
+        let mut new = base_idl();
+        new["metadata"]["version"] = json!("0.2.0");
+        let text = unified(&canonical(base_idl()), &canonical(new), "a.json", "b.json").unwrap();
+        assert!(text.starts_with("--- a.json\n+++ b.json\n@@ "));
+        assert!(text.contains("-    \"version\": \"0.1.0\""));
+        assert!(text.contains("+    \"version\": \"0.2.0\""));
+        assert!(unified(&canonical(base_idl()), &canonical(base_idl()), "a", "b")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::{anyhow, Context, Result};
+use std::io::Read;
+
+/// Section a program build embeds its IDL in
+pub const IDL_SECTION: &str = ".solana";
+
+// ELF section type for sections that occupy no space in the file (`.bss`)
+const SHT_NOBITS: u32 = 8;
+
+/// Extract the IDL embedded in a program's `.so`, decompressing it if it's zlib-compressed
+/// like the on-chain IDL account
+pub fn embedded_idl(elf: &[u8]) -> Result<Vec<u8>> {
+    let data = section(elf, IDL_SECTION)?
+        .ok_or_else(|| anyhow!("Program has no embedded IDL ({} section not found)", IDL_SECTION))?;
+
+    let json = trim_padding(data);
+    if json.is_empty() {
+        return Err(anyhow!("The {} section is empty", IDL_SECTION));
+    }
+    if json[0] == b'{' {
+        return Ok(json.to_vec());
+    }
+
+    let mut idl = Vec::new();
+    flate2::read::ZlibDecoder::new(data)
+        .read_to_end(&mut idl)
+        .with_context(|| {
+            format!("The {} section is neither JSON nor zlib-compressed", IDL_SECTION)
+        })?;
+    Ok(idl)
+}
+
+/// Contents of the named section, or `None` if the file has no such section
+///
+/// Solana programs are 64-bit little-endian ELF files, which is all this reads.
+pub fn section<'a>(elf: &'a [u8], name: &str) -> Result<Option<&'a [u8]>> {
+    if elf.get(..4) != Some(b"\x7fELF".as_slice()) {
+        return Err(anyhow!("Not an ELF file"));
+    }
+    if elf.get(4) != Some(&2) || elf.get(5) != Some(&1) {
+        return Err(anyhow!("Only 64-bit little-endian ELF files are supported"));
+    }
+
+    let shoff = read_u64(elf, 0x28)? as usize;
+    let shentsize = read_u16(elf, 0x3a)? as usize;
+    let shnum = read_u16(elf, 0x3c)? as usize;
+    let shstrndx = read_u16(elf, 0x3e)? as usize;
+
+    let header = |idx: usize| -> Result<SectionHeader> {
+        let base = idx
+            .checked_mul(shentsize)
+            .and_then(|offset| offset.checked_add(shoff))
+            .ok_or_else(|| anyhow!("Section header {} is out of range", idx))?;
+        Ok(SectionHeader {
+            name: read_u32(elf, base)? as usize,
+            kind: read_u32(elf, base + 4)?,
+            offset: read_u64(elf, base + 24)? as usize,
+            size: read_u64(elf, base + 32)? as usize,
+        })
+    };
+
+    let names = header(shstrndx)?.contents(elf)?;
+    for idx in 0..shnum {
+        let section = header(idx)?;
+        let section_name = names
+            .get(section.name..)
+            .and_then(|rest| rest.split(|&b| b == 0).next())
+            .ok_or_else(|| anyhow!("Section {} has an invalid name offset", idx))?;
+        if section_name == name.as_bytes() {
+            if section.kind == SHT_NOBITS {
+                return Ok(Some(&[]));
+            }
+            return section.contents(elf).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+struct SectionHeader {
+    name: usize,
+    kind: u32,
+    offset: usize,
+    size: usize,
+}
+
+impl SectionHeader {
+    fn contents<'a>(&self, elf: &'a [u8]) -> Result<&'a [u8]> {
+        self.offset
+            .checked_add(self.size)
+            .and_then(|end| elf.get(self.offset..end))
+            .ok_or_else(|| anyhow!("Section data extends past the end of the file"))
+    }
+}
+
+// Sections are often padded to an alignment boundary with zero bytes; a zlib stream can end
+// in a zero byte, so only uncompressed JSON is trimmed
+fn trim_padding(data: &[u8]) -> &[u8] {
+    let end = data.iter().rposition(|&b| b != 0).map_or(0, |idx| idx + 1);
+    &data[..end]
+}
+
+fn read_u16(elf: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(read_bytes(elf, offset)?))
+}
+
+fn read_u32(elf: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(elf, offset)?))
+}
+
+fn read_u64(elf: &[u8], offset: usize) -> Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(elf, offset)?))
+}
+
+fn read_bytes<const N: usize>(elf: &[u8], offset: usize) -> Result<[u8; N]> {
+    offset
+        .checked_add(N)
+        .and_then(|end| elf.get(offset..end))
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow!("ELF file is truncated"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const IDL: &[u8] = br#"{"address":"11111111111111111111111111111112"}"#;
+
+    // A minimal 64-bit little-endian ELF: the header, each section's data, a section name table,
+    // then the section headers (the null section first and the name table last)
+    fn elf(sections: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut names = vec![0u8];
+        let mut name_offsets = Vec::new();
+        for name in sections.iter().map(|(name, ..)| *name).chain([".shstrtab"]) {
+            name_offsets.push(names.len() as u32);
+            names.extend(name.as_bytes());
+            names.push(0);
+        }
+        let all = sections
+            .iter()
+            .map(|(_, kind, contents)| (*kind, *contents))
+            .chain([(3, names.as_slice())]);
+
+        let mut data: Vec<u8> = Vec::new();
+        let mut headers = vec![0u8; 64];
+        for ((kind, contents), name) in all.zip(name_offsets) {
+            let mut header = [0u8; 64];
+            header[0..4].copy_from_slice(&name.to_le_bytes());
+            header[4..8].copy_from_slice(&kind.to_le_bytes());
+            header[24..32].copy_from_slice(&(64 + data.len() as u64).to_le_bytes());
+            header[32..40].copy_from_slice(&(contents.len() as u64).to_le_bytes());
+            headers.extend(header);
+            data.extend(contents);
+        }
+        let count = (headers.len() / 64) as u16;
+
+        let mut file = vec![0u8; 64];
+        file[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        file[0x28..0x30].copy_from_slice(&(64 + data.len() as u64).to_le_bytes());
+        file[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        file[0x3c..0x3e].copy_from_slice(&count.to_le_bytes());
+        file[0x3e..0x40].copy_from_slice(&(count - 1).to_le_bytes());
+        file.extend(data);
+        file.extend(headers);
+        file
+    }
+
+    #[test]
+    fn extracts_json_from_the_idl_section_without_padding() {
+        let mut padded = IDL.to_vec();
+        padded.extend([0; 5]);
+        let file = elf(&[(".text", 1, &[0x95; 8]), (IDL_SECTION, 1, &padded)]);
+
+        assert_eq!(embedded_idl(&file).unwrap(), IDL);
+        assert_eq!(section(&file, ".text").unwrap(), Some(&[0x95; 8][..]));
+    }
+
+    #[test]
+    fn decompresses_a_zlib_idl_section() {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(IDL).unwrap();
+        let file = elf(&[(IDL_SECTION, 1, &encoder.finish().unwrap())]);
+
+        assert_eq!(embedded_idl(&file).unwrap(), IDL);
+    }
+
+    #[test]
+    fn a_missing_or_empty_idl_section_is_a_clear_error() {
+        let error = embedded_idl(&elf(&[(".text", 1, &[0x95; 8])])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Program has no embedded IDL (.solana section not found)"
+        );
+
+        let error = embedded_idl(&elf(&[(IDL_SECTION, SHT_NOBITS, &[])])).unwrap_err();
+        assert_eq!(error.to_string(), "The .solana section is empty");
+
+        let error = embedded_idl(b"MZ not an elf").unwrap_err();
+        assert_eq!(error.to_string(), "Not an ELF file");
+    }
+
+    #[test]
+    fn truncated_files_are_errors_not_panics() {
+        let file = elf(&[(IDL_SECTION, 1, IDL)]);
+
+        // Cut inside the file header, before the name table's header and inside its size field
+        for len in [6, 0x30, 0x3f, file.len() - 64, file.len() - 30] {
+            let error = section(&file[..len], IDL_SECTION).unwrap_err();
+            assert_eq!(error.to_string(), "ELF file is truncated", "cut at {}", len);
+        }
+
+        // Section headers pointing past the end of the file, including offsets that overflow
+        let mut far = file.clone();
+        far[0x28..0x30].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        assert_eq!(
+            section(&far, IDL_SECTION).unwrap_err().to_string(),
+            "Section header 2 is out of range"
+        );
+        let mut far = file.clone();
+        far[0x3a..0x3c].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(section(&far, IDL_SECTION).is_err());
+    }
+
+    #[test]
+    fn malformed_headers_are_reported() {
+        let file = elf(&[(IDL_SECTION, 1, IDL)]);
+        // The first section header follows the header and the data of both sections
+        let first = 64 + IDL.len() + ".solana\0.shstrtab\0".len() + 1 + 64;
+
+        let mut wide = file.clone();
+        wide[4] = 1;
+        assert_eq!(
+            section(&wide, IDL_SECTION).unwrap_err().to_string(),
+            "Only 64-bit little-endian ELF files are supported"
+        );
+        let mut big_endian = file.clone();
+        big_endian[5] = 2;
+        assert!(section(&big_endian, IDL_SECTION).is_err());
+
+        let mut bad_name = file.clone();
+        bad_name[first..first + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            section(&bad_name, IDL_SECTION).unwrap_err().to_string(),
+            "Section 1 has an invalid name offset"
+        );
+
+        let mut bad_size = file.clone();
+        bad_size[first + 32..first + 40].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(
+            section(&bad_size, IDL_SECTION).unwrap_err().to_string(),
+            "Section data extends past the end of the file"
+        );
+
+        let mut bad_names = file;
+        bad_names[0x3e..0x40].copy_from_slice(&7u16.to_le_bytes());
+        assert_eq!(
+            section(&bad_names, IDL_SECTION).unwrap_err().to_string(),
+            "ELF file is truncated"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use anchor_lang_idl::types::{
+    Idl, IdlDefinedFields, IdlGenericArg, IdlInstructionAccountItem, IdlSeed, IdlType,
+    IdlTypeDefTy,
+};
+use std::collections::{HashMap, HashSet};
+
+use crate::generate::to_snake_case;
+
+/// Descriptions of the fixes applied to an IDL, and of unsafe fixes that were only found
+#[derive(Default)]
+pub struct FixReport {
+    pub applied: Vec<String>,
+    pub unsafe_applied: Vec<String>,
+    pub unsafe_available: Vec<String>,
+}
+
+/// Apply the auto-fixes to `idl`; fixes that change the program's API only run with `allow_unsafe`
+pub fn apply(idl: &mut Idl, allow_unsafe: bool) -> FixReport {
+    let mut report = FixReport::default();
+
+    let trimmed = trim_docs(idl);
+    if trimmed > 0 {
+        report
+            .applied
+            .push(format!("trimmed whitespace in {} doc line(s)", trimmed));
+    }
+
+    for name in remove_unused_types(idl) {
+        report.applied.push(format!("removed unused type '{}'", name));
+    }
+
+    let renames = snake_case_renames(idl, allow_unsafe);
+    if allow_unsafe {
+        report.unsafe_applied = renames;
+    } else {
+        report.unsafe_available = renames;
+    }
+
+    report
+}
+
+fn trim_docs(idl: &mut Idl) -> usize {
+    let mut count = 0;
+    let mut trim = |docs: &mut Vec<String>| {
+        for doc in docs.iter_mut() {
+            let trimmed = doc.trim();
+            if trimmed.len() != doc.len() {
+                *doc = trimmed.to_string();
+                count += 1;
+            }
+        }
+    };
+
+    trim(&mut idl.docs);
+    for instruction in &mut idl.instructions {
+        trim(&mut instruction.docs);
+        for arg in &mut instruction.args {
+            trim(&mut arg.docs);
+        }
+        for_each_account(&mut instruction.accounts, &mut |account| trim(&mut account.docs));
+    }
+    for def in &mut idl.types {
+        trim(&mut def.docs);
+        if let IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Named(fields)),
+        } = &mut def.ty
+        {
+            for field in fields {
+                trim(&mut field.docs);
+            }
+        }
+    }
+    for constant in &mut idl.constants {
+        trim(&mut constant.docs);
+    }
+    count
+}
+
+/// Remove types not reachable from instructions, accounts, events and constants, returning their names
+pub fn remove_unused_types(idl: &mut Idl) -> Vec<String> {
+    let mut pending: Vec<String> = idl
+        .accounts
+        .iter()
+        .map(|account| account.name.clone())
+        .chain(idl.events.iter().map(|event| event.name.clone()))
+        .collect();
+    for instruction in &idl.instructions {
+        for arg in &instruction.args {
+            referenced_types(&arg.ty, &mut pending);
+        }
+        if let Some(returns) = &instruction.returns {
+            referenced_types(returns, &mut pending);
+        }
+    }
+    for constant in &idl.constants {
+        referenced_types(&constant.ty, &mut pending);
+    }
+
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if !used.insert(name.clone()) {
+            continue;
+        }
+        let Some(def) = idl.types.iter().find(|def| def.name == name) else {
+            continue;
+        };
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => fields_types(fields.as_ref(), &mut pending),
+            IdlTypeDefTy::Enum { variants } => {
+                for variant in variants {
+                    fields_types(variant.fields.as_ref(), &mut pending);
+                }
+            }
+            IdlTypeDefTy::Type { alias } => referenced_types(alias, &mut pending),
+        }
+    }
+
+    let mut removed = Vec::new();
+    idl.types.retain(|def| {
+        let keep = used.contains(&def.name);
+        if !keep {
+            removed.push(def.name.clone());
+        }
+        keep
+    });
+    removed
+}
+
+fn fields_types(fields: Option<&IdlDefinedFields>, names: &mut Vec<String>) {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => {
+            for field in fields {
+                referenced_types(&field.ty, names);
+            }
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            for ty in types {
+                referenced_types(ty, names);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Push the name of every defined type `ty` refers to, including through containers and generics
+pub fn referenced_types(ty: &IdlType, names: &mut Vec<String>) {
+    match ty {
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            referenced_types(inner, names)
+        }
+        IdlType::Defined { name, generics } => {
+            names.push(name.clone());
+            for generic in generics {
+                if let IdlGenericArg::Type { ty } = generic {
+                    referenced_types(ty, names);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Renames instructions, args and accounts to snake_case, keeping seed paths and relations in step
+fn snake_case_renames(idl: &mut Idl, apply: bool) -> Vec<String> {
+    let mut renames = Vec::new();
+
+    for instruction in &mut idl.instructions {
+        let snake = to_snake_case(&instruction.name);
+        if snake != instruction.name {
+            renames.push(format!("instruction '{}' -> '{}'", instruction.name, snake));
+            if apply {
+                instruction.name = snake;
+            }
+        }
+
+        let mut arg_renames = HashMap::new();
+        for arg in &mut instruction.args {
+            let snake = to_snake_case(&arg.name);
+            if snake != arg.name {
+                renames.push(format!(
+                    "arg '{}' of '{}' -> '{}'",
+                    arg.name, instruction.name, snake
+                ));
+                arg_renames.insert(arg.name.clone(), snake.clone());
+                if apply {
+                    arg.name = snake;
+                }
+            }
+        }
+
+        let mut account_renames = HashMap::new();
+        for_each_account(&mut instruction.accounts, &mut |account| {
+            let snake = to_snake_case(&account.name);
+            if snake != account.name {
+                account_renames.insert(account.name.clone(), snake.clone());
+                if apply {
+                    account.name = snake;
+                }
+            }
+        });
+        let mut renamed_accounts: Vec<_> = account_renames.iter().collect();
+        renamed_accounts.sort();
+        for (old, new) in renamed_accounts {
+            renames.push(format!(
+                "account '{}' of '{}' -> '{}'",
+                old, instruction.name, new
+            ));
+        }
+
+        if !apply {
+            continue;
+        }
+        for_each_account(&mut instruction.accounts, &mut |account| {
+            for relation in &mut account.relations {
+                if let Some(new) = account_renames.get(relation.as_str()) {
+                    *relation = new.clone();
+                }
+            }
+            if let Some(pda) = &mut account.pda {
+                for seed in pda.seeds.iter_mut().chain(pda.program.as_mut()) {
+                    match seed {
+                        IdlSeed::Arg(seed) => rename_path(&mut seed.path, &arg_renames),
+                        IdlSeed::Account(seed) => rename_path(&mut seed.path, &account_renames),
+                        IdlSeed::Const(_) => {}
+                    }
+                }
+            }
+        });
+    }
+
+    renames
+}
+
+/// Rename the arg or account a seed path like `params.id` refers to by its first segment
+pub fn rename_path(path: &mut String, renames: &HashMap<String, String>) {
+    let (head, rest) = match path.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (path.as_str(), None),
+    };
+    if let Some(new) = renames.get(head) {
+        *path = match rest {
+            Some(rest) => format!("{}.{}", new, rest),
+            None => new.clone(),
+        };
+    }
+}
+
+fn for_each_account(
+    accounts: &mut [IdlInstructionAccountItem],
+    f: &mut dyn FnMut(&mut anchor_lang_idl::types::IdlInstructionAccount),
+) {
+    for item in accounts {
+        match item {
+            IdlInstructionAccountItem::Single(account) => f(account),
+            IdlInstructionAccountItem::Composite(composite) => {
+                for_each_account(&mut composite.accounts, f)
+            }
+        }
+    }
+}
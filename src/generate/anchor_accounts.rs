@@ -0,0 +1,336 @@
+use super::{find_type, to_pascal_case};
+use anchor_lang_idl::types::{
+    Idl, IdlArrayLen, IdlField, IdlInstruction, IdlInstructionAccount, IdlInstructionAccountItem,
+    IdlSeed, IdlSerialization, IdlType,
+};
+use anyhow::Result;
+use std::fmt::Write;
+
+const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+
+/// Render a `#[derive(Accounts)]` struct skeleton per instruction, with the constraints the IDL
+/// records: `mut`, `Signer`, `seeds`/`bump`, `has_one` and `Option<...>` for optional accounts
+///
+/// The IDL doesn't record `init`, `space`, `close` or custom constraints, and account types only
+/// survive when an account is named after one of the IDL's accounts; the rest are left as
+/// `UncheckedAccount` with a note to fill them in.
+pub fn generate(idl: &Idl) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by dls-anchor from {} v{} as a starting point; review every constraint.",
+        idl.metadata.name, idl.metadata.version
+    )?;
+    writeln!(
+        out,
+        "// init, space, close and custom constraints aren't recorded in the IDL."
+    )?;
+    writeln!(out, "use anchor_lang::prelude::*;")?;
+
+    for instruction in &idl.instructions {
+        let name = to_pascal_case(&instruction.name);
+        write_struct(
+            &mut out,
+            idl,
+            instruction,
+            &name,
+            &instruction.docs,
+            &instruction.accounts,
+        )?;
+    }
+    Ok(out)
+}
+
+fn write_struct(
+    out: &mut String,
+    idl: &Idl,
+    instruction: &IdlInstruction,
+    name: &str,
+    docs: &[String],
+    accounts: &[IdlInstructionAccountItem],
+) -> Result<()> {
+    // Composite groups become their own structs, named after the instruction and the group
+    let mut nested = Vec::new();
+
+    writeln!(out)?;
+    for doc in docs {
+        writeln!(out, "/// {}", doc)?;
+    }
+    writeln!(out, "#[derive(Accounts)]")?;
+    let seed_args = seed_args(instruction, accounts);
+    if !seed_args.is_empty() {
+        let args: Vec<String> = seed_args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, rust_type(&arg.ty)))
+            .collect();
+        writeln!(out, "#[instruction({})]", args.join(", "))?;
+    }
+    writeln!(out, "pub struct {}<'info> {{", name)?;
+
+    for item in accounts {
+        match item {
+            IdlInstructionAccountItem::Single(account) => {
+                write_account(out, idl, instruction, account)?
+            }
+            IdlInstructionAccountItem::Composite(composite) => {
+                let group = format!("{}{}", name, to_pascal_case(&composite.name));
+                writeln!(out, "    pub {}: {}<'info>,", composite.name, group)?;
+                nested.push((group, &composite.accounts));
+            }
+        }
+    }
+    writeln!(out, "}}")?;
+
+    for (group, accounts) in nested {
+        write_struct(out, idl, instruction, &group, &[], accounts)?;
+    }
+    Ok(())
+}
+
+fn write_account(
+    out: &mut String,
+    idl: &Idl,
+    instruction: &IdlInstruction,
+    account: &IdlInstructionAccount,
+) -> Result<()> {
+    let mut constraints = Vec::new();
+    let mut notes = Vec::new();
+
+    if account.writable {
+        constraints.push("mut".to_string());
+    }
+    if let Some(pda) = &account.pda {
+        let stored_bump = crate::pda::stored_bump(pda);
+        let seeds = match stored_bump {
+            Some(_) => &pda.seeds[..pda.seeds.len() - 1],
+            None => &pda.seeds[..],
+        };
+        let seeds: Vec<String> = seeds
+            .iter()
+            .map(|seed| seed_expr(instruction, seed, &mut notes))
+            .collect();
+        constraints.push(format!("seeds = [{}]", seeds.join(", ")));
+        constraints.push(match stored_bump {
+            Some(bump) => format!("bump = {}", bump),
+            None => "bump".to_string(),
+        });
+        if pda.program.is_some() {
+            notes.push("derived from another program; add `seeds::program`".to_string());
+        }
+    }
+    for relation in &account.relations {
+        constraints.push(format!("has_one = {}", relation));
+    }
+
+    let account_type = account_type(idl, account, &mut constraints, &mut notes);
+    let ty = if account.optional {
+        format!("Option<{}>", account_type)
+    } else {
+        account_type
+    };
+
+    for doc in &account.docs {
+        writeln!(out, "    /// {}", doc)?;
+    }
+    if ty.contains("UncheckedAccount") {
+        writeln!(out, "    /// CHECK: the IDL doesn't record this account's type")?;
+    }
+    for note in notes {
+        writeln!(out, "    // NOTE: {}", note)?;
+    }
+    if !constraints.is_empty() {
+        writeln!(out, "    #[account({})]", constraints.join(", "))?;
+    }
+    writeln!(out, "    pub {}: {},", account.name, ty)?;
+    Ok(())
+}
+
+// The Anchor wrapper for an account: `Signer`, `Program<System>`, an `Account`/`AccountLoader`
+// of a same-named IDL account, or `UncheckedAccount`
+fn account_type(
+    idl: &Idl,
+    account: &IdlInstructionAccount,
+    constraints: &mut Vec<String>,
+    notes: &mut Vec<String>,
+) -> String {
+    if account.signer {
+        return "Signer<'info>".to_string();
+    }
+    if let Some(address) = &account.address {
+        if address == SYSTEM_PROGRAM_ID {
+            return "Program<'info, System>".to_string();
+        }
+        constraints.push(format!("address = pubkey!(\"{}\")", address));
+        notes.push("fixed address; use `Program<'info, T>` if this is a program".to_string());
+        return "UncheckedAccount<'info>".to_string();
+    }
+
+    let type_name = to_pascal_case(&account.name);
+    if !idl.accounts.iter().any(|def| def.name == type_name) {
+        return "UncheckedAccount<'info>".to_string();
+    }
+    let zero_copy = find_type(idl, &type_name).is_some_and(|def| {
+        matches!(
+            def.serialization,
+            IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe
+        )
+    });
+    if zero_copy {
+        format!("AccountLoader<'info, {}>", type_name)
+    } else {
+        format!("Account<'info, {}>", type_name)
+    }
+}
+
+// The expression for one seed inside `seeds = [...]`
+fn seed_expr(instruction: &IdlInstruction, seed: &IdlSeed, notes: &mut Vec<String>) -> String {
+    match seed {
+        IdlSeed::Const(seed) => match std::str::from_utf8(&seed.value) {
+            Ok(text) if text.chars().all(|c| c.is_ascii_graphic() || c == ' ') => {
+                format!("b{:?}", text)
+            }
+            // The suffix pins the element type so the slice is `&[u8]`
+            _ => {
+                let bytes: Vec<String> = seed.value.iter().map(|byte| byte.to_string()).collect();
+                format!("&[{}u8]", bytes.join("u8, "))
+            }
+        },
+        IdlSeed::Arg(seed) => {
+            let arg = instruction
+                .args
+                .iter()
+                .find(|arg| arg.name == seed.path);
+            match arg.map(|arg| &arg.ty) {
+                Some(IdlType::Pubkey) => format!("{}.as_ref()", seed.path),
+                Some(IdlType::String) => format!("{}.as_bytes()", seed.path),
+                Some(IdlType::Bytes) | Some(IdlType::Vec(_)) | Some(IdlType::Array(..)) => {
+                    format!("{}.as_ref()", seed.path)
+                }
+                Some(IdlType::Bool | IdlType::Defined { .. } | IdlType::Option(_)) | None => {
+                    notes.push(format!("seed from arg `{}` needs converting to bytes", seed.path));
+                    format!("{}.as_ref()", seed.path)
+                }
+                Some(_) => format!("{}.to_le_bytes().as_ref()", seed.path),
+            }
+        }
+        IdlSeed::Account(seed) => {
+            if seed.path.contains('.') {
+                // `config.authority` reads a field, assumed here to be a `Pubkey`
+                notes.push(format!("seed `{}` reads an account field", seed.path));
+                format!("{}.as_ref()", seed.path)
+            } else {
+                format!("{}.key().as_ref()", seed.path)
+            }
+        }
+    }
+}
+
+// `#[instruction(...)]` must list args from the first, up to the last one a seed in these
+// accounts reads
+fn seed_args<'a>(
+    instruction: &'a IdlInstruction,
+    accounts: &[IdlInstructionAccountItem],
+) -> &'a [IdlField] {
+    let mut used = Vec::new();
+    collect_arg_seeds(accounts, &mut used);
+    let last = instruction
+        .args
+        .iter()
+        .rposition(|arg| used.contains(&arg.name.as_str()));
+    match last {
+        Some(last) => &instruction.args[..=last],
+        None => &[],
+    }
+}
+
+fn collect_arg_seeds<'a>(accounts: &'a [IdlInstructionAccountItem], used: &mut Vec<&'a str>) {
+    for item in accounts {
+        match item {
+            IdlInstructionAccountItem::Single(account) => {
+                let seeds = account.pda.iter().flat_map(|pda| &pda.seeds);
+                for seed in seeds {
+                    if let IdlSeed::Arg(seed) = seed {
+                        // `params.id` reads a field of the `params` arg
+                        used.push(seed.path.split('.').next().unwrap_or_default());
+                    }
+                }
+            }
+            IdlInstructionAccountItem::Composite(composite) => {
+                collect_arg_seeds(&composite.accounts, used)
+            }
+        }
+    }
+}
+
+// Rust spelling of an IDL type, as used in `#[instruction(...)]`
+fn rust_type(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bytes => "Vec<u8>".into(),
+        IdlType::String => "String".into(),
+        IdlType::Pubkey => "Pubkey".into(),
+        IdlType::Option(inner) => format!("Option<{}>", rust_type(inner)),
+        IdlType::Vec(inner) => format!("Vec<{}>", rust_type(inner)),
+        IdlType::Array(inner, IdlArrayLen::Value(len)) => format!("[{}; {}]", rust_type(inner), len),
+        IdlType::Array(inner, IdlArrayLen::Generic(len)) => format!("[{}; {}]", rust_type(inner), len),
+        other => crate::format_type(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pda_accounts_get_seeds_bump_and_the_args_they_read() {
+        let idl: Idl = serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "initialize",
+                "docs": ["Creates the state account"],
+                "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                "accounts": [
+                    { "name": "payer", "writable": true, "signer": true },
+                    {
+                        "name": "state",
+                        "writable": true,
+                        "relations": ["payer"],
+                        "pda": { "seeds": [
+                            { "kind": "const", "value": [115, 116, 97, 116, 101] },
+                            { "kind": "account", "path": "payer" },
+                            { "kind": "arg", "path": "id" }
+                        ] }
+                    },
+                    { "name": "system_program", "address": "11111111111111111111111111111111" }
+                ],
+                "args": [
+                    { "name": "id", "type": "u64" },
+                    { "name": "amount", "type": "u64" }
+                ]
+            }],
+            "accounts": [{ "name": "State", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] }],
+            "types": [{
+                "name": "State",
+                "type": { "kind": "struct", "fields": [{ "name": "payer", "type": "pubkey" }] }
+            }]
+        }))
+        .unwrap();
+
+        let out = generate(&idl).unwrap();
+        let structure = &out[out.find("/// Creates").unwrap()..];
+        assert_eq!(
+            structure,
+            "/// Creates the state account\n\
+             #[derive(Accounts)]\n\
+             #[instruction(id: u64)]\n\
+             pub struct Initialize<'info> {\n\
+             \x20   #[account(mut)]\n\
+             \x20   pub payer: Signer<'info>,\n\
+             \x20   #[account(mut, seeds = [b\"state\", payer.key().as_ref(), id.to_le_bytes().as_ref()], bump, has_one = payer)]\n\
+             \x20   pub state: Account<'info, State>,\n\
+             \x20   pub system_program: Program<'info, System>,\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use anchor_lang_idl::types::{Idl, IdlType, IdlTypeDef, IdlTypeDefTy};
+use anyhow::{anyhow, Result};
+
+pub mod anchor_accounts;
+pub mod openapi;
+pub mod proto;
+pub mod zod;
+
+/// Check a `--namespace` prefix is an identifier every target language accepts
+pub fn check_namespace(namespace: &str) -> Result<()> {
+    let mut chars = namespace.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow!(
+            "Namespace '{}' must start with a letter or underscore and contain only letters, digits and underscores",
+            namespace
+        ));
+    }
+    Ok(())
+}
+
+/// Find a type definition by name
+pub fn find_type<'a>(idl: &'a Idl, name: &str) -> Option<&'a IdlTypeDef> {
+    idl.types.iter().find(|def| def.name == name)
+}
+
+/// Follow `type X = Y` aliases until reaching a type that isn't an alias
+pub fn resolve_alias<'a>(idl: &'a Idl, mut ty: &'a IdlType) -> &'a IdlType {
+    // Bounded so a malformed, self-referencing alias can't loop forever
+    for _ in 0..idl.types.len() {
+        let IdlType::Defined { name, .. } = ty else {
+            break;
+        };
+        match find_type(idl, name) {
+            Some(IdlTypeDef {
+                ty: IdlTypeDefTy::Type { alias },
+                ..
+            }) => ty = alias,
+            _ => break,
+        }
+    }
+    ty
+}
+
+/// `initialize_pool` / `InitializePool` -> `InitializePool`
+pub fn to_pascal_case(name: &str) -> String {
+    name.split(|c: char| c == '_' || c == '-' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// `initialize_pool` / `InitializePool` -> `initializePool`
+pub fn to_camel_case(name: &str) -> String {
+    let pascal = to_pascal_case(name);
+    let mut chars = pascal.chars();
+    chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// `InitializePool` / `initializePool` -> `initialize_pool`
+pub fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c == '-' || c.is_whitespace() {
+            out.push('_');
+            prev_lower = false;
+        } else if c.is_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            prev_lower = false;
+        } else {
+            out.push(c);
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
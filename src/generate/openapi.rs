@@ -0,0 +1,385 @@
+use super::to_pascal_case;
+use anchor_lang_idl::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlInstructionAccount, IdlType, IdlTypeDefTy,
+};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+
+const SCHEMA_REF_PREFIX: &str = "#/components/schemas/";
+
+// Base58 public key, as accepted by `PublicKey` constructors
+const PUBKEY_PATTERN: &str = "^[1-9A-HJ-NP-Za-km-z]{32,44}$";
+
+/// Render the IDL as an OpenAPI 3.1 document with a `POST /instructions/<name>` operation per
+/// instruction and every defined type under `components/schemas`
+///
+/// The request body carries the instruction's `args` and its `accounts` as base58 public keys.
+/// A `namespace` prefixes schema names (PascalCased) and operation ids, so documents for
+/// several programs can be bundled without their components clashing.
+pub fn generate(idl: &Idl, namespace: Option<&str>) -> Result<String> {
+    let mut schemas = Map::new();
+    for def in &idl.types {
+        if !def.generics.is_empty() {
+            return Err(anyhow!(
+                "Type '{}' is generic, which has no OpenAPI schema",
+                def.name
+            ));
+        }
+        let mut schema = type_def_schema(idl, &def.ty)
+            .with_context(|| format!("Failed to map type '{}'", def.name))?;
+        describe(&mut schema, &def.docs);
+        schemas.insert(def.name.clone(), schema);
+    }
+
+    let mut paths = Map::new();
+    for instruction in &idl.instructions {
+        let args: Vec<(&str, &[String], &IdlType)> = instruction
+            .args
+            .iter()
+            .map(|arg| (arg.name.as_str(), arg.docs.as_slice(), &arg.ty))
+            .collect();
+        let args = fields_schema(idl, &args)
+            .with_context(|| format!("Failed to map instruction '{}'", instruction.name))?;
+        let accounts = accounts_schema(&crate::flatten_accounts(&instruction.accounts));
+
+        let mut operation = json!({
+            "operationId": instruction.name,
+            "requestBody": {
+                "required": true,
+                "content": {
+                    "application/json": {
+                        "schema": {
+                            "type": "object",
+                            "required": ["args", "accounts"],
+                            "properties": { "args": args, "accounts": accounts },
+                        }
+                    }
+                }
+            },
+            "responses": {
+                "200": { "description": "Instruction accepted" }
+            },
+        });
+        if let Some(summary) = instruction.docs.first() {
+            operation["summary"] = summary.clone().into();
+        }
+        paths.insert(
+            format!("/instructions/{}", instruction.name),
+            json!({ "post": operation }),
+        );
+    }
+
+    let mut info = json!({
+        "title": idl.metadata.name,
+        "version": idl.metadata.version,
+    });
+    describe(&mut info, &idl.docs);
+
+    let mut document = json!({
+        "openapi": "3.1.0",
+        "info": info,
+        "paths": paths,
+        "components": { "schemas": schemas },
+    });
+    if let Some(namespace) = namespace {
+        apply_namespace(&mut document, namespace);
+    }
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+// Renames every schema to `<Namespace><Name>` and every operation to `<namespace>_<name>`,
+// rewriting the `$ref`s that point at the schemas
+fn apply_namespace(document: &mut Value, namespace: &str) {
+    let schema_prefix = to_pascal_case(namespace);
+    if let Some(schemas) = document["components"]["schemas"].as_object_mut() {
+        *schemas = std::mem::take(schemas)
+            .into_iter()
+            .map(|(name, schema)| (format!("{}{}", schema_prefix, name), schema))
+            .collect();
+    }
+    if let Some(paths) = document["paths"].as_object_mut() {
+        for path in paths.values_mut() {
+            if let Some(id) = path["post"]["operationId"].as_str() {
+                path["post"]["operationId"] = format!("{}_{}", namespace, id).into();
+            }
+        }
+    }
+    prefix_refs(document, &schema_prefix);
+}
+
+fn prefix_refs(value: &mut Value, prefix: &str) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(target) if key == "$ref" => {
+                        if let Some(name) = target.strip_prefix(SCHEMA_REF_PREFIX) {
+                            *target = format!("{}{}{}", SCHEMA_REF_PREFIX, prefix, name);
+                        }
+                    }
+                    _ => prefix_refs(value, prefix),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                prefix_refs(item, prefix);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn type_def_schema(idl: &Idl, ty: &IdlTypeDefTy) -> Result<Value> {
+    match ty {
+        IdlTypeDefTy::Struct { fields } => defined_fields_schema(idl, fields.as_ref()),
+        IdlTypeDefTy::Enum { variants } => {
+            if variants.iter().all(|variant| variant.fields.is_none()) {
+                let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                return Ok(json!({ "type": "string", "enum": names }));
+            }
+            // Data-carrying enums use Anchor's JSON shape: `{ "<variant>": { ...fields } }`
+            let branches = variants
+                .iter()
+                .map(|variant| {
+                    let payload = defined_fields_schema(idl, variant.fields.as_ref())
+                        .with_context(|| format!("Variant '{}'", variant.name))?;
+                    Ok(json!({
+                        "type": "object",
+                        "required": [variant.name],
+                        "properties": { variant.name.clone(): payload },
+                        "additionalProperties": false,
+                    }))
+                })
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(json!({ "oneOf": branches }))
+        }
+        IdlTypeDefTy::Type { alias } => type_schema(idl, alias),
+    }
+}
+
+fn defined_fields_schema(idl: &Idl, fields: Option<&IdlDefinedFields>) -> Result<Value> {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => {
+            let fields: Vec<(&str, &[String], &IdlType)> = fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.docs.as_slice(), &field.ty))
+                .collect();
+            fields_schema(idl, &fields)
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            let items = types
+                .iter()
+                .map(|ty| type_schema(idl, ty))
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(json!({
+                "type": "array",
+                "prefixItems": items,
+                "minItems": types.len(),
+                "maxItems": types.len(),
+            }))
+        }
+        None => Ok(json!({ "type": "object", "additionalProperties": false })),
+    }
+}
+
+// An object schema with a property per field; `Option` fields may be omitted
+fn fields_schema(idl: &Idl, fields: &[(&str, &[String], &IdlType)]) -> Result<Value> {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, docs, ty) in fields {
+        let mut schema = type_schema(idl, ty).with_context(|| format!("Field '{}'", name))?;
+        describe(&mut schema, docs);
+        properties.insert(name.to_string(), schema);
+        if !matches!(ty, IdlType::Option(_)) {
+            required.push(*name);
+        }
+    }
+    Ok(json!({
+        "type": "object",
+        "required": required,
+        "properties": properties,
+        "additionalProperties": false,
+    }))
+}
+
+// Accounts with a fixed address or marked optional don't have to be supplied
+fn accounts_schema(accounts: &[&IdlInstructionAccount]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for account in accounts {
+        let mut schema = json!({ "type": "string", "pattern": PUBKEY_PATTERN });
+        if let Some(address) = &account.address {
+            schema["default"] = address.clone().into();
+        } else if !account.optional {
+            required.push(account.name.as_str());
+        }
+        describe(&mut schema, &account.docs);
+        properties.insert(account.name.clone(), schema);
+    }
+    json!({
+        "type": "object",
+        "required": required,
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+/// JSON Schema for an IDL type, referencing defined types under `components/schemas`
+///
+/// Integers wider than 32 bits exceed JavaScript's safe integer range, so they're decimal strings.
+pub fn type_schema(idl: &Idl, ty: &IdlType) -> Result<Value> {
+    let schema = match ty {
+        IdlType::Bool => json!({ "type": "boolean" }),
+        IdlType::U8 => int_schema(0, u8::MAX as i64),
+        IdlType::I8 => int_schema(i8::MIN as i64, i8::MAX as i64),
+        IdlType::U16 => int_schema(0, u16::MAX as i64),
+        IdlType::I16 => int_schema(i16::MIN as i64, i16::MAX as i64),
+        IdlType::U32 => int_schema(0, u32::MAX as i64),
+        IdlType::I32 => int_schema(i32::MIN as i64, i32::MAX as i64),
+        IdlType::U64 | IdlType::U128 | IdlType::U256 => {
+            json!({ "type": "string", "pattern": "^[0-9]+$" })
+        }
+        IdlType::I64 | IdlType::I128 | IdlType::I256 => {
+            json!({ "type": "string", "pattern": "^-?[0-9]+$" })
+        }
+        IdlType::F32 => json!({ "type": "number", "format": "float" }),
+        IdlType::F64 => json!({ "type": "number", "format": "double" }),
+        IdlType::String => json!({ "type": "string" }),
+        IdlType::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        IdlType::Pubkey => json!({ "type": "string", "pattern": PUBKEY_PATTERN }),
+        IdlType::Option(inner) => json!({ "oneOf": [type_schema(idl, inner)?, { "type": "null" }] }),
+        IdlType::Vec(inner) => json!({ "type": "array", "items": type_schema(idl, inner)? }),
+        IdlType::Array(inner, len) => {
+            let IdlArrayLen::Value(len) = len else {
+                return Err(anyhow!(
+                    "generic array length in {} has no OpenAPI schema",
+                    crate::format_type(ty)
+                ));
+            };
+            json!({
+                "type": "array",
+                "items": type_schema(idl, inner)?,
+                "minItems": len,
+                "maxItems": len,
+            })
+        }
+        IdlType::Defined { name, generics } => {
+            if !generics.is_empty() {
+                return Err(anyhow!(
+                    "generic type {} has no OpenAPI schema",
+                    crate::format_type(ty)
+                ));
+            }
+            if super::find_type(idl, name).is_none() {
+                return Err(anyhow!("type '{}' is not defined in the IDL", name));
+            }
+            json!({ "$ref": format!("{}{}", SCHEMA_REF_PREFIX, name) })
+        }
+        IdlType::Generic(name) => {
+            return Err(anyhow!(
+                "generic parameter '{}' has no OpenAPI schema",
+                name
+            ))
+        }
+        other => {
+            return Err(anyhow!(
+                "type {} has no OpenAPI schema",
+                crate::format_type(other)
+            ))
+        }
+    };
+    Ok(schema)
+}
+
+fn int_schema(min: i64, max: i64) -> Value {
+    json!({ "type": "integer", "minimum": min, "maximum": max })
+}
+
+fn describe(schema: &mut Value, docs: &[String]) {
+    if !docs.is_empty() {
+        schema["description"] = docs.join("\n").into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Idl {
+        serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "set_config",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [{ "name": "authority", "signer": true }],
+                "args": [{ "name": "config", "type": { "defined": { "name": "Config" } } }]
+            }],
+            "types": [
+                {
+                    "name": "Config",
+                    "type": { "kind": "struct", "fields": [
+                        { "name": "mode", "type": { "defined": { "name": "Mode" } } }
+                    ] }
+                },
+                {
+                    "name": "Mode",
+                    "type": { "kind": "enum", "variants": [{ "name": "Open" }, { "name": "Closed" }] }
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    // Every `$ref` in the document
+    fn refs(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match value {
+                        Value::String(target) if key == "$ref" => found.push(target.clone()),
+                        _ => refs(value, found),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| refs(item, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn namespace_prefixes_schemas_operations_and_refs() {
+        let document: Value =
+            serde_json::from_str(&generate(&sample(), Some("my_dex")).unwrap()).unwrap();
+
+        let schemas: Vec<&String> = document["components"]["schemas"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(schemas, ["MyDexConfig", "MyDexMode"]);
+        assert_eq!(
+            document["paths"]["/instructions/set_config"]["post"]["operationId"],
+            "my_dex_set_config"
+        );
+
+        let mut found = Vec::new();
+        refs(&document, &mut found);
+        found.sort();
+        assert_eq!(
+            found,
+            ["#/components/schemas/MyDexConfig", "#/components/schemas/MyDexMode"]
+        );
+    }
+
+    #[test]
+    fn without_a_namespace_names_are_left_alone() {
+        let document: Value = serde_json::from_str(&generate(&sample(), None).unwrap()).unwrap();
+        assert!(document["components"]["schemas"]["Config"].is_object());
+        assert_eq!(
+            document["paths"]["/instructions/set_config"]["post"]["operationId"],
+            "set_config"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use super::{resolve_alias, to_pascal_case, to_snake_case};
+use anchor_lang_idl::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlEnumVariant, IdlType, IdlTypeDefTy,
+};
+use anyhow::{anyhow, Context, Result};
+use std::fmt::Write;
+
+// A message field: name, docs and type
+type Field<'a> = (String, &'a [String], &'a IdlType);
+
+/// Render the IDL as a proto3 schema: a message per struct, an enum (or `oneof` message for
+/// data-carrying enums) per enum, and a `<Instruction>Request` message per instruction's args
+///
+/// Field numbers follow field order, so they're stable as long as fields are only appended.
+/// A `namespace` becomes the outer package segment (`<namespace>.<program>`).
+pub fn generate(idl: &Idl, namespace: Option<&str>) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by dls-anchor from {} v{}. Do not edit.",
+        idl.metadata.name, idl.metadata.version
+    )?;
+    writeln!(out, "syntax = \"proto3\";")?;
+    writeln!(out)?;
+    let package = package_name(&idl.metadata.name);
+    match namespace {
+        Some(namespace) => writeln!(out, "package {}.{};", namespace, package)?,
+        None => writeln!(out, "package {};", package)?,
+    }
+
+    for def in &idl.types {
+        if !def.generics.is_empty() {
+            return Err(anyhow!(
+                "Type '{}' is generic, which proto3 can't represent",
+                def.name
+            ));
+        }
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                writeln!(out)?;
+                write_message(&mut out, idl, &def.name, &def.docs, &fields_of(fields.as_ref()), 0)
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                writeln!(out)?;
+                write_enum(&mut out, idl, &def.name, &def.docs, variants)
+            }
+            // Aliases are inlined wherever they're used
+            IdlTypeDefTy::Type { .. } => Ok(()),
+        }
+        .with_context(|| format!("Failed to map type '{}'", def.name))?;
+    }
+
+    for instruction in &idl.instructions {
+        let fields: Vec<Field> = instruction
+            .args
+            .iter()
+            .map(|arg| (arg.name.clone(), arg.docs.as_slice(), &arg.ty))
+            .collect();
+        let name = format!("{}Request", to_pascal_case(&instruction.name));
+        writeln!(out)?;
+        write_message(&mut out, idl, &name, &instruction.docs, &fields, 0)
+            .with_context(|| format!("Failed to map instruction '{}'", instruction.name))?;
+    }
+
+    Ok(out)
+}
+
+fn package_name(program_name: &str) -> String {
+    to_snake_case(program_name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Tuple fields have no names, so they're numbered
+fn fields_of(fields: Option<&IdlDefinedFields>) -> Vec<Field<'_>> {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => fields
+            .iter()
+            .map(|field| (field.name.clone(), field.docs.as_slice(), &field.ty))
+            .collect(),
+        Some(IdlDefinedFields::Tuple(types)) => types
+            .iter()
+            .enumerate()
+            .map(|(idx, ty)| (format!("field_{}", idx), &[][..], ty))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn write_message(
+    out: &mut String,
+    idl: &Idl,
+    name: &str,
+    docs: &[String],
+    fields: &[Field],
+    depth: usize,
+) -> Result<()> {
+    let pad = "  ".repeat(depth);
+    write_docs(out, docs, &pad)?;
+    writeln!(out, "{}message {} {{", pad, name)?;
+    for (idx, (field_name, field_docs, ty)) in fields.iter().enumerate() {
+        let field_ty =
+            field_type(idl, ty).with_context(|| format!("Field '{}'", field_name))?;
+        write_docs(out, field_docs, &format!("{}  ", pad))?;
+        writeln!(
+            out,
+            "{}  {} {} = {};",
+            pad,
+            field_ty,
+            to_snake_case(field_name),
+            idx + 1
+        )?;
+    }
+    writeln!(out, "{}}}", pad)?;
+    Ok(())
+}
+
+// Unit-only enums map to proto enums; enums with data become a `oneof` over nested messages
+fn write_enum(
+    out: &mut String,
+    idl: &Idl,
+    name: &str,
+    docs: &[String],
+    variants: &[IdlEnumVariant],
+) -> Result<()> {
+    write_docs(out, docs, "")?;
+
+    if variants.iter().all(|variant| variant.fields.is_none()) {
+        // proto3 enum values share the package scope, so they're prefixed with the enum name
+        let prefix = to_snake_case(name).to_uppercase();
+        writeln!(out, "enum {} {{", name)?;
+        for (idx, variant) in variants.iter().enumerate() {
+            writeln!(
+                out,
+                "  {}_{} = {};",
+                prefix,
+                to_snake_case(&variant.name).to_uppercase(),
+                idx
+            )?;
+        }
+        writeln!(out, "}}")?;
+        return Ok(());
+    }
+
+    writeln!(out, "message {} {{", name)?;
+    for variant in variants {
+        write_message(
+            out,
+            idl,
+            &to_pascal_case(&variant.name),
+            &[],
+            &fields_of(variant.fields.as_ref()),
+            1,
+        )
+        .with_context(|| format!("Variant '{}'", variant.name))?;
+    }
+    writeln!(out, "  oneof variant {{")?;
+    for (idx, variant) in variants.iter().enumerate() {
+        writeln!(
+            out,
+            "    {} {} = {};",
+            to_pascal_case(&variant.name),
+            to_snake_case(&variant.name),
+            idx + 1
+        )?;
+    }
+    writeln!(out, "  }}")?;
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+fn write_docs(out: &mut String, docs: &[String], pad: &str) -> Result<()> {
+    for doc in docs {
+        writeln!(out, "{}// {}", pad, doc)?;
+    }
+    Ok(())
+}
+
+// A field's type including its `optional`/`repeated` label
+fn field_type(idl: &Idl, ty: &IdlType) -> Result<String> {
+    let ty = resolve_alias(idl, ty);
+    match ty {
+        IdlType::Option(inner) => Ok(format!("optional {}", scalar_type(idl, inner)?)),
+        IdlType::Vec(inner) | IdlType::Array(inner, _) if !is_byte(idl, inner) => {
+            array_len_is_concrete(ty)?;
+            Ok(format!("repeated {}", scalar_type(idl, inner)?))
+        }
+        other => scalar_type(idl, other),
+    }
+}
+
+// A type that can appear after a label; proto3 has no nested containers
+fn scalar_type(idl: &Idl, ty: &IdlType) -> Result<String> {
+    let ty = resolve_alias(idl, ty);
+    let name = match ty {
+        IdlType::Bool => "bool",
+        IdlType::U8 | IdlType::U16 | IdlType::U32 => "uint32",
+        IdlType::I8 | IdlType::I16 | IdlType::I32 => "int32",
+        IdlType::U64 => "uint64",
+        IdlType::I64 => "int64",
+        IdlType::F32 => "float",
+        IdlType::F64 => "double",
+        // No native 128/256-bit integers; carried as little-endian bytes
+        IdlType::U128 | IdlType::I128 | IdlType::U256 | IdlType::I256 => "bytes",
+        IdlType::Bytes | IdlType::Pubkey => "bytes",
+        IdlType::String => "string",
+        IdlType::Vec(inner) | IdlType::Array(inner, _) if is_byte(idl, inner) => {
+            array_len_is_concrete(ty)?;
+            "bytes"
+        }
+        IdlType::Defined { name, generics } => {
+            if !generics.is_empty() {
+                return Err(anyhow!(
+                    "generic type {} can't be represented in proto3",
+                    crate::format_type(ty)
+                ));
+            }
+            return Ok(name.clone());
+        }
+        IdlType::Generic(name) => {
+            return Err(anyhow!(
+                "generic parameter '{}' can't be represented in proto3",
+                name
+            ))
+        }
+        IdlType::Option(_) | IdlType::Vec(_) | IdlType::Array(_, _) => {
+            return Err(anyhow!(
+                "nested container {} needs a wrapper message in proto3",
+                crate::format_type(ty)
+            ))
+        }
+        other => {
+            return Err(anyhow!(
+                "type {} has no proto3 equivalent",
+                crate::format_type(other)
+            ))
+        }
+    };
+    Ok(name.into())
+}
+
+fn is_byte(idl: &Idl, ty: &IdlType) -> bool {
+    matches!(resolve_alias(idl, ty), IdlType::U8)
+}
+
+fn array_len_is_concrete(ty: &IdlType) -> Result<()> {
+    match ty {
+        IdlType::Array(_, IdlArrayLen::Generic(len)) => Err(anyhow!(
+            "array length generic '{}' can't be represented in proto3",
+            len
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespace_becomes_the_outer_package() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "my-dex", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        }))
+        .unwrap();
+
+        let package = |namespace| {
+            generate(&idl, namespace)
+                .unwrap()
+                .lines()
+                .find(|line| line.starts_with("package "))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(package(Some("acme")), "package acme.my_dex;");
+        assert_eq!(package(None), "package my_dex;");
+    }
+}
@@ -0,0 +1,368 @@
+use super::{find_type, to_pascal_case};
+use anchor_lang_idl::types::{Idl, IdlArrayLen, IdlDefinedFields, IdlField, IdlType, IdlTypeDefTy};
+use anyhow::{anyhow, Context, Result};
+use std::fmt::Write;
+
+/// Render the IDL as TypeScript `zod` schemas: a `<Type>Schema` per defined type and an
+/// `<Instruction>ArgsSchema` per instruction, each with its inferred type alongside
+///
+/// The shapes are the IDL's own, not what Anchor's TypeScript client decodes to (it camelCases
+/// keys, uses `BN` and wraps unit variants as `{ variant: {} }`): keys are IDL names, 64-bit and
+/// wider integers are `bigint`s, unit-only enums are their variant names and other enums are
+/// `{ <Variant>: ... }`. Public keys are `PublicKey`s (or base58 strings that parse as one) and
+/// `Option`s are `null` when absent.
+/// Types are emitted after the types they use, with `z.lazy` only where types refer to each other
+/// in a cycle. A `namespace` prefixes every exported name (PascalCased).
+pub fn generate(idl: &Idl, namespace: Option<&str>) -> Result<String> {
+    let prefix = namespace.map(to_pascal_case).unwrap_or_default();
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by dls-anchor from {} v{}. Do not edit.",
+        idl.metadata.name, idl.metadata.version
+    )?;
+    writeln!(out, "import {{ z }} from \"zod\";")?;
+    writeln!(out, "import {{ PublicKey }} from \"@solana/web3.js\";")?;
+    writeln!(out)?;
+    writeln!(out, "const publicKey = z.union([")?;
+    writeln!(out, "  z.instanceof(PublicKey),")?;
+    writeln!(out, "  z.string().refine(")?;
+    writeln!(out, "    (value) => {{")?;
+    writeln!(out, "      try {{")?;
+    writeln!(out, "        new PublicKey(value);")?;
+    writeln!(out, "        return true;")?;
+    writeln!(out, "      }} catch {{")?;
+    writeln!(out, "        return false;")?;
+    writeln!(out, "      }}")?;
+    writeln!(out, "    }},")?;
+    writeln!(out, "    {{ message: \"Invalid public key\" }},")?;
+    writeln!(out, "  ),")?;
+    writeln!(out, "]);")?;
+
+    let mut emitter = Emitter {
+        idl,
+        prefix: &prefix,
+        emitted: Vec::new(),
+        in_progress: Vec::new(),
+    };
+    for def in &idl.types {
+        emitter.emit_type(&mut out, &def.name)?;
+    }
+
+    for instruction in &idl.instructions {
+        let name = format!("{}{}Args", prefix, to_pascal_case(&instruction.name));
+        let schema = emitter
+            .object_expr(&instruction.args)
+            .with_context(|| format!("Failed to map instruction '{}'", instruction.name))?;
+        writeln!(out)?;
+        write_docs(&mut out, &instruction.docs)?;
+        writeln!(out, "export const {}Schema = {};", name, schema)?;
+        writeln!(out, "export type {} = z.infer<typeof {}Schema>;", name, name)?;
+    }
+    Ok(out)
+}
+
+struct Emitter<'a> {
+    idl: &'a Idl,
+    prefix: &'a str,
+    // Types already written out, and those being written (further up the dependency chain)
+    emitted: Vec<String>,
+    in_progress: Vec<String>,
+}
+
+impl Emitter<'_> {
+    // Writes the schema for a type, after the types it depends on
+    fn emit_type(&mut self, out: &mut String, name: &str) -> Result<()> {
+        if self.emitted.iter().any(|done| done == name) || self.in_progress.iter().any(|n| n == name)
+        {
+            return Ok(());
+        }
+        let def = find_type(self.idl, name)
+            .ok_or_else(|| anyhow!("type '{}' is not defined in the IDL", name))?;
+        if !def.generics.is_empty() {
+            return Err(anyhow!(
+                "Type '{}' is generic, which has no zod schema",
+                def.name
+            ));
+        }
+
+        self.in_progress.push(name.to_string());
+        let mut dependencies = Vec::new();
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => fields_dependencies(fields.as_ref(), &mut dependencies),
+            IdlTypeDefTy::Enum { variants } => {
+                for variant in variants {
+                    fields_dependencies(variant.fields.as_ref(), &mut dependencies);
+                }
+            }
+            IdlTypeDefTy::Type { alias } => crate::fix::referenced_types(alias, &mut dependencies),
+        }
+        for dependency in &dependencies {
+            self.emit_type(out, dependency)?;
+        }
+
+        let schema = match &def.ty {
+            IdlTypeDefTy::Struct { fields } => self.fields_expr(fields.as_ref()),
+            IdlTypeDefTy::Enum { variants } => {
+                if variants.iter().all(|variant| variant.fields.is_none()) {
+                    let names: Vec<String> =
+                        variants.iter().map(|v| format!("{:?}", v.name)).collect();
+                    Ok(format!("z.enum([{}])", names.join(", ")))
+                } else {
+                    // Data-carrying enums are tagged by variant: `{ <Variant>: { ...fields } }`
+                    let branches = variants
+                        .iter()
+                        .map(|variant| {
+                            let payload = self
+                                .fields_expr(variant.fields.as_ref())
+                                .with_context(|| format!("Variant '{}'", variant.name))?;
+                            Ok(format!("z.object({{ {}: {} }}).strict()", variant.name, payload))
+                        })
+                        .collect::<Result<Vec<String>>>()?;
+                    match branches.as_slice() {
+                        [branch] => Ok(branch.clone()),
+                        _ => {
+                            let branches: Vec<String> =
+                                branches.iter().map(|branch| indent(branch)).collect();
+                            Ok(format!("z.union([\n  {},\n])", branches.join(",\n  ")))
+                        }
+                    }
+                }
+            }
+            IdlTypeDefTy::Type { alias } => self.type_expr(alias),
+        }
+        .with_context(|| format!("Failed to map type '{}'", def.name))?;
+        self.in_progress.pop();
+        self.emitted.push(name.to_string());
+
+        let export = format!("{}{}", self.prefix, def.name);
+        writeln!(out)?;
+        write_docs(out, &def.docs)?;
+        writeln!(out, "export const {}Schema = {};", export, schema)?;
+        writeln!(out, "export type {} = z.infer<typeof {}Schema>;", export, export)?;
+        Ok(())
+    }
+
+    fn fields_expr(&self, fields: Option<&IdlDefinedFields>) -> Result<String> {
+        match fields {
+            Some(IdlDefinedFields::Named(fields)) => self.object_expr(fields),
+            Some(IdlDefinedFields::Tuple(types)) => {
+                let items = types
+                    .iter()
+                    .map(|ty| self.type_expr(ty))
+                    .collect::<Result<Vec<String>>>()?;
+                Ok(format!("z.tuple([{}])", items.join(", ")))
+            }
+            None => Ok("z.object({}).strict()".to_string()),
+        }
+    }
+
+    // An object with a key per field; `Option` fields may also be left out
+    fn object_expr(&self, fields: &[IdlField]) -> Result<String> {
+        if fields.is_empty() {
+            return Ok("z.object({}).strict()".to_string());
+        }
+        let mut out = String::from("z.object({\n");
+        for field in fields {
+            let schema = match &field.ty {
+                IdlType::Option(inner) => self
+                    .type_expr(inner)
+                    .map(|inner| format!("{}.nullish()", inner)),
+                ty => self.type_expr(ty),
+            }
+            .with_context(|| format!("Field '{}'", field.name))?;
+            for doc in &field.docs {
+                writeln!(out, "  /** {} */", doc)?;
+            }
+            writeln!(out, "  {}: {},", field.name, indent(&schema))?;
+        }
+        out.push_str("})");
+        Ok(out)
+    }
+
+    /// The zod expression validating a value of an IDL type
+    fn type_expr(&self, ty: &IdlType) -> Result<String> {
+        let expr = match ty {
+            IdlType::Bool => "z.boolean()".to_string(),
+            IdlType::U8 => int_expr(0, u8::MAX as i64),
+            IdlType::I8 => int_expr(i8::MIN as i64, i8::MAX as i64),
+            IdlType::U16 => int_expr(0, u16::MAX as i64),
+            IdlType::I16 => int_expr(i16::MIN as i64, i16::MAX as i64),
+            IdlType::U32 => int_expr(0, u32::MAX as i64),
+            IdlType::I32 => int_expr(i32::MIN as i64, i32::MAX as i64),
+            IdlType::U64 => unsigned_bigint_expr(64),
+            IdlType::U128 => unsigned_bigint_expr(128),
+            IdlType::U256 => unsigned_bigint_expr(256),
+            IdlType::I64 => signed_bigint_expr(64),
+            IdlType::I128 => signed_bigint_expr(128),
+            IdlType::I256 => signed_bigint_expr(256),
+            IdlType::F32 | IdlType::F64 => "z.number()".to_string(),
+            IdlType::String => "z.string()".to_string(),
+            IdlType::Bytes => "z.instanceof(Uint8Array)".to_string(),
+            IdlType::Pubkey => "publicKey".to_string(),
+            IdlType::Option(inner) => format!("{}.nullable()", self.type_expr(inner)?),
+            IdlType::Vec(inner) => format!("z.array({})", self.type_expr(inner)?),
+            IdlType::Array(inner, len) => {
+                let IdlArrayLen::Value(len) = len else {
+                    return Err(anyhow!(
+                        "generic array length in {} has no zod schema",
+                        crate::format_type(ty)
+                    ));
+                };
+                format!("z.array({}).length({})", self.type_expr(inner)?, len)
+            }
+            IdlType::Defined { name, generics } => {
+                if !generics.is_empty() {
+                    return Err(anyhow!(
+                        "generic type {} has no zod schema",
+                        crate::format_type(ty)
+                    ));
+                }
+                if find_type(self.idl, name).is_none() {
+                    return Err(anyhow!("type '{}' is not defined in the IDL", name));
+                }
+                let schema = format!("{}{}Schema", self.prefix, name);
+                // A type that isn't written yet is part of a cycle with the one being written
+                if self.emitted.iter().any(|done| done == name) {
+                    schema
+                } else {
+                    format!("z.lazy((): z.ZodTypeAny => {})", schema)
+                }
+            }
+            IdlType::Generic(name) => {
+                return Err(anyhow!("generic parameter '{}' has no zod schema", name))
+            }
+            other => {
+                return Err(anyhow!(
+                    "type {} has no zod schema",
+                    crate::format_type(other)
+                ))
+            }
+        };
+        Ok(expr)
+    }
+}
+
+fn fields_dependencies(fields: Option<&IdlDefinedFields>, names: &mut Vec<String>) {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => {
+            for field in fields {
+                crate::fix::referenced_types(&field.ty, names);
+            }
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            for ty in types {
+                crate::fix::referenced_types(ty, names);
+            }
+        }
+        None => {}
+    }
+}
+
+// Indents every line after the first, for an expression nested one level deeper
+fn indent(expr: &str) -> String {
+    expr.replace('\n', "\n  ")
+}
+
+fn int_expr(min: i64, max: i64) -> String {
+    format!("z.number().int().min({}).max({})", min, max)
+}
+
+fn unsigned_bigint_expr(bits: u32) -> String {
+    format!("z.bigint().min(0n).max(2n ** {}n - 1n)", bits)
+}
+
+fn signed_bigint_expr(bits: u32) -> String {
+    format!(
+        "z.bigint().min(-(2n ** {}n)).max(2n ** {}n - 1n)",
+        bits - 1,
+        bits - 1
+    )
+}
+
+fn write_docs(out: &mut String, docs: &[String]) -> Result<()> {
+    if docs.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "/**")?;
+    for doc in docs {
+        writeln!(out, " * {}", doc)?;
+    }
+    writeln!(out, " */")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Idl {
+        serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "set_config",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [],
+                "args": [
+                    { "name": "config", "type": { "defined": { "name": "Config" } } },
+                    { "name": "limit", "type": { "option": "u64" } }
+                ]
+            }],
+            "types": [
+                {
+                    "name": "Config",
+                    "type": { "kind": "struct", "fields": [
+                        { "name": "seed", "type": { "array": ["u8", 32] } },
+                        { "name": "authority", "type": "pubkey" },
+                        { "name": "mode", "type": { "defined": { "name": "Mode" } } }
+                    ] }
+                },
+                {
+                    "name": "Mode",
+                    "type": { "kind": "enum", "variants": [{ "name": "Open" }, { "name": "Closed" }] }
+                },
+                {
+                    "name": "Action",
+                    "type": { "kind": "enum", "variants": [
+                        { "name": "Stop" },
+                        { "name": "Move", "fields": [{ "name": "by", "type": "i64" }] }
+                    ] }
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fixed_array_field_checks_its_length() {
+        let out = generate(&sample(), None).unwrap();
+        assert!(out.contains("  seed: z.array(z.number().int().min(0).max(255)).length(32),\n"));
+        assert!(out.contains("  authority: publicKey,\n"));
+    }
+
+    #[test]
+    fn types_come_before_their_users() {
+        let out = generate(&sample(), None).unwrap();
+        let mode = out.find("export const ModeSchema").unwrap();
+        let config = out.find("export const ConfigSchema").unwrap();
+        assert!(mode < config);
+        assert!(out.contains("  mode: ModeSchema,\n"));
+    }
+
+    #[test]
+    fn enums_map_by_variant_shape() {
+        let out = generate(&sample(), None).unwrap();
+        assert!(out.contains("export const ModeSchema = z.enum([\"Open\", \"Closed\"]);"));
+        assert!(out.contains("z.object({ Stop: z.object({}).strict() }).strict()"));
+        assert!(out.contains("max(2n ** 63n - 1n)"));
+    }
+
+    #[test]
+    fn instruction_args_allow_a_missing_option() {
+        let out = generate(&sample(), Some("acme")).unwrap();
+        assert!(out.contains("export const AcmeSetConfigArgsSchema = z.object({"));
+        assert!(out.contains("  limit: z.bigint().min(0n).max(2n ** 64n - 1n).nullish(),\n"));
+    }
+}
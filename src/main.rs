@@ -1,8 +1,25 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use std::fs;
-use std::path::PathBuf;
-use tracing::{debug, info};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::{debug, info, warn};
+use tracing_subscriber::util::SubscriberInitExt;
+
+mod codama;
+mod diagnostics;
+mod diff;
+mod elf;
+mod fix;
+mod generate;
+mod pda;
+mod provenance;
+mod rpc;
+mod schema;
+mod split;
+mod stats;
+
+use diagnostics::{Diagnostics, Severity};
 
 /// CLI tool for generating Anchor IDLs without the full Anchor CLI
 #[derive(Parser)]
@@ -11,10 +28,570 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Refuse input files larger than this many bytes
+    #[arg(long, default_value_t = DEFAULT_MAX_INPUT_BYTES, global = true)]
+    max_input_bytes: u64,
+
+    /// Refuse IDLs with more instructions than this
+    #[arg(long, default_value_t = DEFAULT_MAX_INSTRUCTIONS, global = true)]
+    max_instructions: usize,
+
+    /// Refuse IDLs with more types than this
+    #[arg(long, default_value_t = DEFAULT_MAX_TYPES, global = true)]
+    max_types: usize,
+
+    /// Show experimental and developer commands in the help
+    #[arg(long)]
+    experimental: bool,
+
+    /// Cache parsed IDLs here, keyed by content hash, so repeated calls on an unchanged file skip
+    /// conversion
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Write build and convert output in place instead of through a temporary file and a rename,
+    /// for filesystems where renaming over a file doesn't work
+    #[arg(long, global = true)]
+    no_atomic: bool,
+
+    /// Treat input IDLs as this format instead of inferring it from their content, failing if
+    /// they don't match (useful for files without an extension)
+    #[arg(long, value_enum, global = true)]
+    input_format: Option<InputFormat>,
+
+    /// Log output format; json emits one structured event per line
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+const DEFAULT_MAX_INPUT_BYTES: u64 = 50 * 1024 * 1024;
+
+// Set once from `--max-input-bytes` before any command runs
+static MAX_INPUT_BYTES: OnceLock<u64> = OnceLock::new();
+
+// Far beyond any real program, which has at most a few hundred of each
+const DEFAULT_MAX_INSTRUCTIONS: usize = 10_000;
+const DEFAULT_MAX_TYPES: usize = 50_000;
+
+// Set once from `--max-instructions` and `--max-types` before any command runs
+static MAX_INSTRUCTIONS: OnceLock<usize> = OnceLock::new();
+static MAX_TYPES: OnceLock<usize> = OnceLock::new();
+
+// Set once from `--cache-dir` before any command runs
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Set once from `--no-atomic` before any command runs
+static NO_ATOMIC: OnceLock<bool> = OnceLock::new();
+
+// Set once from `--input-format` before any command runs
+static INPUT_FORMAT: OnceLock<InputFormat> = OnceLock::new();
+
+// Checks the input against a forced `--input-format`; `convert_idl` tells the two formats apart
+// by `metadata.spec`, so that's what decides which one a file is
+fn check_input_format(value: &serde_json::Value, format: InputFormat) -> Result<()> {
+    let flag = input_format_flag(format);
+    if !value.is_object() {
+        return Err(anyhow::anyhow!(
+            "Input is not a JSON object (--input-format {})",
+            flag
+        ));
+    }
+    let current = value.pointer("/metadata/spec").is_some();
+    match format {
+        InputFormat::Json if !current => Err(anyhow::anyhow!(
+            "Input has no metadata.spec, so it isn't a current-spec IDL (--input-format json); \
+             use --input-format legacy-json for a legacy IDL"
+        )),
+        InputFormat::LegacyJson if current => Err(anyhow::anyhow!(
+            "Input has metadata.spec, so it isn't a legacy IDL (--input-format legacy-json); \
+             use --input-format json"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn input_format_flag(format: InputFormat) -> &'static str {
+    match format {
+        InputFormat::Json => "json",
+        InputFormat::LegacyJson => "legacy-json",
+    }
+}
+
+// Counts sections in the raw JSON so a corrupted or adversarial IDL is refused before conversion.
+// Input that isn't a JSON object is left for the conversion to report.
+fn check_section_limits(value: &serde_json::Value) -> Result<()> {
+    let limits = [
+        (
+            "instructions",
+            "--max-instructions",
+            MAX_INSTRUCTIONS.get().copied().unwrap_or(DEFAULT_MAX_INSTRUCTIONS),
+        ),
+        ("types", "--max-types", MAX_TYPES.get().copied().unwrap_or(DEFAULT_MAX_TYPES)),
+    ];
+    for (section, flag, max) in limits {
+        let count = value[section].as_array().map_or(0, Vec::len);
+        if count > max {
+            return Err(anyhow::anyhow!(
+                "IDL has {} {}, over the {} limit of {}",
+                count,
+                section,
+                flag,
+                max
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Parses IDL bytes with `convert_idl`, going through the `--cache-dir` parse cache when one is set.
+// Entries are keyed by a hash of the tool version and the content, so an edited file (or a new
+// release of the converter) misses and is parsed again; cache failures only ever cost a reparse.
+fn parse_idl(idl_bytes: &[u8]) -> Result<anchor_lang_idl::types::Idl> {
+    parse_idl_json(idl_bytes).map(|(_, idl)| idl)
+}
+
+// `parse_idl`, also handing back the input JSON for checks that need what conversion drops
+fn parse_idl_json(
+    idl_bytes: &[u8],
+) -> Result<(serde_json::Value, anchor_lang_idl::types::Idl)> {
+    let value = input_json(serde_json::from_slice(idl_bytes))?;
+    let idl = idl_from_value(&value, idl_bytes)?;
+    Ok((value, idl))
+}
+
+// `parse_idl` after normalizing primitive names, handing back the normalized JSON and how many
+// names changed. The bytes are only re-serialized when a name changed, for the legacy converter.
+fn parse_idl_normalized(
+    idl_bytes: &[u8],
+) -> Result<(serde_json::Value, usize, anchor_lang_idl::types::Idl)> {
+    let mut value = input_json(serde_json::from_slice(idl_bytes))?;
+    let normalized = normalize_primitives(&mut value);
+    let idl = if normalized > 0 {
+        idl_from_value(&value, &serde_json::to_vec(&value)?)?
+    } else {
+        idl_from_value(&value, idl_bytes)?
+    };
+    Ok((value, normalized, idl))
+}
+
+// Applies --input-format and the section limits to the input as parsed once by the caller; the
+// checks and a current-spec conversion then all work from the returned value
+fn input_json(parsed: serde_json::Result<serde_json::Value>) -> Result<serde_json::Value> {
+    let value = match INPUT_FORMAT.get() {
+        Some(format) => {
+            let value = parsed.with_context(|| {
+                format!(
+                    "Input is not valid JSON (--input-format {})",
+                    input_format_flag(*format)
+                )
+            })?;
+            check_input_format(&value, *format)?;
+            value
+        }
+        None => parsed?,
+    };
+    check_section_limits(&value)?;
+    Ok(value)
+}
+
+// Converts the input JSON (with `idl_bytes` its text), through the parse cache when one is set
+fn idl_from_value(
+    value: &serde_json::Value,
+    idl_bytes: &[u8],
+) -> Result<anchor_lang_idl::types::Idl> {
+    match CACHE_DIR.get() {
+        Some(cache_dir) => convert_cached(value, idl_bytes, cache_dir),
+        None => convert_value(value, idl_bytes),
+    }
+}
+
+// `convert_value` behind the parse cache in `cache_dir`
+fn convert_cached(
+    value: &serde_json::Value,
+    idl_bytes: &[u8],
+    cache_dir: &Path,
+) -> Result<anchor_lang_idl::types::Idl> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update([0]);
+    hasher.update(idl_bytes);
+    let key: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    let entry = cache_dir.join(format!("{}.json", key));
+
+    if let Ok(cached) = fs::read(&entry) {
+        match serde_json::from_slice(&cached) {
+            Ok(idl) => {
+                debug!("Parse cache hit: {:?}", entry);
+                return Ok(idl);
+            }
+            Err(e) => debug!("Ignoring unreadable cache entry {:?}: {}", entry, e),
+        }
+    }
+    debug!("Parse cache miss: {:?}", entry);
+
+    let idl = convert_value(value, idl_bytes)?;
+    let stored = fs::create_dir_all(cache_dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| Ok(serde_json::to_vec(&idl)?))
+        .and_then(|json| Ok(write_atomic(&entry, json)?));
+    if let Err(e) = stored {
+        debug!("Failed to write parse cache entry {:?}: {}", entry, e);
+    }
+    Ok(idl)
+}
+
+// Does what `convert_idl` does without parsing the bytes again for a current-spec IDL. The legacy
+// converter is private to `anchor_lang_idl` and only takes bytes, so a legacy IDL goes through it.
+fn convert_value(
+    value: &serde_json::Value,
+    idl_bytes: &[u8],
+) -> Result<anchor_lang_idl::types::Idl> {
+    use serde::Deserialize;
+
+    match value.pointer("/metadata/spec").and_then(|spec| spec.as_str()) {
+        Some("0.1.0") => Ok(anchor_lang_idl::types::Idl::deserialize(value)?),
+        _ => anchor_lang_idl::convert::convert_idl(idl_bytes),
+    }
+}
+
+// Writes under a temporary name in the same directory and renames it over `path`, so a reader (or
+// an interrupted run) only ever sees the old file or the complete new one, never half a file. The
+// data is synced before the rename so a crash can't leave the new name pointing at empty blocks,
+// and an existing file's permissions carry over to its replacement.
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(format!(".{}.tmp", std::process::id()));
+    let partial = PathBuf::from(partial);
+    let written = fs::File::create(&partial)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|()| match fs::metadata(path) {
+            Ok(existing) => fs::set_permissions(&partial, existing.permissions()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        })
+        .and_then(|()| fs::rename(&partial, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+// Writes a build or convert output file, atomically unless `--no-atomic` was given
+fn write_output(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    if NO_ATOMIC.get().copied().unwrap_or(false) {
+        fs::write(path, contents)
+    } else {
+        write_atomic(path, contents)
+    }
+}
+
+// Reads an input file, checking its size first so a mistaken path to a huge file fails fast
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    read_input_limited(
+        path,
+        MAX_INPUT_BYTES.get().copied().unwrap_or(DEFAULT_MAX_INPUT_BYTES),
+    )
+}
+
+fn read_input_limited(path: &Path, max: u64) -> Result<Vec<u8>> {
+    let size = fs::metadata(path)?.len();
+    if size > max {
+        return Err(anyhow::anyhow!(
+            "file is {} bytes, over the --max-input-bytes limit of {}",
+            size,
+            max
+        ));
+    }
+    Ok(fs::read(path)?)
+}
+
+// Fills in an `--output-template` such as `{name}-{version}.json`. `{stem}` is the input file's
+// stem (the program directory for `build`) and `{date}` today's UTC date as YYYY-MM-DD.
+fn render_output_template(
+    template: &str,
+    idl: &anchor_lang_idl::types::Idl,
+    stem: &str,
+) -> Result<PathBuf> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow::anyhow!("Unclosed '{{' in output template '{}'", template))?;
+        let value = match &rest[start + 1..end] {
+            "name" => idl.metadata.name.clone(),
+            "version" => idl.metadata.version.clone(),
+            "stem" => stem.to_string(),
+            "date" => utc_date(),
+            other => {
+                return Err(anyhow::anyhow!(
+                    "Unknown placeholder {{{}}} in output template (expected {{name}}, {{version}}, {{stem}} or {{date}})",
+                    other
+                ))
+            }
+        };
+        // A value mustn't introduce directories the template didn't ask for
+        if value.is_empty() || value.contains(['/', '\\', '\0']) || value == "." || value == ".." {
+            return Err(anyhow::anyhow!(
+                "Output template placeholder {} expands to '{}', which isn't usable in a file name",
+                &rest[start..=end],
+                value
+            ));
+        }
+        rendered.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+
+    let path = PathBuf::from(&rendered);
+    if rendered.contains('\0') || path.file_name().is_none() || rendered.ends_with(['/', '\\']) {
+        return Err(anyhow::anyhow!(
+            "Output template '{}' doesn't produce a file name (got '{}')",
+            template,
+            rendered
+        ));
+    }
+    Ok(path)
+}
+
+// Today's UTC date as YYYY-MM-DD, converted from days since the Unix epoch
+fn utc_date() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    // Civil-from-days over 400-year eras, with years starting in March
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    /// An IDL in the current spec, with `address` and `metadata.spec`
+    Json,
+    /// A pre-0.30 IDL with top-level `name` and `version`
+    LegacyJson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum StatsFormat {
+    Text,
+    Json,
+    /// Prometheus text format, one gauge per metric labeled with the program name
+    Prometheus,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiffFormat {
+    /// One line per change with a summary
+    Human,
+    /// The change list with a `breaking` flag per change
+    Json,
+    /// A unified diff of the canonicalized JSON
+    Unified,
+}
+
+// Aspects `diff --ignore` leaves out of the comparison
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DiffIgnore {
+    /// The whole `metadata` section
+    Metadata,
+    /// Every `docs` array
+    Docs,
+    /// `metadata.version` only
+    Version,
+}
+
+impl DiffIgnore {
+    fn covers(self, path: &str) -> bool {
+        match self {
+            DiffIgnore::Metadata => path == "metadata" || path.starts_with("metadata."),
+            DiffIgnore::Docs => path
+                .split('.')
+                .any(|segment| segment == "docs" || segment.starts_with("docs[")),
+            DiffIgnore::Version => path == "metadata.version",
+        }
+    }
+
+    // Removes the aspect from a canonicalized IDL
+    fn strip(self, value: &mut serde_json::Value) {
+        match self {
+            DiffIgnore::Metadata => {
+                if let Some(object) = value.as_object_mut() {
+                    object.remove("metadata");
+                }
+            }
+            DiffIgnore::Docs => strip_docs(value),
+            DiffIgnore::Version => {
+                if let Some(metadata) = value.get_mut("metadata").and_then(|v| v.as_object_mut()) {
+                    metadata.remove("version");
+                }
+            }
+        }
+    }
+}
+
+fn strip_docs(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.remove("docs");
+            object.values_mut().for_each(strip_docs);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_docs),
+        _ => {}
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GenerateLang {
+    Proto,
+    Openapi,
+    /// Rust `#[derive(Accounts)]` struct skeletons, one per instruction
+    AnchorAccounts,
+    /// TypeScript `zod` schemas for runtime validation of types and instruction args
+    Zod,
+}
+
+// Artifacts `build --emit` writes next to the IDL, each under the IDL's file stem
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum EmitFormat {
+    /// The IDL itself
+    Json,
+    /// `<stem>.proto`
+    Proto,
+    /// `<stem>.openapi.json`
+    Openapi,
+    /// `<stem>.accounts.rs`
+    AnchorAccounts,
+    /// `<stem>.ts` with zod schemas
+    Zod,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    /// The part of the name before the first `_`
+    Prefix,
+    /// A `@group:<name>` tag in the first doc line
+    DocsTag,
+}
+
+// Top-level IDL sections that Convert can keep or drop; address and metadata are always kept
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum IdlSection {
+    Docs,
+    Instructions,
+    Accounts,
+    Events,
+    Errors,
+    Types,
+    Constants,
+}
+
+// Output formats Convert can write
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ConvertTarget {
+    /// The current Anchor IDL spec
+    Anchor,
+    /// A Codama (formerly Kinobi) root node
+    Codama,
+}
+
+// IDL sections whose discriminators can be cross-checked for collisions
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DiscriminatorSection {
+    Accounts,
+    Events,
+    Instructions,
+}
+
+// Casing applied to a name in a discriminator preimage; Anchor uses the IDL name as-is
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DiscriminatorCase {
+    Snake,
+    Camel,
+    AsIs,
+}
+
+impl DiscriminatorCase {
+    fn apply(self, name: &str) -> String {
+        match self {
+            DiscriminatorCase::Snake => generate::to_snake_case(name),
+            DiscriminatorCase::Camel => generate::to_camel_case(name),
+            DiscriminatorCase::AsIs => name.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InstructionSort {
+    Name,
+    Args,
+    Accounts,
+    Source,
+}
+
+// Connection options shared by the commands that talk to an RPC node
+#[derive(Args)]
+struct RpcArgs {
+    #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+    url: String,
+
+    /// Retries for transient RPC failures (timeouts, HTTP 429 and 5xx)
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+
+    /// Delay before the first retry in milliseconds; doubles with each further attempt
+    #[arg(long, default_value_t = 500)]
+    retry_delay: u64,
+
+    /// Commitment level for account fetches; finalized data can lag a fresh deploy
+    #[arg(long, value_enum, default_value_t = rpc::Commitment::Confirmed)]
+    commitment: rpc::Commitment,
+}
+
+impl RpcArgs {
+    fn client(&self) -> rpc::Client {
+        rpc::Client::new(
+            &self.url,
+            self.retries,
+            std::time::Duration::from_millis(self.retry_delay),
+        )
+        .with_commitment(self.commitment)
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     Build {
@@ -32,6 +609,43 @@ enum Commands {
 
         #[arg(long)]
         no_resolution: bool,
+
+        /// Write the IDL to stdout instead of a file
+        #[arg(long, conflicts_with = "output")]
+        stdout: bool,
+
+        /// Also write `<output>.provenance.json` recording where each entry was defined
+        #[arg(long, conflicts_with = "stdout")]
+        with_provenance: bool,
+
+        /// Give up after this many seconds and exit with status 124
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+
+        /// Pretty-print only this many levels deep and keep deeper values on one line
+        /// (1 puts each top-level section on its own line)
+        #[arg(long, value_name = "DEPTH")]
+        pretty_depth: Option<usize>,
+
+        /// File name pattern used instead of `<name>.json`, with {name}, {version}, {stem}
+        /// (the program directory) and {date} placeholders
+        #[arg(long, conflicts_with_all = ["output", "stdout"])]
+        output_template: Option<String>,
+
+        /// Comma-separated artifacts to write next to the IDL; `json` is the IDL itself and the
+        /// rest come from the `generate` generators
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "json",
+            conflicts_with = "stdout"
+        )]
+        emit: Vec<EmitFormat>,
+
+        /// Stop at the first artifact that fails to generate instead of writing the others
+        #[arg(long)]
+        fail_fast: bool,
     },
 
     // Convert an IDL from a legacy format to the current format
@@ -41,12 +655,169 @@ enum Commands {
 
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Write the converted IDL to stdout instead of a file
+        #[arg(long, conflicts_with = "output")]
+        stdout: bool,
+
+        /// Output format; codama writes a Codama root node for the Codama code generators
+        #[arg(long, value_enum, default_value_t = ConvertTarget::Anchor)]
+        to: ConvertTarget,
+
+        /// Record a hash of each account's field layout in `metadata.layoutHashes`
+        #[arg(long)]
+        layout_hash: bool,
+
+        /// Keep only these top-level sections (comma-separated), plus address and metadata
+        #[arg(long, value_enum, value_delimiter = ',', conflicts_with = "exclude")]
+        only: Vec<IdlSection>,
+
+        /// Drop these top-level sections (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        exclude: Vec<IdlSection>,
+
+        /// Keep only these instructions (comma-separated) and the accounts and types they reach;
+        /// events and constants are dropped and errors kept
+        #[arg(long, value_name = "INSTRUCTION", value_delimiter = ',')]
+        select: Vec<String>,
+
+        /// Replace composite account groups with their accounts, in order (the nesting is lost)
+        #[arg(long)]
+        flatten_composites: bool,
+
+        /// Prefix each flattened account with its group names, e.g. `inner_vault`
+        #[arg(long, requires = "flatten_composites")]
+        prefix_composites: bool,
+
+        /// Pretty-print only this many levels deep and keep deeper values on one line
+        /// (1 puts each top-level section on its own line)
+        #[arg(long, value_name = "DEPTH")]
+        pretty_depth: Option<usize>,
+
+        /// Keep the input's top-level key order and the order of named items within each section,
+        /// for smaller diffs when converting repeatedly
+        #[arg(long)]
+        preserve_order: bool,
+
+        /// Print to stderr what the conversion changed: hoisted types, computed discriminators,
+        /// normalized primitives, renamed instructions and input keys that weren't carried over
+        #[arg(long)]
+        report: bool,
+
+        /// Blank the program address and drop `metadata.deployments`, for a template IDL that's
+        /// stamped with an address per deployment
+        #[arg(long)]
+        strip_address: bool,
+
+        /// File name pattern used instead of `<stem>.converted.json`, with {name}, {version},
+        /// {stem} (the input file's stem) and {date} placeholders; written next to the input
+        #[arg(long, conflicts_with_all = ["output", "stdout"])]
+        output_template: Option<String>,
     },
     
 
     Validate {
         #[arg(short, long)]
         input: PathBuf,
+
+        /// Treat the input directory as an example suite and also check every IDL round-trips through Convert
+        #[arg(long)]
+        examples_dir: bool,
+
+        /// Turn warnings (such as a placeholder program address) into errors
+        #[arg(long)]
+        strict: bool,
+
+        /// Pass `--require-address=false` to accept an IDL with no program address, such as a
+        /// template made with `convert --strip-address`; --strict doesn't change this
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+        require_address: bool,
+
+        /// Also check the raw JSON against the bundled IDL JSON Schema
+        #[arg(long)]
+        self_check: bool,
+
+        /// Warn instead of failing on empty discriminators, for pre-discriminator legacy IDLs
+        #[arg(long)]
+        allow_empty_discriminator: bool,
+
+        /// Fail when two discriminators in the checked sections are equal or one prefixes the other
+        #[arg(long)]
+        verify_discriminator_uniqueness: bool,
+
+        /// Sections cross-checked by --verify-discriminator-uniqueness (account data can hold
+        /// accounts or events, so those two are checked against each other by default)
+        #[arg(long, value_enum, value_delimiter = ',', default_values_t = [DiscriminatorSection::Accounts, DiscriminatorSection::Events])]
+        discriminator_sections: Vec<DiscriminatorSection>,
+
+        /// Fail when a discriminator isn't the sha256 prefix Anchor derives from the item's name
+        #[arg(long)]
+        verify_discriminators: bool,
+
+        /// Namespace for instruction discriminators checked by --verify-discriminators, as
+        /// `<namespace>` for every instruction or `<instruction>=<namespace>` for one (repeatable;
+        /// overrides an instruction's `namespace` field; defaults to `global`)
+        #[arg(long = "namespace", value_parser = parse_instruction_namespace, requires = "verify_discriminators")]
+        namespaces: Vec<InstructionNamespace>,
+
+        /// Prefix for every preimage checked by --verify-discriminators, in place of `global`,
+        /// `account` and `event`, for programs that don't follow Anchor's convention
+        #[arg(long, requires = "verify_discriminators", conflicts_with = "namespaces")]
+        disc_prefix: Option<String>,
+
+        /// How names are cased in the preimages checked by --verify-discriminators
+        #[arg(long, value_enum, default_value_t = DiscriminatorCase::AsIs, requires = "verify_discriminators")]
+        disc_case: DiscriminatorCase,
+
+        /// Warn when an account name is writable in one instruction but read-only in another
+        #[arg(long)]
+        consistent_accounts: bool,
+
+        /// Don't warn about instructions that take no accounts and no args
+        #[arg(long)]
+        allow_empty_instructions: bool,
+
+        /// Warn about args and fields whose types nest deeper than this, e.g.
+        /// `Option<Vec<u8>>` is 3 deep (generated clients can hit type depth limits)
+        #[arg(long, value_name = "N")]
+        max_type_depth: Option<usize>,
+
+        /// Fail when a zero-copy (bytemuck) type's fields are in a different order than in this
+        /// baseline IDL, which would misread existing account data
+        #[arg(long)]
+        layout_baseline: Option<PathBuf>,
+
+        /// Fail unless `metadata.spec` is at least this version (legacy IDLs have no spec and fail)
+        #[arg(long, value_parser = parse_spec_version)]
+        min_spec: Option<SpecVersion>,
+
+        /// Fail if `metadata.spec` is newer than this version
+        #[arg(long, value_parser = parse_spec_version)]
+        max_spec: Option<SpecVersion>,
+
+        /// Only check that the IDL parses and print its section counts (a subset of full validation)
+        #[arg(long, conflicts_with_all = ["examples_dir", "self_check"])]
+        count_only: bool,
+
+        /// Output format for the diagnostics (or the section counts of --count-only)
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Fail if validation produced any warnings
+        #[arg(long)]
+        fail_on_warning: bool,
+
+        /// Apply safe auto-fixes (doc whitespace, primitive spellings, unused types) before validating
+        #[arg(long)]
+        fix: bool,
+
+        /// Also apply fixes that change the API, such as renaming to snake_case (implies --fix)
+        #[arg(long)]
+        fix_unsafe: bool,
+
+        /// Write the fixed IDL here instead of overwriting the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
     
     Instructions {
@@ -55,312 +826,6469 @@ enum Commands {
         
         #[arg(long)]
         names_only: bool,
+
+        /// Print a compact one-line signature per instruction
+        #[arg(long)]
+        signatures: bool,
+
+        /// Print each instruction's accounts numbered in transaction order, composites flattened
+        #[arg(long, conflicts_with = "signatures")]
+        indices: bool,
+
+        /// Truncate each doc line to this many characters (0 disables truncation)
+        #[arg(long, default_value_t = 0)]
+        truncate_docs: usize,
+
+        /// Skip this many instructions before printing
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Print at most this many instructions
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Order instructions by name, argument count, account count or IDL order
+        #[arg(long, value_enum, default_value_t = InstructionSort::Source)]
+        sort: InstructionSort,
+
+        /// Invert the chosen order
+        #[arg(long)]
+        reverse: bool,
+
+        /// Print instructions in groups under a header per group
+        #[arg(long, value_enum)]
+        group_by: Option<GroupBy>,
+
+        /// Expand struct and enum arguments inline, resolved from the IDL's types
+        #[arg(long)]
+        expand_args: bool,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
-}
 
-fn display_instructions(path: &PathBuf, names_only: bool) -> Result<()> {
-    debug!("Extracting instructions from IDL at: {:?}", path);
-    
-    // Read the IDL file
-    let idl_bytes = fs::read(path)
-        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
-    
-    // Parse the IDL
-    let idl = anchor_lang_idl::convert::convert_idl(&idl_bytes)
-        .context("Failed to parse IDL")?;
-    
-    println!("\nProgram: {} (v{})", idl.metadata.name, idl.metadata.version);
-    println!("Address: {}", idl.address);
-    println!("\nInstructions ({}):", idl.instructions.len());
-    
-    for (idx, instruction) in idl.instructions.iter().enumerate() {
-        println!("\n{}. {}", idx + 1, instruction.name);
-        
-        if !names_only {
-            // Show documentation if available
-            if !instruction.docs.is_empty() {
-                println!("   Description:");
-                for doc in &instruction.docs {
-                    println!("     {}", doc);
-                }
-            }
+    // List each event with its discriminator and fields
+    Events {
+        #[arg(short, long)]
+        input: PathBuf,
+    },
 
-            if !instruction.args.is_empty() {
-                println!("   Arguments:");
-                for arg in &instruction.args {
-                    println!("     {} ({})", arg.name, format_type(&arg.ty));
-                    if !arg.docs.is_empty() {
-                        println!("       {}", arg.docs.join(" "));
-                    }
-                }
-            } else {
-                println!("   Arguments: None");
-            }
-            
-            // Show accounts
-            println!("   Accounts:");
-            if instruction.accounts.is_empty() {
-                println!("     None");
-            } else {
-                display_accounts(&instruction.accounts, 1);
-            }
-            
-            if let Some(returns) = &instruction.returns {
-                println!("   Returns: {}", format_type(returns));
-            }
-        }
-    }
-    
-    Ok(())
-}
+    // Compare an IDL against a committed baseline for regression testing
+    Snapshot {
+        #[arg(short, long)]
+        input: PathBuf,
 
-// Recursively display accounts with proper indentation
-fn display_accounts(accounts: &[anchor_lang_idl::types::IdlInstructionAccountItem], depth: usize) {
-    use anchor_lang_idl::types::IdlInstructionAccountItem;
-    
-    let indent = "  ".repeat(depth + 2);
-    
-    for account in accounts {
-        match account {
-            IdlInstructionAccountItem::Single(acc) => {
-                let mut attrs = Vec::new();
-                if acc.writable {
-                    attrs.push("writable");
-                }
-                if acc.signer {
-                    attrs.push("signer");
-                }
+        #[arg(short, long)]
+        baseline: PathBuf,
+
+        /// Overwrite the baseline with the canonicalized input instead of comparing
+        #[arg(long)]
+        update: bool,
+    },
+
+    // Validate an IDL and write it to <out-dir>/idls/<program>.json for `declare_program!`
+    Prepare {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Root of the crate that calls `declare_program!`
+        #[arg(short, long, default_value = ".")]
+        out_dir: PathBuf,
+    },
+
+    // Extract the IDL embedded in a built program's .so
+    FromElf {
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Write the extracted IDL here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    // Write the IDL as types.json (the `types` section) and interface.json (everything else)
+    Split {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Directory to write types.json and interface.json into
+        #[arg(short, long)]
+        output_dir: PathBuf,
+    },
+
+    // Recombine an interface.json and types.json written by Split
+    Merge {
+        /// The interface half of a split IDL
+        #[arg(short, long)]
+        interface: PathBuf,
+
+        /// Types file to merge in (defaults to the one recorded in the interface's metadata)
+        #[arg(short, long)]
+        types: Option<PathBuf>,
+
+        /// Write the merged IDL here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    // Print the part of the IDL a JSON Pointer (`/instructions/0/name`) or `$.a[0].b` path selects
+    Query {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// RFC 6901 JSON Pointer, or a JSONPath subset of `.key`, `[index]` and `['key']` steps from `$`
+        pointer: String,
+
+        /// Print string values without quotes
+        #[arg(long)]
+        raw: bool,
+    },
+
+    // List where each defined type (or just one) is referenced, grouped by kind of use
+    Usage {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Only report this type
+        #[arg(long = "type", value_name = "TYPE")]
+        ty: Option<String>,
+    },
+
+    // List the IDL's named constants with their types and values
+    Constants {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Print integer values with thousands separators
+        #[arg(long)]
+        group_digits: bool,
+    },
+
+    // Report size and complexity metrics for an IDL
+    Stats {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+        format: StatsFormat,
+    },
+
+    // Find the instruction, account or event a discriminator belongs to
+    Whois {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Hex (optionally 0x-prefixed), base58 or comma-separated decimal bytes; a value that's
+        /// valid hex is read as hex
+        discriminator: String,
+
+        /// Also report items whose discriminator starts with the given bytes
+        #[arg(long)]
+        prefix: bool,
+    },
+
+    // Compare two IDLs structurally, ignoring key and section ordering
+    Diff {
+        old: PathBuf,
+
+        new: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = DiffFormat::Human)]
+        diff_format: DiffFormat,
+
+        /// Print nothing unless there are breaking changes; list only those and exit nonzero
+        #[arg(long, conflicts_with = "diff_format")]
+        check_only: bool,
+
+        /// Leave these aspects out of the comparison (comma-separated); --verbose lists what
+        /// they hid as ignored differences
+        #[arg(long, value_enum, value_delimiter = ',')]
+        ignore: Vec<DiffIgnore>,
+    },
+
+    // Compute the address and bump of a PDA account from its seeds
+    Pda {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        instruction: String,
+
+        account: String,
+
+        /// Value for an instruction arg seed, e.g. --arg amount=42
+        #[arg(long = "arg", value_parser = parse_key_val)]
+        args: Vec<(String, String)>,
+
+        /// Public key for an account seed, e.g. --account authority=<pubkey>
+        #[arg(long = "account", value_parser = parse_key_val)]
+        account_keys: Vec<(String, String)>,
+    },
+
+    // Experimental: compare a local IDL against the one published on-chain
+    VerifyDeployed {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Program address to check (defaults to the address in the local IDL)
+        #[arg(short, long)]
+        address: Option<String>,
+
+        #[command(flatten)]
+        rpc: RpcArgs,
+    },
+
+    // Compare a local IDL with the latest one a registry publishes for the program
+    CheckRegistry {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Registry base URL; the latest IDL is fetched from `<url>/programs/<program>/idl`
+        #[arg(long)]
+        registry_url: String,
+
+        /// Program name or address as the registry knows it
+        #[arg(long)]
+        program: String,
+
+        /// Retries for transient failures (timeouts, HTTP 429 and 5xx)
+        #[arg(long, default_value_t = 3)]
+        retries: u32,
+
+        /// Delay before the first retry in milliseconds; doubles with each further attempt
+        #[arg(long, default_value_t = 500)]
+        retry_delay: u64,
+    },
+
+    // Fetch the published IDLs of several programs into a directory
+    FetchAll {
+        /// Program addresses to fetch
+        #[arg(required = true)]
+        addresses: Vec<String>,
+
+        #[command(flatten)]
+        rpc: RpcArgs,
+
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// Maximum number of requests in flight at once
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+    },
+
+    // Generate a schema for the IDL's data shapes in another language
+    Generate {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        #[arg(short, long, value_enum)]
+        lang: GenerateLang,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Prefix for the emitted identifiers: the outer proto package, OpenAPI schema and
+        /// operation names, or zod export names
+        #[arg(long)]
+        namespace: Option<String>,
+    },
+
+    // Developer tool: time parsing (and optionally converting) an IDL
+    #[command(hide = true)]
+    Bench {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        #[arg(short = 'n', long, default_value_t = 100)]
+        iterations: u32,
+
+        /// Also time `convert_idl`, the path every other command goes through
+        #[arg(long)]
+        convert: bool,
+    },
+
+    // Check that the IDL address matches the program's `declare_id!`
+    CheckAddress {
+        #[arg(long)]
+        idl: PathBuf,
+
+        #[arg(short, long, default_value = ".")]
+        program_path: PathBuf,
+    },
+}
+
+// The log subscriber for `--log-format` and `--verbose`, writing to `writer`
+fn log_dispatch<W>(format: LogFormat, verbose: bool, writer: W) -> tracing::Dispatch
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    // logging based n verbosity flag
+    let log_level = if verbose { "debug" } else { "info" };
+    let log_filter = format!("dls_anchor={},anchor_lang_idl={}", log_level, log_level);
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt()
+            .with_writer(writer)
+            .with_env_filter(log_filter)
+            .into(),
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_writer(writer)
+            .with_env_filter(log_filter)
+            .into(),
+    }
+}
+
+// Parses `name=value` pairs passed on the command line
+fn parse_key_val(arg: &str) -> std::result::Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected name=value, got '{}'", arg))
+}
+
+// Presentation options for the instructions listing
+struct ListOptions {
+    names_only: bool,
+    truncate_docs: usize,
+    offset: usize,
+    limit: Option<usize>,
+    sort: InstructionSort,
+    reverse: bool,
+    group_by: Option<GroupBy>,
+    expand_args: bool,
+}
+
+// How many levels of nested types --expand-args prints before eliding the rest
+const MAX_EXPAND_DEPTH: usize = 8;
+
+fn display_instructions(path: &PathBuf, options: &ListOptions) -> Result<()> {
+    debug!("Extracting instructions from IDL at: {:?}", path);
+    
+    // Read the IDL file
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    
+    // Parse the IDL
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+    
+    println!("\nProgram: {} (v{})", idl.metadata.name, idl.metadata.version);
+    println!("Address: {}", idl.address);
+    println!("\nInstructions ({}):", idl.instructions.len());
+
+    let instructions = sort_instructions(&idl.instructions, options.sort, options.reverse);
+    let instructions = group_instructions(instructions, options.group_by);
+
+    // Window the listing; printed indices stay absolute within the chosen order
+    let total = instructions.len();
+    let (start, end) = page_window(total, options.offset, options.limit);
+    
+    let mut current_group = None;
+    for (idx, (group, instruction)) in instructions.iter().enumerate().take(end).skip(start) {
+        if group.is_some() && current_group != Some(group) {
+            let size = instructions.iter().filter(|(g, _)| g == group).count();
+            println!("\n[{}] ({})", group.as_deref().unwrap_or_default(), size);
+            current_group = Some(group);
+        }
+        print_instruction(idx, instruction, &idl.types, options);
+    }
+
+    if let Some(footer) = page_footer(total, options.offset, options.limit) {
+        println!("\n{}", footer);
+    }
+    
+    Ok(())
+}
+
+// The `[start, end)` range `--offset` and `--limit` select out of `total` instructions
+fn page_window(total: usize, offset: usize, limit: Option<usize>) -> (usize, usize) {
+    let start = offset.min(total);
+    let end = limit.map_or(total, |limit| start.saturating_add(limit).min(total));
+    (start, end)
+}
+
+// Line printed after a paged listing saying which part was shown; `None` when not paging
+fn page_footer(total: usize, offset: usize, limit: Option<usize>) -> Option<String> {
+    if offset == 0 && limit.is_none() {
+        return None;
+    }
+    let (start, end) = page_window(total, offset, limit);
+    Some(if start == end {
+        format!("No instructions in range (offset {} of {})", offset, total)
+    } else {
+        format!("Showing {}-{} of {}", start + 1, end, total)
+    })
+}
+
+// Stable sort, so instructions that compare equal keep their IDL order
+fn sort_instructions(
+    instructions: &[anchor_lang_idl::types::IdlInstruction],
+    sort: InstructionSort,
+    reverse: bool,
+) -> Vec<&anchor_lang_idl::types::IdlInstruction> {
+    let mut sorted: Vec<_> = instructions.iter().collect();
+    match sort {
+        InstructionSort::Name => sorted.sort_by(|a, b| a.name.cmp(&b.name)),
+        InstructionSort::Args => sorted.sort_by_key(|ix| ix.args.len()),
+        InstructionSort::Accounts => sorted.sort_by_key(|ix| flatten_accounts(&ix.accounts).len()),
+        InstructionSort::Source => {}
+    }
+    if reverse {
+        sorted.reverse();
+    }
+    sorted
+}
+
+// Buckets instructions by group key, keeping groups in order of first appearance
+fn group_instructions(
+    instructions: Vec<&anchor_lang_idl::types::IdlInstruction>,
+    group_by: Option<GroupBy>,
+) -> Vec<(Option<String>, &anchor_lang_idl::types::IdlInstruction)> {
+    let Some(group_by) = group_by else {
+        return instructions.into_iter().map(|ix| (None, ix)).collect();
+    };
+
+    let mut groups: Vec<(String, Vec<&anchor_lang_idl::types::IdlInstruction>)> = Vec::new();
+    for instruction in instructions {
+        let key = match group_by {
+            GroupBy::Prefix => instruction.name.split('_').next().unwrap_or_default().to_string(),
+            GroupBy::DocsTag => instruction
+                .docs
+                .first()
+                .and_then(|doc| doc.split_once("@group:"))
+                .and_then(|(_, tag)| tag.split_whitespace().next())
+                .unwrap_or("ungrouped")
+                .to_string(),
+        };
+        match groups.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, members)) => members.push(instruction),
+            None => groups.push((key, vec![instruction])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .flat_map(|(key, members)| members.into_iter().map(move |ix| (Some(key.clone()), ix)))
+        .collect()
+}
+
+fn print_instruction(
+    idx: usize,
+    instruction: &anchor_lang_idl::types::IdlInstruction,
+    types: &[anchor_lang_idl::types::IdlTypeDef],
+    options: &ListOptions,
+) {
+    println!("\n{}. {}", idx + 1, instruction.name);
+
+    if options.names_only {
+        return;
+    }
+
+    // Show documentation if available
+    if !instruction.docs.is_empty() {
+        println!("   Description:");
+        for doc in &instruction.docs {
+            println!("     {}", truncate_doc(doc, options.truncate_docs));
+        }
+    }
+
+    if !instruction.args.is_empty() {
+        println!("   Arguments:");
+        for arg in &instruction.args {
+            println!("     {} ({})", arg.name, format_type(&arg.ty));
+            if !arg.docs.is_empty() {
+                println!(
+                    "       {}",
+                    truncate_doc(&arg.docs.join(" "), options.truncate_docs)
+                );
+            }
+            if options.expand_args {
+                let mut lines = Vec::new();
+                expand_type(types, &arg.ty, 7, options.truncate_docs, &mut Vec::new(), &mut lines);
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+        }
+    } else {
+        println!("   Arguments: None");
+    }
+    
+    // Show accounts
+    println!("   Accounts:");
+    if instruction.accounts.is_empty() {
+        println!("     None");
+    } else {
+        let mut lines = Vec::new();
+        account_lines(&instruction.accounts, 1, options.truncate_docs, &mut lines);
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+    
+    if let Some(returns) = &instruction.returns {
+        println!("   Returns: {}", format_type(returns));
+    }
+}
+
+fn display_events(path: &PathBuf) -> Result<()> {
+    debug!("Extracting events from IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    // Legacy IDLs declare event fields inline; conversion moves them into `types`, so both
+    // shapes are read from there
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    write_events(&idl, &mut std::io::stdout().lock())?;
+    Ok(())
+}
+
+// The `events` listing: each event's discriminator, docs and fields, resolved from `types`
+fn write_events(
+    idl: &anchor_lang_idl::types::Idl,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlTypeDefTy};
+
+    writeln!(out, "\nProgram: {} (v{})", idl.metadata.name, idl.metadata.version)?;
+    writeln!(out, "\nEvents ({}):", idl.events.len())?;
+
+    for (idx, event) in idl.events.iter().enumerate() {
+        writeln!(out, "\n{}. {}", idx + 1, event.name)?;
+        writeln!(out, "   Discriminator: {}", render_bytes(&event.discriminator))?;
+
+        let Some(def) = idl.types.iter().find(|def| def.name == event.name) else {
+            writeln!(out, "   Fields: (type definition missing)")?;
+            continue;
+        };
+        if !def.docs.is_empty() {
+            writeln!(out, "   Description:")?;
+            for doc in &def.docs {
+                writeln!(out, "     {}", doc)?;
+            }
+        }
+        match &def.ty {
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } if !fields.is_empty() => {
+                writeln!(out, "   Fields:")?;
+                for field in fields {
+                    writeln!(out, "     {} ({})", field.name, format_type(&field.ty))?;
+                }
+            }
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Tuple(types)),
+            } if !types.is_empty() => {
+                writeln!(out, "   Fields:")?;
+                for (field_idx, ty) in types.iter().enumerate() {
+                    writeln!(out, "     {} ({})", field_idx, format_type(ty))?;
+                }
+            }
+            IdlTypeDefTy::Struct { .. } => writeln!(out, "   Fields: None")?,
+            _ => writeln!(out, "   Fields: (type '{}' is not a struct)", def.name)?,
+        }
+    }
+
+    Ok(())
+}
+
+// Appends a line per field or variant of the type `ty` refers to, looking through Option/Vec/array
+// wrappers, with field docs cut to `truncate_docs`; `stack` holds the types being expanded so
+// recursive types stop instead of looping
+fn expand_type(
+    types: &[anchor_lang_idl::types::IdlTypeDef],
+    ty: &anchor_lang_idl::types::IdlType,
+    indent: usize,
+    truncate_docs: usize,
+    stack: &mut Vec<String>,
+    lines: &mut Vec<String>,
+) {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+
+    let name = match ty {
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            return expand_type(types, inner, indent, truncate_docs, stack, lines)
+        }
+        IdlType::Defined { name, .. } => name,
+        _ => return,
+    };
+    let Some(def) = types.iter().find(|def| &def.name == name) else {
+        return;
+    };
+    let pad = " ".repeat(indent);
+    if stack.contains(name) {
+        lines.push(format!("{}(recursive {})", pad, name));
+        return;
+    }
+    if stack.len() >= MAX_EXPAND_DEPTH {
+        lines.push(format!("{}...", pad));
+        return;
+    }
+
+    stack.push(name.clone());
+    let push_fields = |fields: &IdlDefinedFields,
+                       indent: usize,
+                       stack: &mut Vec<String>,
+                       lines: &mut Vec<String>| {
+        let pad = " ".repeat(indent);
+        match fields {
+            IdlDefinedFields::Named(fields) => {
+                for field in fields {
+                    let mut line = format!("{}{}: {}", pad, field.name, format_type(&field.ty));
+                    if !field.docs.is_empty() {
+                        line.push_str("  // ");
+                        line.push_str(&truncate_doc(&field.docs.join(" "), truncate_docs));
+                    }
+                    lines.push(line);
+                    expand_type(types, &field.ty, indent + 2, truncate_docs, stack, lines);
+                }
+            }
+            IdlDefinedFields::Tuple(fields) => {
+                for (idx, ty) in fields.iter().enumerate() {
+                    lines.push(format!("{}{}: {}", pad, idx, format_type(ty)));
+                    expand_type(types, ty, indent + 2, truncate_docs, stack, lines);
+                }
+            }
+        }
+    };
+    match &def.ty {
+        IdlTypeDefTy::Struct { fields } => {
+            if let Some(fields) = fields {
+                push_fields(fields, indent, stack, lines);
+            }
+        }
+        IdlTypeDefTy::Enum { variants } => {
+            for variant in variants {
+                lines.push(format!("{}{}", pad, variant.name));
+                if let Some(fields) = &variant.fields {
+                    push_fields(fields, indent + 2, stack, lines);
+                }
+            }
+        }
+        IdlTypeDefTy::Type { alias } => expand_type(types, alias, indent, truncate_docs, stack, lines),
+    }
+    stack.pop();
+}
+
+// Shortens a doc line to `max` characters with an ellipsis; 0 disables truncation
+fn truncate_doc(doc: &str, max: usize) -> String {
+    if max == 0 || doc.chars().count() <= max {
+        return doc.to_string();
+    }
+    let truncated: String = doc.chars().take(max).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+// Prints a terse `name(arg: type, ...) [n accounts]` line per instruction
+fn display_signatures(path: &PathBuf, format: OutputFormat) -> Result<()> {
+    debug!("Extracting instruction signatures from IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    match format {
+        OutputFormat::Text => {
+            for instruction in &idl.instructions {
+                let args: Vec<String> = instruction
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}: {}", arg.name, format_type(&arg.ty)))
+                    .collect();
+                let account_count = flatten_accounts(&instruction.accounts).len();
+                let returns = instruction
+                    .returns
+                    .as_ref()
+                    .map(|ty| format!(" -> {}", format_type(ty)))
+                    .unwrap_or_default();
+
+                println!(
+                    "{}({}){} [{} account{}]",
+                    instruction.name,
+                    args.join(", "),
+                    returns,
+                    account_count,
+                    if account_count == 1 { "" } else { "s" }
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let signatures: Vec<serde_json::Value> = idl
+                .instructions
+                .iter()
+                .map(|instruction| {
+                    serde_json::json!({
+                        "name": instruction.name,
+                        "args": instruction.args.iter().map(|arg| serde_json::json!({
+                            "name": arg.name,
+                            "type": format_type(&arg.ty),
+                        })).collect::<Vec<_>>(),
+                        "accounts": flatten_accounts(&instruction.accounts).len(),
+                        "returns": instruction.returns.as_ref().map(format_type),
+                    })
+                })
+                .collect();
+
+            let signatures_json = serde_json::to_string_pretty(&signatures)
+                .context("Failed to serialize signatures to JSON")?;
+            println!("{}", signatures_json);
+        }
+    }
+
+    Ok(())
+}
+
+// Prints the position of every account in each instruction's account list, which raw
+// clients must reproduce exactly
+fn display_account_indices(path: &PathBuf, format: OutputFormat) -> Result<()> {
+    debug!("Extracting account indices from IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    match format {
+        OutputFormat::Text => {
+            for instruction in &idl.instructions {
+                println!("{}", instruction.name);
+                for (idx, account) in flatten_accounts(&instruction.accounts).iter().enumerate() {
+                    let flags: Vec<&str> = [
+                        (account.writable, "writable"),
+                        (account.signer, "signer"),
+                        (account.optional, "optional"),
+                    ]
+                    .into_iter()
+                    .filter_map(|(set, flag)| set.then_some(flag))
+                    .collect();
+                    if flags.is_empty() {
+                        println!("  {}: {}", idx, account.name);
+                    } else {
+                        println!("  {}: {} ({})", idx, account.name, flags.join(", "));
+                    }
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let index_map: serde_json::Map<String, serde_json::Value> = idl
+                .instructions
+                .iter()
+                .map(|instruction| {
+                    let accounts: Vec<serde_json::Value> = flatten_accounts(&instruction.accounts)
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, account)| account_json(idx, account))
+                        .collect();
+                    (instruction.name.clone(), accounts.into())
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&index_map)?);
+        }
+    }
+
+    Ok(())
+}
+
+// One account of `instructions --indices --format json`: its position and flags, plus the
+// fixed address, relations and PDA seeds the text listing shows
+fn account_json(
+    idx: usize,
+    account: &anchor_lang_idl::types::IdlInstructionAccount,
+) -> serde_json::Value {
+    let pda = account.pda.as_ref().map(|pda| {
+        serde_json::json!({
+            "seeds": pda.seeds.iter().map(seed_json).collect::<Vec<_>>(),
+            "program": pda.program.as_ref().map(seed_json),
+        })
+    });
+    serde_json::json!({
+        "index": idx,
+        "name": account.name,
+        "writable": account.writable,
+        "signer": account.signer,
+        "optional": account.optional,
+        "address": account.address,
+        "relations": account.relations,
+        "pda": pda,
+    })
+}
+
+// `{ "kind": "const", "hex": ..., "bytes": [...] }`, or `{ "kind": "arg" | "account", "path": ... }`
+fn seed_json(seed: &anchor_lang_idl::types::IdlSeed) -> serde_json::Value {
+    use anchor_lang_idl::types::IdlSeed;
+
+    match seed {
+        IdlSeed::Const(seed) => serde_json::json!({
+            "kind": "const",
+            "hex": seed.value.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            "bytes": seed.value,
+        }),
+        IdlSeed::Arg(seed) => serde_json::json!({ "kind": "arg", "path": seed.path }),
+        IdlSeed::Account(seed) => serde_json::json!({ "kind": "account", "path": seed.path }),
+    }
+}
+
+// Flattens composite account groups into the ordered list of single accounts
+fn flatten_accounts(
+    accounts: &[anchor_lang_idl::types::IdlInstructionAccountItem],
+) -> Vec<&anchor_lang_idl::types::IdlInstructionAccount> {
+    use anchor_lang_idl::types::IdlInstructionAccountItem;
+
+    let mut flat = Vec::new();
+    for account in accounts {
+        match account {
+            IdlInstructionAccountItem::Single(acc) => flat.push(acc),
+            IdlInstructionAccountItem::Composite(composite) => {
+                flat.extend(flatten_accounts(&composite.accounts))
+            }
+        }
+    }
+    flat
+}
+
+// Appends the account tree with its attributes, docs and PDA seeds; docs are cut to `truncate_docs`
+fn account_lines(
+    accounts: &[anchor_lang_idl::types::IdlInstructionAccountItem],
+    depth: usize,
+    truncate_docs: usize,
+    lines: &mut Vec<String>,
+) {
+    use anchor_lang_idl::types::IdlInstructionAccountItem;
+    
+    let indent = "  ".repeat(depth + 2);
+    
+    for account in accounts {
+        match account {
+            IdlInstructionAccountItem::Single(acc) => {
+                let mut attrs = Vec::new();
+                if acc.writable {
+                    attrs.push("writable");
+                }
+                if acc.signer {
+                    attrs.push("signer");
+                }
                 if acc.optional {
                     attrs.push("optional");
                 }
-                
-                let attr_str = if attrs.is_empty() {
-                    String::new()
-                } else {
-                    format!(" ({})", attrs.join(", "))
-                };
-                
-                println!("{}{}{}", indent, acc.name, attr_str);
-                
-                if let Some(pda) = &acc.pda {
-                    println!("{}  PDA with {} seeds", indent, pda.seeds.len());
+                
+                let attr_str = if attrs.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", attrs.join(", "))
+                };
+                
+                lines.push(format!("{}{}{}", indent, acc.name, attr_str));
+                if !acc.docs.is_empty() {
+                    lines.push(format!(
+                        "{}  {}",
+                        indent,
+                        truncate_doc(&acc.docs.join(" "), truncate_docs)
+                    ));
+                }
+                
+                if let Some(pda) = &acc.pda {
+                    let bump = match pda::stored_bump(pda) {
+                        Some(byte) => format!(
+                            "last seed is the one-byte constant {}, possibly a stored bump",
+                            byte
+                        ),
+                        None => "canonical bump found when deriving".to_string(),
+                    };
+                    lines.push(format!("{}  PDA with {} seeds, {}", indent, pda.seeds.len(), bump));
+                    for seed in &pda.seeds {
+                        let seed = match seed {
+                            anchor_lang_idl::types::IdlSeed::Const(seed) => {
+                                format!("const {}", render_bytes(&seed.value))
+                            }
+                            anchor_lang_idl::types::IdlSeed::Arg(seed) => format!("arg {}", seed.path),
+                            anchor_lang_idl::types::IdlSeed::Account(seed) => {
+                                format!("account {}", seed.path)
+                            }
+                        };
+                        lines.push(format!("{}    {}", indent, seed));
+                    }
+                }
+            },
+            IdlInstructionAccountItem::Composite(composite) => {
+                lines.push(format!("{}{}:", indent, composite.name));
+                account_lines(&composite.accounts, depth + 1, truncate_docs, lines);
+            }
+        }
+    }
+}
+
+/// Format an IDL type for display
+fn format_type(ty: &anchor_lang_idl::types::IdlType) -> String {
+    use anchor_lang_idl::types::{IdlArrayLen, IdlGenericArg, IdlType};
+    
+    match ty {
+        IdlType::Bool => "bool".into(),
+        IdlType::U8 => "u8".into(),
+        IdlType::I8 => "i8".into(),
+        IdlType::U16 => "u16".into(),
+        IdlType::I16 => "i16".into(),
+        IdlType::U32 => "u32".into(),
+        IdlType::I32 => "i32".into(),
+        IdlType::F32 => "f32".into(),
+        IdlType::U64 => "u64".into(),
+        IdlType::I64 => "i64".into(),
+        IdlType::F64 => "f64".into(),
+        IdlType::U128 => "u128".into(),
+        IdlType::I128 => "i128".into(),
+        IdlType::U256 => "u256".into(),
+        IdlType::I256 => "i256".into(),
+        IdlType::Bytes => "bytes".into(),
+        IdlType::String => "string".into(),
+        IdlType::Pubkey => "pubkey".into(),
+        IdlType::Option(inner) => format!("Option<{}>", format_type(inner)),
+        IdlType::Vec(inner) => format!("Vec<{}>", format_type(inner)),
+        IdlType::Array(inner, len) => match len {
+            IdlArrayLen::Value(size) => format!("[{}; {}]", format_type(inner), size),
+            IdlArrayLen::Generic(name) => format!("[{}; {}]", format_type(inner), name),
+        },
+        IdlType::Defined { name, generics } => {
+            if generics.is_empty() {
+                name.clone()
+            } else {
+                let generic_strs: Vec<String> = generics
+                    .iter()
+                    .map(|g| match g {
+                        IdlGenericArg::Type { ty } => format_type(ty),
+                        IdlGenericArg::Const { value } => value.clone(),
+                    })
+                    .collect();
+                format!("{}<{}>", name, generic_strs.join(", "))
+            }
+        }
+        IdlType::Generic(name) => name.clone(),
+        // wildcard pattern for any new types added in the future
+        _ => "<unknown type>".into(),
+    }
+}
+
+// Program IDs that appear in scaffolding and templates but never in a real deployment
+const PLACEHOLDER_PROGRAM_IDS: &[&str] = &[
+    // System program, commonly left in place of a real program ID
+    "11111111111111111111111111111111",
+    // Default ID generated by `anchor init`
+    "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+];
+
+// The IDL doesn't record `init`/`payer` constraints, so likely payers are inferred: accounts
+// named `payer`, and read-only signers seeding a writable PDA when no other signer is writable
+fn payer_problems(idl: &anchor_lang_idl::types::Idl) -> Vec<String> {
+    let named_payer = |name: &str| name == "payer" || name.ends_with("_payer");
+
+    let mut problems = Vec::new();
+    for instruction in &idl.instructions {
+        let accounts = flatten_accounts(&instruction.accounts);
+
+        for account in &accounts {
+            if named_payer(&account.name) && !(account.writable && account.signer) {
+                problems.push(format!(
+                    "'{}' in '{}' looks like a payer but isn't writable and signer",
+                    account.name, instruction.name
+                ));
+            }
+        }
+
+        if accounts.iter().any(|acc| acc.writable && acc.signer) {
+            continue;
+        }
+        for account in accounts.iter().filter(|acc| acc.signer && !acc.writable) {
+            let seeds_writable_pda = accounts.iter().any(|acc| {
+                acc.writable
+                    && acc.pda.as_ref().is_some_and(|pda| {
+                        pda.seeds.iter().any(|seed| {
+                            matches!(
+                                seed,
+                                anchor_lang_idl::types::IdlSeed::Account(seed)
+                                    if seed.path == account.name
+                            )
+                        })
+                    })
+            });
+            if seeds_writable_pda && !named_payer(&account.name) {
+                problems.push(format!(
+                    "signer '{}' in '{}' seeds a writable PDA and may fund its init, but isn't writable",
+                    account.name, instruction.name
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// A decoder matches data against discriminators by prefix, so two of them collide when one is a
+// prefix of the other (including being equal); empty discriminators are reported separately
+fn discriminator_collisions(
+    idl: &anchor_lang_idl::types::Idl,
+    sections: &[DiscriminatorSection],
+) -> Vec<String> {
+    let mut entries: Vec<(String, &[u8])> = Vec::new();
+    for (idx, section) in sections.iter().enumerate() {
+        // A section listed twice shouldn't report every entry as colliding with itself
+        if sections[..idx].contains(section) {
+            continue;
+        }
+        match section {
+            DiscriminatorSection::Accounts => entries.extend(idl.accounts.iter().map(|account| {
+                (format!("account '{}'", account.name), account.discriminator.as_slice())
+            })),
+            DiscriminatorSection::Events => entries.extend(
+                idl.events
+                    .iter()
+                    .map(|event| (format!("event '{}'", event.name), event.discriminator.as_slice())),
+            ),
+            DiscriminatorSection::Instructions => entries.extend(idl.instructions.iter().map(|ix| {
+                (format!("instruction '{}'", ix.name), ix.discriminator.as_slice())
+            })),
+        }
+    }
+    entries.retain(|(_, discriminator)| !discriminator.is_empty());
+
+    let mut collisions = Vec::new();
+    for (idx, (name, discriminator)) in entries.iter().enumerate() {
+        for (other_name, other) in &entries[idx + 1..] {
+            let shared = discriminator.len().min(other.len());
+            if discriminator[..shared] == other[..shared] {
+                collisions.push(format!(
+                    "{} and {} share bytes {:?}",
+                    name,
+                    other_name,
+                    &discriminator[..shared]
+                ));
+            }
+        }
+    }
+    collisions
+}
+
+// Generated clients take args and accounts side by side, so an arg named like one of the
+// instruction's accounts yields colliding or confusing identifiers
+fn shadowed_account_names(idl: &anchor_lang_idl::types::Idl) -> Vec<String> {
+    let mut problems = Vec::new();
+    for instruction in &idl.instructions {
+        let accounts = flatten_accounts(&instruction.accounts);
+        for arg in &instruction.args {
+            if accounts.iter().any(|account| account.name == arg.name) {
+                problems.push(format!(
+                    "arg '{}' in '{}' has the same name as one of its accounts",
+                    arg.name, instruction.name
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// A `pubkey` arg named like an account (`authority`, `authority_key`) is often an account that
+// was modeled as an arg, which leaves PDA seeds and relations unable to refer to it. Accounts of
+// any instruction count, since the arg may be the only place the instruction mentions it. An arg
+// named exactly like one of its own instruction's accounts is already in the accounts list, and
+// `shadowed_account_names` reports that collision.
+fn pubkey_arg_accounts(idl: &anchor_lang_idl::types::Idl) -> Vec<String> {
+    let account_names: std::collections::BTreeSet<&str> = idl
+        .instructions
+        .iter()
+        .flat_map(|instruction| flatten_accounts(&instruction.accounts))
+        .map(|account| account.name.as_str())
+        .collect();
+
+    let mut problems = Vec::new();
+    for instruction in &idl.instructions {
+        let own_accounts = flatten_accounts(&instruction.accounts);
+        for arg in &instruction.args {
+            if !matches!(arg.ty, anchor_lang_idl::types::IdlType::Pubkey)
+                || own_accounts.iter().any(|account| account.name == arg.name)
+            {
+                continue;
+            }
+            let base = ["_pubkey", "_key", "_address"]
+                .iter()
+                .find_map(|suffix| arg.name.strip_suffix(suffix))
+                .unwrap_or(&arg.name);
+            if let Some(account) = [arg.name.as_str(), base]
+                .into_iter()
+                .find(|name| account_names.contains(name))
+            {
+                problems.push(format!(
+                    "pubkey arg '{}' in '{}' is named like account '{}'; should it be in the accounts list?",
+                    arg.name, instruction.name, account
+                ));
+            }
+        }
+    }
+    problems
+}
+
+// Nesting depth of a type as `format_type` spells it: a primitive or plain defined type is 1, and
+// each `Option`, `Vec`, array or generic argument list adds a level
+fn type_depth(ty: &anchor_lang_idl::types::IdlType) -> usize {
+    use anchor_lang_idl::types::{IdlGenericArg, IdlType};
+
+    match ty {
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            1 + type_depth(inner)
+        }
+        IdlType::Defined { generics, .. } => {
+            1 + generics
+                .iter()
+                .map(|generic| match generic {
+                    IdlGenericArg::Type { ty } => type_depth(ty),
+                    IdlGenericArg::Const { .. } => 0,
+                })
+                .max()
+                .unwrap_or(0)
+        }
+        _ => 1,
+    }
+}
+
+// Args and type fields nested deeper than `max`, which some client generators can't express
+fn deep_types(idl: &anchor_lang_idl::types::Idl, max: usize) -> Vec<String> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+
+    let mut problems = Vec::new();
+    let mut check = |element: String, ty: &IdlType| {
+        let depth = type_depth(ty);
+        if depth > max {
+            problems.push(format!(
+                "{} has type {} nested {} deep, over --max-type-depth {}",
+                element,
+                format_type(ty),
+                depth,
+                max
+            ));
+        }
+    };
+
+    for instruction in &idl.instructions {
+        for arg in &instruction.args {
+            check(format!("arg '{}' in '{}'", arg.name, instruction.name), &arg.ty);
+        }
+    }
+    for def in &idl.types {
+        let mut check_fields = |owner: &str, fields: &IdlDefinedFields| match fields {
+            IdlDefinedFields::Named(fields) => {
+                for field in fields {
+                    check(format!("field '{}.{}'", owner, field.name), &field.ty);
+                }
+            }
+            IdlDefinedFields::Tuple(types) => {
+                for (idx, ty) in types.iter().enumerate() {
+                    check(format!("field '{}.{}'", owner, idx), ty);
+                }
+            }
+        };
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                if let Some(fields) = fields {
+                    check_fields(&def.name, fields);
+                }
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                for variant in variants {
+                    if let Some(fields) = &variant.fields {
+                        check_fields(&format!("{}::{}", def.name, variant.name), fields);
+                    }
+                }
+            }
+            IdlTypeDefTy::Type { alias } => check(format!("alias '{}'", def.name), alias),
+        }
+    }
+    problems
+}
+
+// Same-named accounts usually refer to the same on-chain account, so differing mutability across
+// instructions may be a missing `mut`; it's a heuristic, so these are only ever warnings
+fn inconsistent_accounts(idl: &anchor_lang_idl::types::Idl) -> Vec<String> {
+    let mut usage: std::collections::BTreeMap<&str, (Vec<&str>, Vec<&str>)> =
+        std::collections::BTreeMap::new();
+    for instruction in &idl.instructions {
+        for account in flatten_accounts(&instruction.accounts) {
+            let (writable, readonly) = usage.entry(account.name.as_str()).or_default();
+            let list = if account.writable { writable } else { readonly };
+            if !list.contains(&instruction.name.as_str()) {
+                list.push(instruction.name.as_str());
+            }
+        }
+    }
+
+    usage
+        .into_iter()
+        .filter(|(_, (writable, readonly))| !writable.is_empty() && !readonly.is_empty())
+        .map(|(name, (writable, readonly))| {
+            format!(
+                "account '{}' is writable in {} but read-only in {}",
+                name,
+                writable.join(", "),
+                readonly.join(", ")
+            )
+        })
+        .collect()
+}
+
+// Zero-copy accounts are read by casting their bytes, so moving a field silently misreads every
+// existing account; fields that were added or removed are left to `diff`
+fn reordered_zero_copy_fields(
+    baseline: &anchor_lang_idl::types::Idl,
+    idl: &anchor_lang_idl::types::Idl,
+) -> Vec<String> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlSerialization, IdlTypeDefTy};
+
+    let field_names = |ty: &IdlTypeDefTy| -> Option<Vec<String>> {
+        match ty {
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } => Some(fields.iter().map(|field| field.name.clone()).collect()),
+            _ => None,
+        }
+    };
+
+    let mut problems = Vec::new();
+    for def in &idl.types {
+        if !matches!(
+            def.serialization,
+            IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe
+        ) {
+            continue;
+        }
+        let Some(old) = baseline.types.iter().find(|old| old.name == def.name) else {
+            continue;
+        };
+        let (Some(old_fields), Some(new_fields)) = (field_names(&old.ty), field_names(&def.ty))
+        else {
+            continue;
+        };
+
+        // Compare the relative order of the fields both versions have
+        let old_order: Vec<&String> = old_fields.iter().filter(|f| new_fields.contains(f)).collect();
+        let new_order: Vec<&String> = new_fields.iter().filter(|f| old_fields.contains(f)).collect();
+        if old_order == new_order {
+            continue;
+        }
+        problems.push(format!(
+            "type '{}' fields were {} and are now {}",
+            def.name,
+            old_order.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(", "),
+            new_order.iter().map(|f| f.as_str()).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    problems
+}
+
+// Finds account seeds that reference an account missing from the instruction (errors) or an
+// optional one, which may be absent at runtime (warnings)
+fn pda_seed_problems(idl: &anchor_lang_idl::types::Idl) -> (Vec<String>, Vec<String>) {
+    let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+    for instruction in &idl.instructions {
+        let accounts = flatten_accounts(&instruction.accounts);
+        for account in &accounts {
+            let Some(pda) = &account.pda else {
+                continue;
+            };
+            for seed in pda.seeds.iter().chain(pda.program.as_ref()) {
+                if let anchor_lang_idl::types::IdlSeed::Arg(seed) = seed {
+                    let source = seed.path.split('.').next().unwrap_or_default();
+                    if !instruction.args.iter().any(|arg| arg.name == source) {
+                        errors.push(format!(
+                            "PDA '{}' in '{}' seeds from arg '{}', which isn't an arg of the instruction",
+                            account.name, instruction.name, seed.path
+                        ));
+                    }
+                }
+                let anchor_lang_idl::types::IdlSeed::Account(seed) = seed else {
+                    continue;
+                };
+                // `config.authority` reads a field of the `config` account
+                let source = seed.path.split('.').next().unwrap_or_default();
+                match accounts.iter().find(|acc| acc.name == source) {
+                    None => errors.push(format!(
+                        "PDA '{}' in '{}' seeds from account '{}', which isn't in the instruction",
+                        account.name, instruction.name, seed.path
+                    )),
+                    Some(source_account) if source_account.optional => warnings.push(format!(
+                        "PDA '{}' in '{}' seeds from optional account '{}'; derivation fails when it's omitted",
+                        account.name, instruction.name, seed.path
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    (errors, warnings)
+}
+
+// Finds arg seeds whose type can't be turned into seed bytes: seeds must be bytes, a string,
+// a pubkey or an integer. `params.id` paths are followed through struct fields; seeds from
+// missing args are reported by `pda_seed_problems`.
+fn arg_seed_type_problems(idl: &anchor_lang_idl::types::Idl) -> Vec<String> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlSeed, IdlType, IdlTypeDefTy};
+
+    let seed_compatible = |ty: &IdlType| match generate::resolve_alias(idl, ty) {
+        IdlType::Bytes | IdlType::String | IdlType::Pubkey => true,
+        IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::U128
+        | IdlType::I128 => true,
+        IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            matches!(generate::resolve_alias(idl, inner), IdlType::U8)
+        }
+        _ => false,
+    };
+    // The type at a dotted path, or `None` when a segment isn't a named field
+    fn field_type<'a>(
+        idl: &'a anchor_lang_idl::types::Idl,
+        mut ty: &'a IdlType,
+        fields: std::str::Split<char>,
+    ) -> Option<&'a IdlType> {
+        for name in fields {
+            let IdlType::Defined { name: type_name, .. } = generate::resolve_alias(idl, ty) else {
+                return None;
+            };
+            let Some(IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(defined)),
+            }) = generate::find_type(idl, type_name).map(|def| &def.ty)
+            else {
+                return None;
+            };
+            ty = &defined.iter().find(|field| field.name == name)?.ty;
+        }
+        Some(ty)
+    }
+
+    let mut problems = Vec::new();
+    for instruction in &idl.instructions {
+        for account in flatten_accounts(&instruction.accounts) {
+            let seeds = account.pda.iter().flat_map(|pda| pda.seeds.iter().chain(pda.program.as_ref()));
+            for seed in seeds {
+                let IdlSeed::Arg(seed) = seed else {
+                    continue;
+                };
+                let mut path = seed.path.split('.');
+                let source = path.next().unwrap_or_default();
+                let Some(arg) = instruction.args.iter().find(|arg| arg.name == source) else {
+                    continue;
+                };
+                match field_type(idl, &arg.ty, path) {
+                    None => problems.push(format!(
+                        "PDA '{}' in '{}' seeds from '{}', which doesn't name a field of arg '{}' ({})",
+                        account.name,
+                        instruction.name,
+                        seed.path,
+                        arg.name,
+                        format_type(&arg.ty)
+                    )),
+                    Some(ty) if !seed_compatible(ty) => problems.push(format!(
+                        "PDA '{}' in '{}' seeds from arg '{}' of type {}, which can't be used as a seed",
+                        account.name,
+                        instruction.name,
+                        seed.path,
+                        format_type(ty)
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    problems
+}
+
+// Renders bytes as hex, with a quoted UTF-8 preview when they're printable text:
+// `0x7661756c74 ("vault")`
+fn render_bytes(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.is_empty() && text.chars().all(|c| !c.is_control()) => {
+            format!("0x{} ({:?})", hex, text)
+        }
+        _ => format!("0x{}", hex),
+    }
+}
+
+// Inserts thousands separators into an integer literal: `-1000000` -> `-1,000,000`
+fn group_digits(digits: &str) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", digits),
+    };
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, c) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("{}{}", sign, grouped)
+}
+
+// Bytes of a constant written as a byte string (`b"vault"`) or a list of bytes (`[1, 2]`)
+fn constant_bytes(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return items
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.trim_end_matches("u8").parse::<u8>().ok())
+            .collect();
+    }
+
+    let literal = value.strip_prefix("b\"")?.strip_suffix('"')?;
+    let mut bytes = Vec::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let byte = match chars.next()? {
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            '0' => 0,
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16).ok()?
+            }
+            c if c.is_ascii() => c as u8,
+            _ => return None,
+        };
+        bytes.push(byte);
+    }
+    Some(bytes)
+}
+
+// Outcome of checking a constant's value against its declared type
+enum ConstantCheck {
+    Valid,
+    // An expression such as `60 * 60` that can't be checked without evaluating it
+    NotLiteral,
+    Invalid(String),
+}
+
+fn check_constant(constant: &anchor_lang_idl::types::IdlConst) -> ConstantCheck {
+    use anchor_lang_idl::types::IdlType;
+
+    let value = constant.value.trim();
+    let ty = format_type(&constant.ty);
+    let invalid = |reason: &str| ConstantCheck::Invalid(format!("'{}' {} {}", value, reason, ty));
+
+    // Integer literals may carry digit separators and a type suffix, e.g. `1_000u64`
+    let integer = value.replace('_', "");
+    let integer = integer.strip_suffix(ty.as_str()).unwrap_or(&integer);
+    let is_integer_literal = integer
+        .strip_prefix('-')
+        .unwrap_or(integer)
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        && !integer.is_empty();
+
+    macro_rules! check_int {
+        ($int:ty) => {
+            if !is_integer_literal {
+                ConstantCheck::NotLiteral
+            } else if integer.parse::<$int>().is_ok() {
+                ConstantCheck::Valid
+            } else {
+                invalid("is out of range for")
+            }
+        };
+    }
+
+    match &constant.ty {
+        IdlType::U8 => check_int!(u8),
+        IdlType::I8 => check_int!(i8),
+        IdlType::U16 => check_int!(u16),
+        IdlType::I16 => check_int!(i16),
+        IdlType::U32 => check_int!(u32),
+        IdlType::I32 => check_int!(i32),
+        IdlType::U64 => check_int!(u64),
+        IdlType::I64 => check_int!(i64),
+        IdlType::U128 => check_int!(u128),
+        IdlType::I128 => check_int!(i128),
+        IdlType::Bool => match value {
+            "true" | "false" => ConstantCheck::Valid,
+            _ => invalid("is not a valid"),
+        },
+        IdlType::F32 | IdlType::F64 => {
+            let float = value.trim_end_matches(ty.as_str()).replace('_', "");
+            if float.parse::<f64>().is_ok() {
+                ConstantCheck::Valid
+            } else {
+                ConstantCheck::NotLiteral
+            }
+        }
+        IdlType::String => {
+            if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                ConstantCheck::Valid
+            } else {
+                invalid("is not a quoted string literal for")
+            }
+        }
+        IdlType::Pubkey => match pda::decode_pubkey(value.trim_matches('"')) {
+            Ok(_) => ConstantCheck::Valid,
+            Err(_) => ConstantCheck::NotLiteral,
+        },
+        IdlType::Bytes | IdlType::Vec(_) | IdlType::Array(_, _) => {
+            let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) else {
+                // Byte string literals (`b"seed"`) have no element count to check cheaply
+                return ConstantCheck::NotLiteral;
+            };
+            let count = items.split(',').filter(|item| !item.trim().is_empty()).count();
+            match &constant.ty {
+                IdlType::Array(_, anchor_lang_idl::types::IdlArrayLen::Value(len))
+                    if *len != count =>
+                {
+                    invalid(&format!("has {} element(s), which doesn't match", count))
+                }
+                _ => ConstantCheck::Valid,
+            }
+        }
+        _ => ConstantCheck::NotLiteral,
+    }
+}
+
+// Lists the reasons an event's type definition can't be Borsh-serialized
+fn event_serialization_problems(
+    idl: &anchor_lang_idl::types::Idl,
+    event_name: &str,
+) -> Vec<String> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+
+    let Some(type_def) = idl.types.iter().find(|ty| ty.name == event_name) else {
+        return vec!["type definition is missing".into()];
+    };
+
+    let mut problems = Vec::new();
+    let fields: Vec<(String, &IdlType)> = match &type_def.ty {
+        IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Named(fields)),
+        } => fields.iter().map(|f| (f.name.clone(), &f.ty)).collect(),
+        IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Tuple(types)),
+        } => types
+            .iter()
+            .enumerate()
+            .map(|(idx, ty)| (idx.to_string(), ty))
+            .collect(),
+        IdlTypeDefTy::Struct { fields: None } => Vec::new(),
+        _ => return vec!["type is not a struct".into()],
+    };
+
+    for (name, ty) in fields {
+        if let Some(reason) = unserializable_reason(idl, ty) {
+            problems.push(format!("field '{}' {}", name, reason));
+        }
+    }
+
+    problems
+}
+
+// Finds bare generics or defined types missing from `idl.types` within a field type
+fn unserializable_reason(
+    idl: &anchor_lang_idl::types::Idl,
+    ty: &anchor_lang_idl::types::IdlType,
+) -> Option<String> {
+    use anchor_lang_idl::types::{IdlGenericArg, IdlType};
+
+    match ty {
+        IdlType::Generic(name) => Some(format!("uses bare generic '{}'", name)),
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            unserializable_reason(idl, inner)
+        }
+        IdlType::Defined { name, generics } => {
+            if !idl.types.iter().any(|ty| &ty.name == name) {
+                return Some(format!("references unresolved type '{}'", name));
+            }
+            generics.iter().find_map(|generic| match generic {
+                IdlGenericArg::Type { ty } => unserializable_reason(idl, ty),
+                IdlGenericArg::Const { .. } => None,
+            })
+        }
+        _ => None,
+    }
+}
+
+// Switches that tune how `validate_idl` judges an IDL
+#[derive(Clone, Copy)]
+struct ValidateOptions<'a> {
+    strict: bool,
+    require_address: bool,
+    self_check: bool,
+    allow_empty_discriminator: bool,
+    consistent_accounts: bool,
+    allow_empty_instructions: bool,
+    max_type_depth: Option<usize>,
+    layout_baseline: Option<&'a anchor_lang_idl::types::Idl>,
+    verify_discriminators: bool,
+    namespaces: &'a [InstructionNamespace],
+    disc_prefix: Option<&'a str>,
+    disc_case: DiscriminatorCase,
+    // Sections to cross-check for colliding discriminators; empty skips the check
+    discriminator_sections: &'a [DiscriminatorSection],
+    min_spec: Option<SpecVersion>,
+    max_spec: Option<SpecVersion>,
+    format: OutputFormat,
+    fail_on_warning: bool,
+}
+
+// IDL spec versions `convert_idl` can parse
+const SUPPORTED_SPECS: &[&str] = &["0.1.0"];
+
+// A dotted `metadata.spec` version, compared numerically part by part
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SpecVersion([u64; 3]);
+
+impl std::fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [major, minor, patch] = self.0;
+        write!(f, "{}.{}.{}", major, minor, patch)
+    }
+}
+
+// Namespace in an instruction discriminator's `<namespace>:<name>` preimage
+#[derive(Clone)]
+struct InstructionNamespace {
+    // `None` applies to every instruction without its own entry
+    instruction: Option<String>,
+    namespace: String,
+}
+
+// Namespace Anchor uses for instructions declared in a `#[program]` module
+const DEFAULT_INSTRUCTION_NAMESPACE: &str = "global";
+
+// Accepts `<namespace>` or `<instruction>=<namespace>`
+fn parse_instruction_namespace(value: &str) -> Result<InstructionNamespace, String> {
+    let (instruction, namespace) = match value.split_once('=') {
+        Some((instruction, namespace)) => (Some(instruction.to_string()), namespace),
+        None => (None, value),
+    };
+    if namespace.is_empty() || namespace.contains(':') {
+        return Err(format!("'{}' is not a valid namespace", namespace));
+    }
+    Ok(InstructionNamespace {
+        instruction,
+        namespace: namespace.to_string(),
+    })
+}
+
+// Recomputes each discriminator as the first 8 bytes of sha256 over Anchor's preimage:
+// `<namespace>:<name>` for instructions, `account:<Name>` and `event:<Name>` otherwise.
+// An instruction's namespace comes from --namespace, then its `namespace` field in the raw
+// JSON `raw` (which the typed IDL doesn't keep), then `global`. `prefix` replaces all three
+// prefixes and `case` recases the name, for programs with their own convention.
+fn discriminator_mismatches(
+    idl: &anchor_lang_idl::types::Idl,
+    raw: &serde_json::Value,
+    namespaces: &[InstructionNamespace],
+    prefix: Option<&str>,
+    case: DiscriminatorCase,
+) -> Vec<String> {
+    use sha2::{Digest, Sha256};
+
+    let declared_namespace = |name: &str| -> Option<String> {
+        raw["instructions"]
+            .as_array()?
+            .iter()
+            .find(|instruction| instruction["name"] == name)?
+            .get("namespace")?
+            .as_str()
+            .map(str::to_string)
+    };
+    let namespace_for = |name: &str| -> String {
+        let flag = |instruction: Option<&str>| {
+            namespaces
+                .iter()
+                .rev()
+                .find(|entry| entry.instruction.as_deref() == instruction)
+                .map(|entry| entry.namespace.clone())
+        };
+        flag(Some(name))
+            .or_else(|| declared_namespace(name))
+            .or_else(|| flag(None))
+            .unwrap_or_else(|| DEFAULT_INSTRUCTION_NAMESPACE.to_string())
+    };
+
+    let expected: Vec<(String, String, &[u8])> = idl
+        .instructions
+        .iter()
+        .map(|instruction| {
+            (
+                format!("instruction '{}'", instruction.name),
+                format!(
+                    "{}:{}",
+                    prefix.map_or_else(|| namespace_for(&instruction.name), str::to_string),
+                    case.apply(&instruction.name)
+                ),
+                instruction.discriminator.as_slice(),
+            )
+        })
+        .chain(idl.accounts.iter().map(|account| {
+            (
+                format!("account '{}'", account.name),
+                format!("{}:{}", prefix.unwrap_or("account"), case.apply(&account.name)),
+                account.discriminator.as_slice(),
+            )
+        }))
+        .chain(idl.events.iter().map(|event| {
+            (
+                format!("event '{}'", event.name),
+                format!("{}:{}", prefix.unwrap_or("event"), case.apply(&event.name)),
+                event.discriminator.as_slice(),
+            )
+        }))
+        .collect();
+
+    expected
+        .into_iter()
+        .filter_map(|(item, preimage, actual)| {
+            let hash = Sha256::digest(preimage.as_bytes());
+            (actual != &hash[..8]).then(|| {
+                format!(
+                    "{} is {:?}, expected {:?} from \"{}\"",
+                    item,
+                    actual,
+                    &hash[..8],
+                    preimage
+                )
+            })
+        })
+        .collect()
+}
+
+// Accepts `MAJOR[.MINOR[.PATCH]]`, with missing parts read as 0
+fn parse_spec_version(value: &str) -> Result<SpecVersion, String> {
+    let parts: Vec<&str> = value.split('.').collect();
+    if parts.len() > 3 {
+        return Err(format!("'{}' has more than three version parts", value));
+    }
+    let mut version = [0; 3];
+    for (slot, part) in version.iter_mut().zip(&parts) {
+        *slot = part
+            .parse()
+            .map_err(|_| format!("'{}' is not a dotted numeric version", value))?;
+    }
+    Ok(SpecVersion(version))
+}
+
+// The input's `metadata.spec`, or `legacy` for an IDL without one
+fn input_spec(value: &serde_json::Value) -> String {
+    value["metadata"]["spec"]
+        .as_str()
+        .unwrap_or("legacy")
+        .to_string()
+}
+
+// Warns about specs this tool doesn't know, and enforces --min-spec/--max-spec
+fn check_spec(
+    value: &serde_json::Value,
+    options: ValidateOptions<'_>,
+    diagnostics: &mut Diagnostics,
+) {
+    let Some(spec) = value["metadata"]["spec"].as_str() else {
+        debug!("IDL has no metadata.spec; treating it as a legacy IDL");
+        if let Some(min) = options.min_spec {
+            diagnostics.error(
+                "spec-range",
+                format!(
+                    "IDL has no metadata.spec (legacy format), which is below --min-spec {}",
+                    min
+                ),
+            );
+        }
+        return;
+    };
+    debug!("IDL spec: {}", spec);
+
+    let version = parse_spec_version(spec).ok();
+    if !SUPPORTED_SPECS.contains(&spec) {
+        let newest = SUPPORTED_SPECS
+            .iter()
+            .filter_map(|supported| parse_spec_version(supported).ok())
+            .max();
+        let message = match (version, newest) {
+            (Some(version), Some(newest)) if version > newest => format!(
+                "IDL spec {} is newer than the newest supported spec {}; parsing may be incomplete",
+                spec, newest
+            ),
+            _ => format!(
+                "IDL spec '{}' is not recognized (supported: {}); parsing may be incomplete",
+                spec,
+                SUPPORTED_SPECS.join(", ")
+            ),
+        };
+        diagnostics.warning("unsupported-spec", message);
+    }
+
+    if options.min_spec.is_none() && options.max_spec.is_none() {
+        return;
+    }
+    let Some(version) = version else {
+        diagnostics.error(
+            "spec-range",
+            format!("IDL spec '{}' is not a dotted numeric version", spec),
+        );
+        return;
+    };
+    if let Some(min) = options.min_spec.filter(|min| version < *min) {
+        diagnostics.error(
+            "spec-range",
+            format!("IDL spec {} is below --min-spec {}", spec, min),
+        );
+    } else if let Some(max) = options.max_spec.filter(|max| version > *max) {
+        diagnostics.error(
+            "spec-range",
+            format!("IDL spec {} is above --max-spec {}", spec, max),
+        );
+    }
+}
+
+// Checks the raw JSON against the bundled schema, independent of `convert_idl`
+fn self_check_idl(value: &serde_json::Value, diagnostics: &mut Diagnostics) {
+    let errors = schema::validate(value);
+    if errors.is_empty() {
+        debug!("IDL conforms to the bundled schema (spec {})", schema::SCHEMA_IDL_SPEC);
+        return;
+    }
+
+    let details: Vec<String> = errors
+        .iter()
+        .map(|e| format!("  {}: {}", e.pointer, e.message))
+        .collect();
+    diagnostics.error(
+        "schema",
+        format!(
+            "IDL does not conform to the spec {} schema ({} error(s)):\n{}",
+            schema::SCHEMA_IDL_SPEC,
+            errors.len(),
+            details.join("\n")
+        ),
+    );
+}
+
+// Validates an IDL file against specification, rendering what the checks found in the chosen
+// format; a single error becomes the returned error, and several are summarized by count.
+// Hands back the parsed IDL so callers that go on to use it don't read the file again.
+fn validate_idl(
+    path: &PathBuf,
+    options: ValidateOptions<'_>,
+) -> Result<anchor_lang_idl::types::Idl> {
+    debug!("Validating IDL at: {:?}", path);
+
+    let mut diagnostics = Diagnostics::for_path(path.display());
+    // Only I/O failures come back as errors; problems with the IDL itself are diagnostics
+    let checked = check_idl(path, options, &mut diagnostics);
+
+    match options.format {
+        OutputFormat::Text => diagnostics.render_human(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diagnostics.to_json())?),
+    }
+    let idl = checked?;
+
+    // The diagnostics above already carry the details, so several errors are only summarized
+    let errors = diagnostics.count(Severity::Error);
+    if errors == 1 {
+        if let Some(error) = diagnostics.first_error() {
+            return Err(anyhow::anyhow!("{}", error.message));
+        }
+    }
+    if errors > 0 {
+        return Err(anyhow::anyhow!(
+            "IDL validation failed with {} error(s)",
+            errors
+        ));
+    }
+    let warnings = diagnostics.count(Severity::Warning);
+    if options.fail_on_warning && warnings > 0 {
+        return Err(anyhow::anyhow!(
+            "Validation produced {} warning(s) and --fail-on-warning is set",
+            warnings
+        ));
+    }
+
+    // An IDL that doesn't parse is always an error, so there is one here
+    let idl = idl.context("IDL validation failed")?;
+    info!("IDL validation successful!");
+    info!("Program: {}", idl.metadata.name);
+    info!("Version: {}", idl.metadata.version);
+    info!("Accounts: {}", idl.accounts.len());
+    info!("Instructions: {}", idl.instructions.len());
+    info!("Types: {}", idl.types.len());
+    Ok(idl)
+}
+
+// Runs every validation check, collecting all the errors rather than stopping at the first;
+// only an IDL that doesn't parse cuts the checks short. Returns the IDL when there were no errors.
+fn check_idl(
+    path: &PathBuf,
+    options: ValidateOptions<'_>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Option<anchor_lang_idl::types::Idl>> {
+    // Read the IDL file
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+
+    // Parsed once; the spec and schema checks, conversion and the namespace lookup share it.
+    // Input that isn't JSON skips the spec check and is reported when parsing below.
+    let parsed = serde_json::from_slice::<serde_json::Value>(&idl_bytes);
+    match &parsed {
+        Ok(value) => {
+            check_spec(value, options, diagnostics);
+            if options.self_check {
+                self_check_idl(value, diagnostics);
+            }
+        }
+        Err(e) if options.self_check => {
+            diagnostics.error("schema", format!("Failed to parse IDL as JSON: {}", e));
+        }
+        Err(_) => {}
+    }
+    
+    // Try to parse it as the current IDL format
+    let parsed = input_json(parsed)
+        .and_then(|value| Ok((idl_from_value(&value, &idl_bytes)?, value)));
+    let (idl, value) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            diagnostics.error("parse", format!("IDL validation failed: {}", e));
+            return Ok(None);
+        }
+    };
+
+    if idl.address.is_empty() {
+        if options.require_address {
+            diagnostics.error("missing-address", "IDL is missing program address");
+        } else {
+            diagnostics.push(
+                Severity::Info,
+                "missing-address",
+                "IDL has no program address, which --require-address=false allows",
+            );
+        }
+    }
+
+    if PLACEHOLDER_PROGRAM_IDS.contains(&idl.address.as_str()) {
+        if options.strict {
+            diagnostics.error(
+                "placeholder-address",
+                format!("IDL address {} is a known placeholder program ID", idl.address),
+            );
+        } else {
+            diagnostics.warning(
+                "placeholder-address",
+                format!(
+                    "IDL address {} is a known placeholder program ID; was the program ID set before building?",
+                    idl.address
+                ),
+            );
+        }
+    }
+    
+    if idl.metadata.name.is_empty() {
+        diagnostics.error("missing-name", "IDL is missing program name");
+    }
+    
+    if idl.metadata.version.is_empty() {
+        diagnostics.error("missing-version", "IDL is missing version");
+    }
+    
+    // Check for empty discriminators
+    let empty_discriminators = idl
+        .accounts
+        .iter()
+        .filter(|account| account.discriminator.is_empty())
+        .map(|account| format!("Account '{}'", account.name))
+        .chain(
+            idl.instructions
+                .iter()
+                .filter(|instruction| instruction.discriminator.is_empty())
+                .map(|instruction| format!("Instruction '{}'", instruction.name)),
+        )
+        .chain(
+            idl.events
+                .iter()
+                .filter(|event| event.discriminator.is_empty())
+                .map(|event| format!("Event '{}'", event.name)),
+        );
+    for item in empty_discriminators {
+        let message = format!("{} has an empty discriminator", item);
+        if options.allow_empty_discriminator {
+            diagnostics.warning("empty-discriminator", message);
+        } else {
+            diagnostics.error("empty-discriminator", message);
+        }
+    }
+
+    let collisions = discriminator_collisions(&idl, options.discriminator_sections);
+    if !collisions.is_empty() {
+        diagnostics.error(
+            "discriminator-collision",
+            format!("Ambiguous discriminators: {}", collisions.join("; ")),
+        );
+    }
+
+    if options.verify_discriminators {
+        let mismatches = discriminator_mismatches(
+            &idl,
+            &value,
+            options.namespaces,
+            options.disc_prefix,
+            options.disc_case,
+        );
+        if !mismatches.is_empty() {
+            diagnostics.error(
+                "discriminator-mismatch",
+                format!("Discriminators don't match their names: {}", mismatches.join("; ")),
+            );
+        }
+    }
+
+    let (seed_errors, seed_warnings) = pda_seed_problems(&idl);
+    if !seed_errors.is_empty() {
+        diagnostics.error(
+            "invalid-pda-seed",
+            format!("Invalid PDA seeds: {}", seed_errors.join("; ")),
+        );
+    }
+    for warning in seed_warnings {
+        if options.strict {
+            diagnostics.error("optional-pda-seed", format!("Invalid PDA seeds: {}", warning));
+        } else {
+            diagnostics.warning("optional-pda-seed", warning);
+        }
+    }
+
+    let mut invalid_constants = Vec::new();
+    for constant in &idl.constants {
+        match check_constant(constant) {
+            ConstantCheck::Valid => {}
+            ConstantCheck::NotLiteral => diagnostics.push(
+                Severity::Info,
+                "unchecked-constant",
+                format!(
+                    "Constant '{}' = {} isn't a literal and wasn't checked",
+                    constant.name, constant.value
+                ),
+            ),
+            ConstantCheck::Invalid(reason) => {
+                invalid_constants.push(format!("'{}' ({})", constant.name, reason))
+            }
+        }
+    }
+    if !invalid_constants.is_empty() {
+        diagnostics.error(
+            "invalid-constant",
+            format!(
+                "Constants with values that don't match their type: {}",
+                invalid_constants.join("; ")
+            ),
+        );
+    }
+
+    for problem in arg_seed_type_problems(&idl) {
+        diagnostics.warning("arg-seed-type", problem);
+    }
+
+    // A payer that isn't writable and signer makes `init` fail at runtime
+    for problem in payer_problems(&idl) {
+        if options.strict {
+            diagnostics.error("payer", format!("Payer check failed: {}", problem));
+        } else {
+            diagnostics.warning("payer", problem);
+        }
+    }
+
+    for problem in shadowed_account_names(&idl) {
+        diagnostics.warning("arg-shadows-account", problem);
+    }
+    for problem in pubkey_arg_accounts(&idl) {
+        diagnostics.warning("pubkey-arg-account", problem);
+    }
+
+    // Real instructions nearly always touch a signer or the program; an empty one is usually a stub
+    if !options.allow_empty_instructions {
+        for instruction in &idl.instructions {
+            if instruction.accounts.is_empty() && instruction.args.is_empty() {
+                diagnostics.warning(
+                    "empty-instruction",
+                    format!(
+                        "Instruction '{}' takes no accounts and no args; pass --allow-empty-instructions if that's intended",
+                        instruction.name
+                    ),
+                );
+            }
+        }
+    }
+
+    if let Some(max) = options.max_type_depth {
+        for problem in deep_types(&idl, max) {
+            diagnostics.warning("type-depth", problem);
+        }
+    }
+
+    if options.consistent_accounts {
+        for problem in inconsistent_accounts(&idl) {
+            diagnostics.warning("inconsistent-account", problem);
+        }
+    }
+
+    if let Some(baseline) = options.layout_baseline {
+        let reordered = reordered_zero_copy_fields(baseline, &idl);
+        if !reordered.is_empty() {
+            diagnostics.error(
+                "field-reorder",
+                format!("Zero-copy layouts changed: {}", reordered.join("; ")),
+            );
+        }
+    }
+
+    // Events are emitted with Borsh, so every field must resolve to a concrete type
+    let mut unserializable_events = Vec::new();
+    for event in &idl.events {
+        let problems = event_serialization_problems(&idl, &event.name);
+        if !problems.is_empty() {
+            unserializable_events
+                .push(format!("'{}' ({})", event.name, problems.join(", ")));
+        }
+    }
+    if !unserializable_events.is_empty() {
+        diagnostics.error(
+            "unserializable-event",
+            format!(
+                "Events with fields that can't be serialized: {}",
+                unserializable_events.join("; ")
+            ),
+        );
+    }
+    
+    if diagnostics.first_error().is_some() {
+        return Ok(None);
+    }
+    Ok(Some(idl))
+}
+
+// Applies the auto-fixes and writes the result, reporting each change
+fn fix_idl(input: &PathBuf, output: &PathBuf, allow_unsafe: bool) -> Result<()> {
+    debug!("Fixing IDL at {:?}", input);
+
+    let idl_bytes = read_input(input)
+        .with_context(|| format!("Failed to read IDL file at {:?}", input))?;
+    let (value, normalized, mut idl) = parse_idl_normalized(&idl_bytes)
+        .context("Failed to parse IDL")?;
+    // The fixed IDL is written in the current spec, which mustn't silently replace a legacy file
+    if value.pointer("/metadata/spec").is_none() && output == input {
+        return Err(anyhow::anyhow!(
+            "{:?} is a legacy IDL and --fix writes the current spec; pass --output to write the fixed IDL elsewhere",
+            input
+        ));
+    }
+
+    let mut report = fix::apply(&mut idl, allow_unsafe);
+    if normalized > 0 {
+        report
+            .applied
+            .insert(0, format!("normalized {} primitive type name(s)", normalized));
+    }
+
+    for change in &report.applied {
+        println!("  fixed: {}", change);
+    }
+    for change in &report.unsafe_applied {
+        println!("  fixed (unsafe): renamed {}", change);
+    }
+    if !report.unsafe_available.is_empty() {
+        println!("Unsafe fixes not applied (pass --fix-unsafe):");
+        for change in &report.unsafe_available {
+            println!("  rename {}", change);
+        }
+    }
+
+    let total = report.applied.len() + report.unsafe_applied.len();
+    if total == 0 && output == input {
+        info!("Nothing to fix in {:?}", input);
+        return Ok(());
+    }
+
+    let idl_json = anchor_lang_idl::serde_json::to_string_pretty(&idl)
+        .context("Failed to serialize fixed IDL to JSON")?;
+    write_output(output, idl_json)
+        .with_context(|| format!("Failed to write fixed IDL to {:?}", output))?;
+    info!("Applied {} fix(es) and wrote {:?}", total, output);
+
+    Ok(())
+}
+
+// Parses the IDL and prints how many entries each section has, skipping all other checks
+fn count_sections(path: &PathBuf, format: OutputFormat) -> Result<()> {
+    debug!("Counting IDL sections at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .map_err(|e| anyhow::anyhow!("IDL validation failed: {}", e))?;
+
+    let counts = [
+        ("instructions", "Instructions", idl.instructions.len()),
+        ("accounts", "Accounts", idl.accounts.len()),
+        ("events", "Events", idl.events.len()),
+        ("errors", "Errors", idl.errors.len()),
+        ("types", "Types", idl.types.len()),
+        ("constants", "Constants", idl.constants.len()),
+    ];
+
+    match format {
+        OutputFormat::Text => {
+            println!("Program: {} (v{})", idl.metadata.name, idl.metadata.version);
+            for (_, label, count) in counts {
+                println!("{}: {}", label, count);
+            }
+        }
+        OutputFormat::Json => {
+            let mut summary = serde_json::Map::new();
+            summary.insert("name".into(), idl.metadata.name.into());
+            summary.insert("version".into(), idl.metadata.version.into());
+            for (section, _, count) in counts {
+                summary.insert(section.into(), count.into());
+            }
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+    }
+
+    Ok(())
+}
+
+// Prints the `stats` metrics for one IDL, or one NDJSON line per IDL in a directory
+fn idl_stats(path: &PathBuf, format: StatsFormat) -> Result<()> {
+    debug!("Collecting stats for IDL at: {:?}", path);
+
+    if path.is_dir() {
+        if format != StatsFormat::Json {
+            return Err(anyhow::anyhow!(
+                "stats on a directory writes NDJSON and requires --format json"
+            ));
+        }
+        let mut stdout = std::io::stdout().lock();
+        return stats_dir(path, &mut stdout);
+    }
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let (value, idl) = parse_idl_json(&idl_bytes).context("Failed to parse IDL")?;
+    let spec = input_spec(&value);
+    let metrics = stats::collect(&idl);
+
+    match format {
+        StatsFormat::Text => {
+            println!("Program: {} (v{})", idl.metadata.name, idl.metadata.version);
+            println!("spec: {}", spec);
+            for metric in &metrics {
+                println!("{}: {}", metric.name, metric.value);
+            }
+        }
+        StatsFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&stats::to_json(&idl, &spec, &metrics))?
+        ),
+        StatsFormat::Prometheus => print!("{}", stats::to_prometheus(&idl, &spec, &metrics)),
+    }
+    Ok(())
+}
+
+// Writes one compact JSON line per `.json` file in `dir`, in name order, as each is read. A file
+// that can't be read or parsed gets a line with an `error` field and doesn't stop the rest.
+fn stats_dir(dir: &PathBuf, out: &mut impl std::io::Write) -> Result<()> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {:?}", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    for file in &files {
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parsed = read_input(file).and_then(|idl_bytes| parse_idl_json(&idl_bytes));
+        let line = match parsed {
+            Ok((value, idl)) => {
+                let mut line = stats::to_json(&idl, &input_spec(&value), &stats::collect(&idl));
+                line["file"] = name.into();
+                line
+            }
+            Err(e) => serde_json::json!({ "file": name, "error": format!("{:#}", e) }),
+        };
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// Checks that converting an IDL yields exactly the input JSON
+fn check_round_trip(path: &PathBuf) -> Result<()> {
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let original: serde_json::Value = serde_json::from_slice(&idl_bytes)
+        .context("Failed to parse IDL as JSON")?;
+
+    let converted_idl = parse_idl(&idl_bytes)
+        .context("Failed to convert IDL")?;
+    let converted = serde_json::to_value(&converted_idl)
+        .context("Failed to serialize converted IDL")?;
+
+    let changes = diff::diff(&original, &converted);
+    if changes.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("conversion changed {} value(s)", changes.len()))
+    }
+}
+
+// Validates every JSON file in a directory and prints a summary table
+fn validate_dir(dir: &PathBuf, round_trip: bool, options: ValidateOptions<'_>) -> Result<()> {
+    debug!("Validating IDLs in directory: {:?}", dir);
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {:?}", dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("No JSON files found in {:?}", dir));
+    }
+
+    let mut rows = Vec::new();
+    let mut failures = Vec::new();
+
+    for file in &files {
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let validation = match validate_idl(file, options) {
+            Ok(_) => "ok".to_string(),
+            Err(e) => {
+                failures.push(format!("{}: {:#}", name, e));
+                "FAIL".to_string()
+            }
+        };
+
+        let round_trip_result = if !round_trip {
+            "-".to_string()
+        } else {
+            match check_round_trip(file) {
+                Ok(()) => "ok".to_string(),
+                Err(e) => {
+                    failures.push(format!("{}: round-trip: {:#}", name, e));
+                    "FAIL".to_string()
+                }
+            }
+        };
+
+        rows.push((name, validation, round_trip_result));
+    }
+
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max("FILE".len());
+    println!("\n{:<width$}  {:<10}  ROUND-TRIP", "FILE", "VALIDATION", width = width);
+    for (name, validation, round_trip_result) in &rows {
+        println!("{:<width$}  {:<10}  {}", name, validation, round_trip_result, width = width);
+    }
+
+    if failures.is_empty() {
+        println!("\nAll {} IDL(s) passed", rows.len());
+        return Ok(());
+    }
+
+    println!("\nFailures:");
+    for failure in &failures {
+        println!("  {}", failure);
+    }
+
+    Err(anyhow::anyhow!(
+        "{} check(s) failed across {} IDL(s)",
+        failures.len(),
+        rows.len()
+    ))
+}
+
+// Hashes each account's ordered field names and types; docs are deliberately excluded
+// so only changes to the byte layout change the hash
+fn account_layout_hashes(
+    idl: &anchor_lang_idl::types::Idl,
+) -> serde_json::Map<String, serde_json::Value> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlTypeDefTy};
+    use sha2::{Digest, Sha256};
+
+    let mut hashes = serde_json::Map::new();
+    for account in &idl.accounts {
+        let Some(type_def) = idl.types.iter().find(|ty| ty.name == account.name) else {
+            continue;
+        };
+
+        let mut hasher = Sha256::new();
+        match &type_def.ty {
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } => {
+                for field in fields {
+                    hasher.update(format!("{}:{};", field.name, format_type(&field.ty)));
+                }
+            }
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Tuple(types)),
+            } => {
+                for (idx, ty) in types.iter().enumerate() {
+                    hasher.update(format!("{}:{};", idx, format_type(ty)));
+                }
+            }
+            IdlTypeDefTy::Struct { fields: None } => {}
+            // Non-struct accounts hash their whole definition minus docs
+            other => hasher.update(serde_json::to_string(other).unwrap_or_default()),
+        }
+
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        hashes.insert(account.name.clone(), hash.into());
+    }
+
+    hashes
+}
+
+// `declare_program!` reads `idls/<name>.json` in the current spec, so legacy IDLs are converted and
+// the output is serialized from the spec types, in their field order
+fn prepare_idl(path: &PathBuf, out_dir: &Path) -> Result<()> {
+    debug!("Preparing IDL at {:?} for declare_program!", path);
+
+    // Missing addresses and empty discriminators are validation errors
+    let options = ValidateOptions {
+        strict: false,
+        require_address: true,
+        self_check: false,
+        allow_empty_discriminator: false,
+        consistent_accounts: false,
+        allow_empty_instructions: false,
+        max_type_depth: None,
+        layout_baseline: None,
+        verify_discriminators: false,
+        namespaces: &[],
+        disc_prefix: None,
+        disc_case: DiscriminatorCase::AsIs,
+        discriminator_sections: &[],
+        min_spec: None,
+        max_spec: None,
+        format: OutputFormat::Text,
+        fail_on_warning: false,
+    };
+    let idl = validate_idl(path, options)?;
+
+    let idls_dir = out_dir.join("idls");
+    fs::create_dir_all(&idls_dir)
+        .with_context(|| format!("Failed to create {:?}", idls_dir))?;
+    let output_path = idls_dir.join(format!("{}.json", idl.metadata.name));
+    let idl_json = anchor_lang_idl::serde_json::to_string_pretty(&idl)
+        .context("Failed to serialize IDL to JSON")?;
+    write_output(&output_path, idl_json)
+        .with_context(|| format!("Failed to write IDL to {:?}", output_path))?;
+
+    info!(
+        "IDL written to {:?}; use it with `declare_program!({})`",
+        output_path, idl.metadata.name
+    );
+    Ok(())
+}
+
+fn extract_elf_idl(path: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
+    debug!("Extracting embedded IDL from: {:?}", path);
+
+    let elf_bytes = read_input(path)
+        .with_context(|| format!("Failed to read program at {:?}", path))?;
+    let idl_bytes = elf::embedded_idl(&elf_bytes)
+        .with_context(|| format!("Failed to extract IDL from {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Embedded IDL is not a valid IDL")?;
+    info!("Found embedded IDL for {} (v{})", idl.metadata.name, idl.metadata.version);
+
+    match output {
+        Some(output_path) => {
+            fs::write(output_path, &idl_bytes)
+                .with_context(|| format!("Failed to write IDL to {:?}", output_path))?;
+            info!("IDL written to: {:?}", output_path);
+        }
+        None => println!("{}", String::from_utf8_lossy(&idl_bytes)),
+    }
+
+    Ok(())
+}
+
+fn split_idl(path: &PathBuf, output_dir: &Path) -> Result<()> {
+    debug!("Splitting IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let (interface, types) = split::split(&idl)?;
+    // Refuse to write a split that wouldn't merge back into the same IDL
+    let merged = split::merge(interface.clone(), types.clone())?;
+    if serde_json::to_value(&merged)? != serde_json::to_value(&idl)? {
+        return Err(anyhow::anyhow!("Split IDL doesn't merge back into the original"));
+    }
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory {:?}", output_dir))?;
+    for (name, value) in [(split::TYPES_FILE, &types), ("interface.json", &interface)] {
+        let file = output_dir.join(name);
+        fs::write(&file, serde_json::to_string_pretty(value)?)
+            .with_context(|| format!("Failed to write {:?}", file))?;
+    }
+
+    info!(
+        "Split {} type(s) into {:?}",
+        idl.types.len(),
+        output_dir.join(split::TYPES_FILE)
+    );
+    Ok(())
+}
+
+fn merge_idl(interface_path: &PathBuf, types_path: Option<&PathBuf>, output: Option<&PathBuf>) -> Result<()> {
+    let read_json = |path: &Path| -> Result<serde_json::Value> {
+        let bytes = read_input(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("Failed to parse {:?} as JSON", path))
+    };
+
+    let interface = read_json(interface_path)?;
+    let types_path = match types_path {
+        Some(types_path) => types_path.clone(),
+        None => {
+            let recorded = split::types_file(&interface).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{:?} doesn't record a types file in metadata.split; pass --types",
+                    interface_path
+                )
+            })?;
+            interface_path.with_file_name(recorded)
+        }
+    };
+    debug!("Merging {:?} with {:?}", interface_path, types_path);
+
+    let idl = split::merge(interface, read_json(&types_path)?)?;
+    let idl_json = serde_json::to_string_pretty(&idl)?;
+
+    match output {
+        Some(output_path) => {
+            fs::write(output_path, idl_json)
+                .with_context(|| format!("Failed to write merged IDL to {:?}", output_path))?;
+            info!("Merged IDL written to: {:?}", output_path);
+        }
+        None => println!("{}", idl_json),
+    }
+
+    Ok(())
+}
+
+// Replaces composite groups with their accounts. With `prefix`, each account is renamed
+// `<group>_<name>`, along with seed paths and relations that point at its siblings.
+fn flatten_composite_accounts(
+    accounts: Vec<anchor_lang_idl::types::IdlInstructionAccountItem>,
+    prefix: bool,
+) -> Vec<anchor_lang_idl::types::IdlInstructionAccountItem> {
+    use anchor_lang_idl::types::{IdlInstructionAccountItem, IdlSeed};
+
+    let mut flat = Vec::new();
+    for item in accounts {
+        let composite = match item {
+            IdlInstructionAccountItem::Single(_) => {
+                flat.push(item);
+                continue;
+            }
+            IdlInstructionAccountItem::Composite(composite) => composite,
+        };
+        let mut members = flatten_composite_accounts(composite.accounts, prefix);
+        if prefix {
+            let renames: std::collections::HashMap<String, String> = members
+                .iter()
+                .filter_map(|member| match member {
+                    IdlInstructionAccountItem::Single(account) => Some((
+                        account.name.clone(),
+                        format!("{}_{}", composite.name, account.name),
+                    )),
+                    IdlInstructionAccountItem::Composite(_) => None,
+                })
+                .collect();
+            for member in &mut members {
+                let IdlInstructionAccountItem::Single(account) = member else {
+                    continue;
+                };
+                account.name = renames[&account.name].clone();
+                for relation in &mut account.relations {
+                    if let Some(new) = renames.get(relation.as_str()) {
+                        *relation = new.clone();
+                    }
+                }
+                if let Some(pda) = &mut account.pda {
+                    for seed in pda.seeds.iter_mut().chain(pda.program.as_mut()) {
+                        if let IdlSeed::Account(seed) = seed {
+                            fix::rename_path(&mut seed.path, &renames);
+                        }
+                    }
+                }
+            }
+        }
+        flat.extend(members);
+    }
+    flat
+}
+
+// Pretty-prints the IDL with its top-level keys in the order the input used them, and the named
+// items of each section (instructions, types, ...) in their input order; items and keys the
+// input didn't have keep their usual place after the ones it did. Nested objects keep the
+// usual field order, so this is byte-identical to plain output when the input was already in it.
+// `raw` is the parsed input; the key order is read from its text, `input`, which parsing loses.
+fn to_json_in_input_order(
+    idl: &mut anchor_lang_idl::types::Idl,
+    raw: &serde_json::Value,
+    input: &[u8],
+    layout_hashes: Option<serde_json::Map<String, serde_json::Value>>,
+) -> serde_json::Result<String> {
+    use serde_json::to_string_pretty as pretty;
+
+    // Legacy names are converted to snake_case, so either spelling finds its input position
+    let order = |section: &str| {
+        let names: Vec<String> = raw[section]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item["name"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        move |name: &str| {
+            names
+                .iter()
+                .position(|input| input == name || generate::to_snake_case(input) == name)
+                .unwrap_or(usize::MAX)
+        }
+    };
+    let position = order("instructions");
+    idl.instructions.sort_by_key(|item| position(&item.name));
+    let position = order("accounts");
+    idl.accounts.sort_by_key(|item| position(&item.name));
+    let position = order("events");
+    idl.events.sort_by_key(|item| position(&item.name));
+    let position = order("errors");
+    idl.errors.sort_by_key(|item| position(&item.name));
+    let position = order("types");
+    idl.types.sort_by_key(|item| position(&item.name));
+    let position = order("constants");
+    idl.constants.sort_by_key(|item| position(&item.name));
+
+    // Keys the default serialization emits (empty sections are skipped), in its order
+    let emitted: Vec<String> = match serde_json::to_value(&*idl)? {
+        serde_json::Value::Object(object) => {
+            const ORDER: &[&str] = &[
+                "address", "metadata", "docs", "instructions", "accounts", "events", "errors",
+                "types", "constants",
+            ];
+            ORDER
+                .iter()
+                .filter(|key| object.contains_key(**key))
+                .map(|key| key.to_string())
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    // Legacy IDLs keep the name and version at the top level; they now live in `metadata`
+    let input_keys: Vec<String> = top_level_keys(input)
+        .into_iter()
+        .map(|key| match key.as_str() {
+            "name" | "version" => "metadata".to_string(),
+            _ => key,
+        })
+        .collect();
+    let mut keys: Vec<&String> = Vec::new();
+    for key in input_keys.iter().chain(&emitted) {
+        if emitted.contains(key) && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let mut out = String::from("{");
+    for (idx, key) in keys.iter().enumerate() {
+        let value = match key.as_str() {
+            "address" => pretty(&idl.address)?,
+            "metadata" => match &layout_hashes {
+                Some(hashes) => {
+                    let mut metadata = serde_json::to_value(&idl.metadata)?;
+                    metadata["layoutHashes"] = hashes.clone().into();
+                    pretty(&metadata)?
+                }
+                None => pretty(&idl.metadata)?,
+            },
+            "docs" => pretty(&idl.docs)?,
+            "instructions" => pretty(&idl.instructions)?,
+            "accounts" => pretty(&idl.accounts)?,
+            "events" => pretty(&idl.events)?,
+            "errors" => pretty(&idl.errors)?,
+            "types" => pretty(&idl.types)?,
+            _ => pretty(&idl.constants)?,
+        };
+        let separator = if idx == 0 { "" } else { "," };
+        out.push_str(&format!(
+            "{}\n  {}: {}",
+            separator,
+            pretty(key)?,
+            value.replace('\n', "\n  ")
+        ));
+    }
+    out.push_str("\n}");
+    Ok(out)
+}
+
+// Re-indents JSON text like `to_string_pretty` down to `depth` levels of nesting, writing anything
+// deeper compactly on one line. Works on the text rather than a `Value` so key order is kept.
+fn limit_pretty_depth(json: &str, depth: usize) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut level = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = json.chars().peekable();
+    let newline = |out: &mut String, level: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(level));
+    };
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                out.push(c);
+                level += 1;
+                // Empty containers stay `{}` / `[]`
+                while chars.next_if(|next| next.is_whitespace()).is_some() {}
+                if matches!(chars.peek(), Some('}' | ']')) {
+                    out.push(chars.next().unwrap_or_default());
+                    level -= 1;
+                } else if level <= depth {
+                    newline(&mut out, level);
+                }
+            }
+            '}' | ']' => {
+                if level <= depth {
+                    newline(&mut out, level - 1);
+                }
+                level = level.saturating_sub(1);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                if level <= depth {
+                    newline(&mut out, level);
+                }
+            }
+            ':' => out.push_str(if level <= depth { ": " } else { ":" }),
+            c if c.is_whitespace() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Top-level keys of a JSON object in the order they're written; `serde_json::Value` sorts its
+// keys, so they're scanned straight from the text. Anything that isn't an object yields nothing.
+fn top_level_keys(json: &[u8]) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut depth = 0usize;
+    let mut idx = 0;
+    // The start of the string being read, and whether the next string at depth 1 is a key
+    let mut string_start = None;
+    let mut expect_key = false;
+    while idx < json.len() {
+        let byte = json[idx];
+        if let Some(start) = string_start {
+            match byte {
+                b'\\' => idx += 1,
+                b'"' => {
+                    string_start = None;
+                    if depth == 1 && expect_key {
+                        if let Ok(key) = serde_json::from_slice::<String>(&json[start..=idx]) {
+                            keys.push(key);
+                        }
+                        expect_key = false;
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            match byte {
+                b'"' => string_start = Some(idx),
+                b'{' | b'[' => {
+                    depth += 1;
+                    expect_key = depth == 1;
+                }
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                b',' if depth == 1 => expect_key = true,
+                _ => {}
+            }
+        }
+        idx += 1;
+    }
+    keys
+}
+
+// Empties every top-level section `keep` rejects
+fn filter_sections(idl: &mut anchor_lang_idl::types::Idl, keep: impl Fn(IdlSection) -> bool) {
+    if !keep(IdlSection::Docs) {
+        idl.docs.clear();
+    }
+    if !keep(IdlSection::Instructions) {
+        idl.instructions.clear();
+    }
+    if !keep(IdlSection::Accounts) {
+        idl.accounts.clear();
+    }
+    if !keep(IdlSection::Events) {
+        idl.events.clear();
+    }
+    if !keep(IdlSection::Errors) {
+        idl.errors.clear();
+    }
+    if !keep(IdlSection::Types) {
+        idl.types.clear();
+    }
+    if !keep(IdlSection::Constants) {
+        idl.constants.clear();
+    }
+}
+
+// Blanks the program address and its deployments, leaving a template IDL that's not tied to
+// one cluster's program
+fn clear_address(idl: &mut anchor_lang_idl::types::Idl) {
+    idl.address.clear();
+    idl.metadata.deployments = None;
+}
+
+// Keeps the named instructions, the accounts their PDA seeds or args name and every type those
+// reach. Events and constants aren't tied to an instruction and go; errors can come from any
+// instruction, so they all stay.
+fn select_instructions(idl: &mut anchor_lang_idl::types::Idl, names: &[String]) -> Result<()> {
+    use anchor_lang_idl::types::IdlSeed;
+
+    for name in names {
+        if !idl.instructions.iter().any(|instruction| &instruction.name == name) {
+            return Err(anyhow::anyhow!("Instruction '{}' is not in the IDL", name));
+        }
+    }
+    idl.instructions.retain(|instruction| names.contains(&instruction.name));
+    idl.events.clear();
+    idl.constants.clear();
+
+    let mut seed_accounts = std::collections::HashSet::new();
+    for instruction in &idl.instructions {
+        for account in flatten_accounts(&instruction.accounts) {
+            let Some(pda) = &account.pda else { continue };
+            for seed in pda.seeds.iter().chain(pda.program.as_ref()) {
+                if let IdlSeed::Account(seed) = seed {
+                    seed_accounts.extend(seed.account.clone());
+                }
+            }
+        }
+    }
+
+    // Types the args reach decide which accounts stay; the accounts then pull in their own types
+    let types = idl.types.clone();
+    let accounts = std::mem::take(&mut idl.accounts);
+    fix::remove_unused_types(idl);
+    idl.accounts = accounts
+        .into_iter()
+        .filter(|account| {
+            seed_accounts.contains(&account.name)
+                || idl.types.iter().any(|def| def.name == account.name)
+        })
+        .collect();
+    idl.types = types;
+    fix::remove_unused_types(idl);
+    Ok(())
+}
+
+fn query_idl(path: &PathBuf, query: &str, raw: bool) -> Result<()> {
+    debug!("Querying {:?} for {}", path, query);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let value: serde_json::Value = serde_json::from_slice(&idl_bytes)
+        .context("Failed to parse IDL as JSON")?;
+
+    let pointer = if query.starts_with('$') {
+        json_path_to_pointer(query)?
+    } else {
+        query.to_string()
+    };
+    let matched = value
+        .pointer(&pointer)
+        .ok_or_else(|| anyhow::anyhow!("'{}' doesn't match anything in the IDL", query))?;
+
+    match matched {
+        serde_json::Value::String(text) if raw => println!("{}", text),
+        other => println!("{}", serde_json::to_string_pretty(other)?),
+    }
+    Ok(())
+}
+
+// Translates `$.instructions[0]['name']` into the JSON Pointer `/instructions/0/name`
+fn json_path_to_pointer(path: &str) -> Result<String> {
+    let invalid = || anyhow::anyhow!("'{}' is not a supported JSONPath", path);
+    let escape = |segment: &str| segment.replace('~', "~0").replace('/', "~1");
+
+    let mut pointer = String::new();
+    let mut rest = path.strip_prefix('$').ok_or_else(invalid)?;
+    while !rest.is_empty() {
+        let (segment, tail) = if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            (tail[..end].to_string(), &tail[end..])
+        } else if let Some(tail) = rest.strip_prefix("['") {
+            let end = tail.find("']").ok_or_else(invalid)?;
+            (tail[..end].to_string(), &tail[end + 2..])
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']').ok_or_else(invalid)?;
+            let index: usize = tail[..end].trim().parse().map_err(|_| invalid())?;
+            (index.to_string(), &tail[end + 1..])
+        } else {
+            return Err(invalid());
+        };
+        if segment.is_empty() {
+            return Err(invalid());
+        }
+        pointer.push('/');
+        pointer.push_str(&escape(&segment));
+        rest = tail;
+    }
+    Ok(pointer)
+}
+
+// Kinds of reference `usage` groups by, declared in display order
+#[derive(Clone, Copy, PartialEq)]
+enum UsageKind {
+    Arg,
+    Returns,
+    Account,
+    Event,
+    AccountField,
+    EventField,
+    TypeField,
+    Constant,
+}
+
+impl UsageKind {
+    const ALL: [UsageKind; 8] = [
+        UsageKind::Arg,
+        UsageKind::Returns,
+        UsageKind::Account,
+        UsageKind::Event,
+        UsageKind::AccountField,
+        UsageKind::EventField,
+        UsageKind::TypeField,
+        UsageKind::Constant,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            UsageKind::Arg => "args",
+            UsageKind::Returns => "returns",
+            UsageKind::Account => "accounts",
+            UsageKind::Event => "events",
+            UsageKind::AccountField => "account fields",
+            UsageKind::EventField => "event fields",
+            UsageKind::TypeField => "type fields",
+            UsageKind::Constant => "constants",
+        }
+    }
+}
+
+// The unused-type detector's dependency walk, inverted: for each type, the places naming it
+fn type_usage(
+    idl: &anchor_lang_idl::types::Idl,
+) -> std::collections::BTreeMap<String, Vec<(UsageKind, String)>> {
+    use anchor_lang_idl::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+
+    let mut usage: std::collections::BTreeMap<String, Vec<(UsageKind, String)>> =
+        std::collections::BTreeMap::new();
+    let mut record = |ty: &IdlType, kind: UsageKind, site: String| {
+        let mut names = Vec::new();
+        fix::referenced_types(ty, &mut names);
+        names.sort();
+        names.dedup();
+        for name in names {
+            usage.entry(name).or_default().push((kind, site.clone()));
+        }
+    };
+
+    for instruction in &idl.instructions {
+        for arg in &instruction.args {
+            record(&arg.ty, UsageKind::Arg, format!("{}.{}", instruction.name, arg.name));
+        }
+        if let Some(returns) = &instruction.returns {
+            record(returns, UsageKind::Returns, instruction.name.clone());
+        }
+    }
+    for def in &idl.types {
+        let kind = if idl.accounts.iter().any(|account| account.name == def.name) {
+            UsageKind::AccountField
+        } else if idl.events.iter().any(|event| event.name == def.name) {
+            UsageKind::EventField
+        } else {
+            UsageKind::TypeField
+        };
+        let mut record_fields = |owner: &str, fields: &IdlDefinedFields| match fields {
+            IdlDefinedFields::Named(fields) => {
+                for field in fields {
+                    record(&field.ty, kind, format!("{}.{}", owner, field.name));
+                }
+            }
+            IdlDefinedFields::Tuple(types) => {
+                for (idx, ty) in types.iter().enumerate() {
+                    record(ty, kind, format!("{}.{}", owner, idx));
+                }
+            }
+        };
+        match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                if let Some(fields) = fields {
+                    record_fields(&def.name, fields);
+                }
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                for variant in variants {
+                    if let Some(fields) = &variant.fields {
+                        record_fields(&format!("{}::{}", def.name, variant.name), fields);
+                    }
+                }
+            }
+            IdlTypeDefTy::Type { alias } => record(alias, kind, def.name.clone()),
+        }
+    }
+    for constant in &idl.constants {
+        record(&constant.ty, UsageKind::Constant, constant.name.clone());
+    }
+
+    // Account data and events are laid out by the type of the same name
+    for account in &idl.accounts {
+        usage
+            .entry(account.name.clone())
+            .or_default()
+            .push((UsageKind::Account, account.name.clone()));
+    }
+    for event in &idl.events {
+        usage
+            .entry(event.name.clone())
+            .or_default()
+            .push((UsageKind::Event, event.name.clone()));
+    }
+    usage
+}
+
+fn display_usage(path: &PathBuf, only: Option<&String>) -> Result<()> {
+    debug!("Collecting type usage from IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    if let Some(name) = only {
+        if !idl.types.iter().any(|def| &def.name == name) {
+            return Err(anyhow::anyhow!("Type '{}' is not defined in the IDL", name));
+        }
+    }
+
+    let usage = type_usage(&idl);
+    for def in idl.types.iter().filter(|def| only.is_none_or(|name| &def.name == name)) {
+        println!("\n{}", def.name);
+        let sites = usage.get(&def.name).map(Vec::as_slice).unwrap_or_default();
+        if sites.is_empty() {
+            println!("  (unused)");
+            continue;
+        }
+        for kind in UsageKind::ALL {
+            let names: Vec<&str> = sites
+                .iter()
+                .filter(|(site_kind, _)| *site_kind == kind)
+                .map(|(_, site)| site.as_str())
+                .collect();
+            if !names.is_empty() {
+                println!("  {}: {}", kind.label(), names.join(", "));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Reads a discriminator given as comma-separated decimal bytes, hex or base58, in that order
+fn parse_discriminator(value: &str) -> Result<Vec<u8>> {
+    let value = value.trim();
+    if value.contains(',') {
+        return value
+            .split(',')
+            .map(|byte| {
+                byte.trim()
+                    .parse::<u8>()
+                    .with_context(|| format!("'{}' is not a byte (0-255)", byte.trim()))
+            })
+            .collect();
+    }
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if value.starts_with("0x")
+        || (!hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) && hex.len().is_multiple_of(2))
+    {
+        return pda::decode_hex(hex);
+    }
+    bs58::decode(value)
+        .into_vec()
+        .with_context(|| format!("'{}' is not hex, base58 or comma-separated bytes", value))
+}
+
+fn whois(path: &PathBuf, discriminator: &str, prefix: bool) -> Result<()> {
+    debug!("Looking up discriminator {} in IDL at: {:?}", discriminator, path);
+
+    let bytes = parse_discriminator(discriminator)?;
+    if bytes.is_empty() {
+        return Err(anyhow::anyhow!("Discriminator is empty"));
+    }
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let matches = discriminator_matches(&idl, &bytes, prefix);
+    if matches.is_empty() {
+        return Err(anyhow::anyhow!("no match for discriminator {}", render_bytes(&bytes)));
+    }
+    for line in matches {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// `<kind> <name> (<discriminator>)` for each instruction, account and event whose discriminator
+// is `bytes`, or starts with them when `prefix` is set
+fn discriminator_matches(
+    idl: &anchor_lang_idl::types::Idl,
+    bytes: &[u8],
+    prefix: bool,
+) -> Vec<String> {
+    let items = idl
+        .instructions
+        .iter()
+        .map(|ix| ("instruction", &ix.name, &ix.discriminator))
+        .chain(idl.accounts.iter().map(|account| ("account", &account.name, &account.discriminator)))
+        .chain(idl.events.iter().map(|event| ("event", &event.name, &event.discriminator)));
+
+    items
+        .filter(|(_, _, item_discriminator)| {
+            if prefix {
+                item_discriminator.starts_with(bytes)
+            } else {
+                item_discriminator.as_slice() == bytes
+            }
+        })
+        .map(|(kind, name, item_discriminator)| {
+            format!("{} {} ({})", kind, name, render_bytes(item_discriminator))
+        })
+        .collect()
+}
+
+fn display_constants(path: &PathBuf, grouped: bool) -> Result<()> {
+    use anchor_lang_idl::types::IdlType;
+
+    debug!("Extracting constants from IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    println!("\nProgram: {} (v{})", idl.metadata.name, idl.metadata.version);
+    println!("\nConstants ({}):", idl.constants.len());
+    for constant in &idl.constants {
+        let is_integer = matches!(
+            constant.ty,
+            IdlType::U8
+                | IdlType::I8
+                | IdlType::U16
+                | IdlType::I16
+                | IdlType::U32
+                | IdlType::I32
+                | IdlType::U64
+                | IdlType::I64
+                | IdlType::U128
+                | IdlType::I128
+        );
+        let value = if grouped && is_integer && matches!(check_constant(constant), ConstantCheck::Valid) {
+            let digits = constant.value.trim().replace('_', "");
+            let digits = digits.strip_suffix(format_type(&constant.ty).as_str()).unwrap_or(&digits);
+            group_digits(digits)
+        } else {
+            constant.value.clone()
+        };
+        println!("\n  {}: {} = {}", constant.name, format_type(&constant.ty), value);
+
+        let is_bytes = match &constant.ty {
+            IdlType::Bytes => true,
+            IdlType::Vec(inner) | IdlType::Array(inner, _) => matches!(**inner, IdlType::U8),
+            _ => false,
+        };
+        if let Some(bytes) = constant_bytes(&constant.value).filter(|_| is_bytes) {
+            println!("    bytes: {}", render_bytes(&bytes));
+        }
+        for doc in &constant.docs {
+            println!("    {}", doc);
+        }
+    }
+
+    Ok(())
+}
+
+fn diff_idls(
+    old_path: &PathBuf,
+    new_path: &PathBuf,
+    format: DiffFormat,
+    check_only: bool,
+    ignore: &[DiffIgnore],
+    verbose: bool,
+) -> Result<()> {
+    debug!("Diffing IDL {:?} against {:?}", new_path, old_path);
+
+    let mut canonical = Vec::new();
+    for path in [old_path, new_path] {
+        let idl_bytes = read_input(path)
+            .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+        let idl = parse_idl(&idl_bytes)
+            .with_context(|| format!("Failed to parse IDL at {:?}", path))?;
+        canonical.push(diff::canonicalize(&idl)?);
+    }
+    // What the ignored aspects hide, reported on its own under --verbose
+    let ignored: Vec<diff::Change> = if ignore.is_empty() {
+        Vec::new()
+    } else {
+        diff::diff(&canonical[0], &canonical[1])
+            .into_iter()
+            .filter(|change| ignore.iter().any(|aspect| aspect.covers(change.path())))
+            .collect()
+    };
+    for value in &mut canonical {
+        for aspect in ignore {
+            aspect.strip(value);
+        }
+    }
+    let (old, new) = (&canonical[0], &canonical[1]);
+    let changes = diff::diff(old, new);
+
+    if check_only {
+        let breaking: Vec<diff::Change> =
+            changes.into_iter().filter(|change| change.is_breaking()).collect();
+        if breaking.is_empty() {
+            return Ok(());
+        }
+        diff::print_changes(&breaking);
+        return Err(anyhow::anyhow!(
+            "{} breaking change(s) from {:?}",
+            breaking.len(),
+            old_path
+        ));
+    }
+
+    match format {
+        DiffFormat::Human => {
+            if changes.is_empty() {
+                println!("No differences");
+            } else {
+                diff::print_changes(&changes);
+                let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+                println!("{} of them breaking", breaking);
+            }
+            if verbose && !ignored.is_empty() {
+                println!("\nIgnored differences:");
+                diff::print_changes(&ignored);
+            }
+        }
+        DiffFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&diff::changes_to_json(&changes))?);
+        }
+        DiffFormat::Unified => {
+            print!(
+                "{}",
+                diff::unified(
+                    old,
+                    new,
+                    &old_path.display().to_string(),
+                    &new_path.display().to_string()
+                )?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Canonicalizes an IDL and compares it against (or updates) a stored baseline
+fn snapshot_idl(input: &PathBuf, baseline: &PathBuf, update: bool) -> Result<()> {
+    debug!("Snapshotting IDL at {:?} against {:?}", input, baseline);
+
+    let idl_bytes = read_input(input)
+        .with_context(|| format!("Failed to read IDL file at {:?}", input))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+    let current = diff::canonicalize(&idl)?;
+
+    if update {
+        let snapshot_json = serde_json::to_string_pretty(&current)
+            .context("Failed to serialize snapshot to JSON")?;
+        write_output(baseline, snapshot_json)
+            .with_context(|| format!("Failed to write baseline to {:?}", baseline))?;
+
+        info!("Updated baseline at {:?}", baseline);
+        return Ok(());
+    }
+
+    if !baseline.exists() {
+        return Err(anyhow::anyhow!(
+            "Baseline {:?} does not exist (run with --update to create it)",
+            baseline
+        ));
+    }
+
+    let baseline_bytes = read_input(baseline)
+        .with_context(|| format!("Failed to read baseline at {:?}", baseline))?;
+    let baseline_idl = parse_idl(&baseline_bytes)
+        .context("Failed to parse baseline IDL")?;
+    let expected = diff::canonicalize(&baseline_idl)?;
+
+    let changes = diff::diff(&expected, &current);
+    if changes.is_empty() {
+        info!("IDL matches baseline {:?}", baseline);
+        return Ok(());
+    }
+
+    println!("\nSnapshot mismatch against {:?}:\n", baseline);
+    diff::print_changes(&changes);
+
+    Err(anyhow::anyhow!(
+        "IDL does not match baseline ({} change(s))",
+        changes.len()
+    ))
+}
+
+// Derives a PDA address using seeds from the IDL plus any values passed on the command line
+fn compute_pda(
+    path: &PathBuf,
+    instruction: &str,
+    account: &str,
+    args: &[(String, String)],
+    account_keys: &[(String, String)],
+) -> Result<()> {
+    debug!("Computing PDA for {}.{} from IDL at: {:?}", instruction, account, path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let inputs = pda::SeedInputs {
+        args: args.iter().cloned().collect(),
+        accounts: account_keys.iter().cloned().collect(),
+    };
+    let derived = pda::derive_pda(&idl, instruction, account, &inputs)?;
+
+    println!("Account: {}.{}", instruction, account);
+    println!("Program: {}", derived.program_id);
+    println!("Address: {}", derived.address);
+    println!("Bump:    {} (canonical)", derived.bump);
+
+    Ok(())
+}
+
+// Fetches the on-chain IDL for a program and diffs it against the local one
+fn verify_deployed(path: &PathBuf, address: Option<&String>, client: &rpc::Client) -> Result<()> {
+    debug!("Verifying IDL at {:?} against deployed program", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let local_idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let address = address.unwrap_or(&local_idl.address);
+    let deployed_bytes = client.fetch_idl(address)?
+        .ok_or_else(|| anyhow::anyhow!("Program {} has no IDL published on-chain", address))?;
+    let deployed_idl = parse_idl(&deployed_bytes)
+        .context("Failed to parse on-chain IDL")?;
+
+    let changes = diff::diff(
+        &diff::canonicalize(&deployed_idl)?,
+        &diff::canonicalize(&local_idl)?,
+    );
+    if changes.is_empty() {
+        info!("Local IDL matches the IDL deployed for {}", address);
+        return Ok(());
+    }
+
+    println!("\nLocal IDL differs from the IDL deployed for {}:\n", address);
+    diff::print_changes(&changes);
+
+    Err(anyhow::anyhow!(
+        "Local IDL has drifted from the deployed IDL ({} change(s))",
+        changes.len()
+    ))
+}
+
+fn check_registry(
+    path: &PathBuf,
+    registry_url: &str,
+    program: &str,
+    retries: u32,
+    retry_delay: std::time::Duration,
+) -> Result<()> {
+    debug!("Checking IDL at {:?} against {} in {}", path, program, registry_url);
+
+    if program.is_empty()
+        || !program
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(anyhow::anyhow!(
+            "Program '{}' must be a name or address made of letters, digits, '_' and '-'",
+            program
+        ));
+    }
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let local_idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let client = rpc::Client::new(registry_url, retries, retry_delay);
+    let published = match client.get(&format!("programs/{}/idl", program)) {
+        Ok(Some(published)) => published,
+        Ok(None) => {
+            return Err(anyhow::anyhow!(
+                "Program '{}' has no IDL published to the registry at {}",
+                program,
+                registry_url
+            ))
+        }
+        Err(e) => {
+            let unreachable = matches!(
+                e.downcast_ref::<ureq::Error>(),
+                Some(ureq::Error::Transport(_))
+            );
+            if unreachable {
+                return Err(e).with_context(|| {
+                    format!("Registry at {} could not be reached", registry_url)
+                });
+            }
+            return Err(e).with_context(|| format!("Registry at {} rejected the request", registry_url));
+        }
+    };
+    let published_idl = parse_idl(&published)
+        .context("Failed to parse the registry's IDL")?;
+
+    let changes = diff::diff(
+        &diff::canonicalize(&published_idl)?,
+        &diff::canonicalize(&local_idl)?,
+    );
+    if changes.is_empty() {
+        info!("Local IDL matches the IDL published for {}", program);
+        return Ok(());
+    }
+
+    println!("\nLocal IDL differs from the IDL published for {}:\n", program);
+    diff::print_changes(&changes);
+
+    Err(anyhow::anyhow!(
+        "Local IDL has drifted from the registry's IDL ({} change(s))",
+        changes.len()
+    ))
+}
+
+// Outcome of fetching one program's IDL: where it was written, or `None` if none is published
+type FetchResult = Result<Option<PathBuf>>;
+
+fn fetch_all(
+    addresses: &[String],
+    client: &rpc::Client,
+    output_dir: &Path,
+    concurrency: usize,
+) -> Result<()> {
+    debug!("Fetching {} IDL(s)", addresses.len());
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory {:?}", output_dir))?;
+
+    // A fixed pool of workers pulls addresses off a shared cursor, bounding requests in flight
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results: std::sync::Mutex<Vec<Option<FetchResult>>> =
+        std::sync::Mutex::new(addresses.iter().map(|_| None).collect());
+    let workers = concurrency.clamp(1, addresses.len().max(1));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(address) = addresses.get(idx) else {
+                    break;
+                };
+                let result = fetch_one(client, address, output_dir);
+                results.lock().unwrap()[idx] = Some(result);
+            });
+        }
+    });
+
+    let (mut fetched, mut missing, mut failed) = (0, 0, 0);
+    for (address, result) in addresses.iter().zip(results.into_inner().unwrap()) {
+        match result.unwrap_or_else(|| Err(anyhow::anyhow!("not fetched"))) {
+            Ok(Some(path)) => {
+                fetched += 1;
+                println!("  ok       {} -> {}", address, path.display());
+            }
+            Ok(None) => {
+                missing += 1;
+                println!("  no idl   {}", address);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("  failed   {}: {:#}", address, e);
+            }
+        }
+    }
+
+    println!(
+        "\n{} fetched, {} without a published IDL, {} failed",
+        fetched, missing, failed
+    );
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("Failed to fetch {} of {} IDL(s)", failed, addresses.len()));
+    }
+    Ok(())
+}
+
+fn fetch_one(client: &rpc::Client, address: &str, output_dir: &Path) -> FetchResult {
+    let Some(idl_bytes) = client.fetch_idl(address)? else {
+        return Ok(None);
+    };
+    let path = output_dir.join(format!("{}.json", address));
+    fs::write(&path, idl_bytes).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(Some(path))
+}
+
+// Runs one `generate` generator over a parsed IDL
+fn generate_source(
+    idl: &anchor_lang_idl::types::Idl,
+    lang: GenerateLang,
+    namespace: Option<&str>,
+) -> Result<String> {
+    match lang {
+        GenerateLang::Proto => generate::proto::generate(idl, namespace),
+        GenerateLang::Openapi => generate::openapi::generate(idl, namespace),
+        GenerateLang::Zod => generate::zod::generate(idl, namespace),
+        GenerateLang::AnchorAccounts => {
+            if namespace.is_some() {
+                warn!("--namespace doesn't apply to anchor-accounts; wrap the output in a module instead");
+            }
+            generate::anchor_accounts::generate(idl)
+        }
+    }
+}
+
+// Writes each `build --emit` artifact next to `output_path`, with the same stem, and returns the
+// paths written. A generator that fails is reported and skipped unless `fail_fast` is set; either
+// way the build fails if any artifact couldn't be written.
+fn emit_artifacts(
+    idl: &anchor_lang_idl::types::Idl,
+    idl_json: &str,
+    output_path: &Path,
+    emit: &[EmitFormat],
+    fail_fast: bool,
+) -> Result<Vec<PathBuf>> {
+    let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut written = Vec::new();
+    let mut failed = 0;
+
+    for format in emit {
+        let (path, contents) = match format {
+            EmitFormat::Json => (output_path.to_path_buf(), Ok(idl_json.to_string())),
+            EmitFormat::Proto => (
+                output_path.with_file_name(format!("{}.proto", stem)),
+                generate_source(idl, GenerateLang::Proto, None),
+            ),
+            EmitFormat::Openapi => (
+                output_path.with_file_name(format!("{}.openapi.json", stem)),
+                generate_source(idl, GenerateLang::Openapi, None),
+            ),
+            EmitFormat::AnchorAccounts => (
+                output_path.with_file_name(format!("{}.accounts.rs", stem)),
+                generate_source(idl, GenerateLang::AnchorAccounts, None),
+            ),
+            EmitFormat::Zod => (
+                output_path.with_file_name(format!("{}.ts", stem)),
+                generate_source(idl, GenerateLang::Zod, None),
+            ),
+        };
+
+        let result = contents.and_then(|contents| {
+            write_output(&path, contents)
+                .with_context(|| format!("Failed to write {:?}", path))
+        });
+        match result {
+            Ok(()) => {
+                info!("Wrote {:?}", path);
+                written.push(path);
+            }
+            Err(e) if fail_fast => {
+                return Err(e.context(format!("Failed to emit {:?}", path)));
+            }
+            Err(e) => {
+                warn!("Failed to emit {:?}: {:#}", path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "Failed to emit {} of {} artifact(s)",
+            failed,
+            emit.len()
+        ));
+    }
+    Ok(written)
+}
+
+fn generate_code(
+    path: &PathBuf,
+    lang: GenerateLang,
+    output: Option<&PathBuf>,
+    namespace: Option<&str>,
+) -> Result<()> {
+    debug!("Generating code from IDL at: {:?}", path);
+
+    if let Some(namespace) = namespace {
+        generate::check_namespace(namespace)?;
+    }
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let code = generate_source(&idl, lang, namespace)?;
+
+    match output {
+        Some(output_path) => {
+            fs::write(output_path, code)
+                .with_context(|| format!("Failed to write output to {:?}", output_path))?;
+            info!("Generated code written to: {:?}", output_path);
+        }
+        None => print!("{}", code),
+    }
+
+    Ok(())
+}
+
+// Canonical primitive names other than `pubkey`, matched case-insensitively
+const PRIMITIVES: &[&str] = &[
+    "bool", "u8", "i8", "u16", "i16", "u32", "i32", "f32", "u64", "i64", "f64", "u128", "i128",
+    "u256", "i256", "bytes", "string",
+];
+
+// Spellings of `pubkey` seen in the wild, lowercased
+const PUBKEY_SPELLINGS: &[&str] = &["pubkey", "publickey", "public_key"];
+
+// Top-level keys `convert_idl` reads from either format
+const IDL_KEYS: &[&str] = &[
+    "address", "metadata", "name", "version", "docs", "instructions", "accounts", "events",
+    "errors", "types", "constants",
+];
+
+// Describes what `convert` did by comparing the input JSON with the converted IDL. Legacy IDLs
+// define account and event layouts inline and carry no discriminators, so conversion hoists
+// the former into `types` and computes the latter.
+fn conversion_report(
+    raw: &serde_json::Value,
+    idl: &anchor_lang_idl::types::Idl,
+    normalized: usize,
+) -> Vec<String> {
+    let items = |section: &str| raw[section].as_array().cloned().unwrap_or_default();
+
+    let format = match raw["metadata"]["spec"].as_str() {
+        Some(spec) => format!("spec {}", spec),
+        None => "legacy (no metadata.spec)".to_string(),
+    };
+    let hoisted = items("accounts")
+        .iter()
+        .filter(|account| account.get("type").is_some())
+        .count()
+        + items("events")
+            .iter()
+            .filter(|event| event.get("fields").is_some())
+            .count();
+    let computed = ["instructions", "accounts", "events"]
+        .iter()
+        .flat_map(|section| items(section))
+        .filter(|item| item.get("discriminator").is_none())
+        .count();
+    let renamed: Vec<String> = items("instructions")
+        .iter()
+        .zip(&idl.instructions)
+        .filter_map(|(raw, converted)| {
+            let name = raw["name"].as_str()?;
+            (name != converted.name).then(|| format!("{} -> {}", name, converted.name))
+        })
+        .collect();
+    let dropped: Vec<&str> = raw
+        .as_object()
+        .map(|object| {
+            object
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !IDL_KEYS.contains(key))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let list = |names: &[String]| {
+        if names.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", names.join(", "))
+        }
+    };
+    vec![
+        format!("input format: {}", format),
+        format!("inline account/event types hoisted: {}", hoisted),
+        format!("discriminators computed: {}", computed),
+        format!("primitive names normalized: {}", normalized),
+        format!("instructions renamed: {}{}", renamed.len(), list(&renamed)),
+        format!(
+            "input keys not carried over: {}",
+            if dropped.is_empty() { "none".to_string() } else { dropped.join(", ") }
+        ),
+    ]
+}
+
+// Rewrites inconsistently spelled or cased primitive names to the form the input's format
+// expects, so conversion yields canonical modern names; returns how many names changed
+fn normalize_primitives(value: &mut serde_json::Value) -> usize {
+    // The legacy parser only accepts `publicKey`, which conversion then maps to `pubkey`
+    let pubkey = if value.pointer("/metadata/spec").is_some() {
+        "pubkey"
+    } else {
+        "publicKey"
+    };
+    let substitutions = normalize_type_names(value, pubkey);
+    debug!("Normalized {} primitive type name(s)", substitutions);
+    substitutions
+}
+
+fn normalize_type_names(value: &mut serde_json::Value, pubkey: &str) -> usize {
+    let mut count = 0;
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                match (key.as_str(), child) {
+                    ("type" | "option" | "vec" | "coption", serde_json::Value::String(name)) => {
+                        count += normalize_primitive(name, pubkey);
+                    }
+                    ("array", serde_json::Value::Array(items)) => {
+                        if let Some(serde_json::Value::String(name)) = items.first_mut() {
+                            count += normalize_primitive(name, pubkey);
+                        }
+                        count += items
+                            .iter_mut()
+                            .map(|item| normalize_type_names(item, pubkey))
+                            .sum::<usize>();
+                    }
+                    (_, child) => count += normalize_type_names(child, pubkey),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            count += items
+                .iter_mut()
+                .map(|item| normalize_type_names(item, pubkey))
+                .sum::<usize>();
+        }
+        _ => {}
+    }
+    count
+}
+
+fn normalize_primitive(name: &mut String, pubkey: &str) -> usize {
+    let lower = name.to_lowercase();
+    let canonical = if PUBKEY_SPELLINGS.contains(&lower.as_str()) {
+        Some(pubkey)
+    } else {
+        PRIMITIVES.iter().find(|p| **p == lower).copied()
+    };
+
+    match canonical {
+        Some(canonical) if canonical != name => {
+            *name = canonical.to_string();
+            1
+        }
+        _ => 0,
+    }
+}
+
+fn bench(path: &PathBuf, iterations: u32, convert: bool) -> Result<()> {
+    debug!("Benchmarking IDL at: {:?}", path);
+
+    let idl_bytes = read_input(path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
+    let iterations = iterations.max(1);
+
+    let mut stages: Vec<(&str, Vec<std::time::Duration>)> = Vec::new();
+    stages.push((
+        "parse",
+        time_iterations(iterations, || {
+            serde_json::from_slice::<serde_json::Value>(&idl_bytes)
+                .map(drop)
+                .context("Failed to parse IDL as JSON")
+        })?,
+    ));
+    if convert {
+        stages.push((
+            "convert",
+            time_iterations(iterations, || {
+                anchor_lang_idl::convert::convert_idl(&idl_bytes)
+                    .map(drop)
+                    .context("Failed to convert IDL")
+            })?,
+        ));
+    }
+
+    println!(
+        "{} iteration(s) over {:?} ({} bytes)\n",
+        iterations,
+        path,
+        idl_bytes.len()
+    );
+    println!("{:<10} {:>12} {:>12} {:>12} {:>12}", "STAGE", "MIN", "MEDIAN", "MAX", "PER SEC");
+    for (stage, mut timings) in stages {
+        timings.sort();
+        let median = timings[timings.len() / 2];
+        let per_sec = 1.0 / median.as_secs_f64().max(f64::EPSILON);
+        println!(
+            "{:<10} {:>12} {:>12} {:>12} {:>12.0}",
+            stage,
+            format!("{:.1?}", timings[0]),
+            format!("{:.1?}", median),
+            format!("{:.1?}", timings[timings.len() - 1]),
+            per_sec
+        );
+    }
+
+    Ok(())
+}
+
+fn time_iterations(
+    iterations: u32,
+    mut run: impl FnMut() -> Result<()>,
+) -> Result<Vec<std::time::Duration>> {
+    (0..iterations)
+        .map(|_| {
+            let start = std::time::Instant::now();
+            run()?;
+            Ok(start.elapsed())
+        })
+        .collect()
+}
+
+// Finds the program ID declared with `declare_id!` in a program's sources
+fn extract_program_id(program_path: &Path) -> Result<String> {
+    let candidates = if program_path.is_file() {
+        vec![program_path.to_path_buf()]
+    } else {
+        let src = program_path.join("src");
+        let mut files: Vec<PathBuf> = fs::read_dir(&src)
+            .with_context(|| format!("Failed to read program sources at {:?}", src))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+            .collect();
+        // `declare_id!` almost always lives in lib.rs, so look there first
+        files.sort_by_key(|path| (!path.ends_with("lib.rs"), path.clone()));
+        files
+    };
+
+    for file in &candidates {
+        let source = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {:?}", file))?;
+        if let Some(id) = find_declare_id(&strip_comments(&source)) {
+            debug!("Found declare_id!(\"{}\") in {:?}", id, file);
+            pda::decode_pubkey(&id)
+                .with_context(|| format!("Invalid program ID in declare_id! in {:?}", file))?;
+            return Ok(id);
+        }
+    }
+
+    Err(anyhow::anyhow!("No declare_id! found in {:?}", program_path))
+}
+
+// Removes line and block comments so commented-out IDs are ignored
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(idx) = rest.find('/') {
+        out.push_str(&rest[..idx]);
+        let tail = &rest[idx..];
+        if tail.starts_with("//") {
+            rest = tail.find('\n').map_or("", |end| &tail[end..]);
+        } else if tail.starts_with("/*") {
+            rest = tail.find("*/").map_or("", |end| &tail[end + 2..]);
+        } else {
+            out.push('/');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn find_declare_id(source: &str) -> Option<String> {
+    let mut rest = source;
+    while let Some(idx) = rest.find("declare_id!") {
+        rest = &rest[idx + "declare_id!".len()..];
+        let Some(args) = rest.trim_start().strip_prefix('(') else {
+            continue;
+        };
+        let Some(literal) = args.trim_start().strip_prefix('"') else {
+            continue;
+        };
+        if let Some(end) = literal.find('"') {
+            return Some(literal[..end].trim().to_string());
+        }
+    }
+    None
+}
+
+// Compares the IDL's address against the program's `declare_id!`
+fn check_address(idl_path: &PathBuf, program_path: &Path) -> Result<()> {
+    debug!("Checking IDL address at {:?} against {:?}", idl_path, program_path);
+
+    let idl_bytes = read_input(idl_path)
+        .with_context(|| format!("Failed to read IDL file at {:?}", idl_path))?;
+    let idl = parse_idl(&idl_bytes)
+        .context("Failed to parse IDL")?;
+
+    let declared = extract_program_id(program_path)?;
+    if declared != idl.address {
+        return Err(anyhow::anyhow!(
+            "IDL address does not match the program source\n  IDL address: {}\n  declare_id!: {}",
+            idl.address,
+            declared
+        ));
+    }
+
+    info!("IDL address {} matches declare_id! in {:?}", idl.address, program_path);
+    Ok(())
+}
+
+// Exit status for a timed-out build, matching coreutils `timeout`
+const BUILD_TIMEOUT_EXIT_CODE: i32 = 124;
+
+// Runs `build_idl` on a worker thread so it can be abandoned once `timeout` passes.
+// `build_idl` can't be cancelled, so on timeout the process exits while the cargo build it
+// started may still be running in the background.
+fn build_with_timeout(
+    path: PathBuf,
+    resolution: bool,
+    skip_lint: bool,
+    no_docs: bool,
+    timeout: Option<std::time::Duration>,
+) -> Result<anchor_lang_idl::types::Idl> {
+    // Directly call the build_idl function - using #[allow(deprecated)] to avoid warnings
+    #[allow(deprecated)]
+    let build = move || {
+        anchor_lang_idl::build::build_idl(path, resolution, skip_lint, no_docs)
+            .context("Failed to build IDL")
+    };
+    let Some(timeout) = timeout else {
+        return build();
+    };
+
+    run_with_timeout(build, timeout).unwrap_or_else(|| {
+        eprintln!("Error: IDL build timed out after {}s", timeout.as_secs());
+        std::process::exit(BUILD_TIMEOUT_EXIT_CODE);
+    })
+}
+
+// Runs `work` on a worker thread and waits up to `timeout` for it; `None` means it timed out
+// and was left running
+fn run_with_timeout<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+    timeout: std::time::Duration,
+) -> Option<Result<T>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        // The receiver is gone once the work has timed out, so there's no one to tell
+        let _ = sender.send(work());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => Some(result),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => None,
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            Some(Err(anyhow::anyhow!("IDL build thread exited without a result")))
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    // Hidden commands only show up in the help with --experimental, which has to be known
+    // before clap renders it
+    let experimental = std::env::args().any(|arg| arg == "--experimental");
+    let matches = Cli::command()
+        .mut_subcommand("bench", |cmd| cmd.hide(!experimental))
+        .get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let _ = MAX_INPUT_BYTES.set(cli.max_input_bytes);
+    let _ = MAX_INSTRUCTIONS.set(cli.max_instructions);
+    let _ = MAX_TYPES.set(cli.max_types);
+    if let Some(cache_dir) = &cli.cache_dir {
+        let _ = CACHE_DIR.set(cache_dir.clone());
+    }
+    let _ = NO_ATOMIC.set(cli.no_atomic);
+    if let Some(input_format) = cli.input_format {
+        let _ = INPUT_FORMAT.set(input_format);
+    }
+
+    // Logs go to stderr so stdout stays clean for piped output
+    log_dispatch(cli.log_format, cli.verbose, std::io::stderr).init();
+
+    match &cli.command {
+        Commands::Build {
+            path,
+            output,
+            skip_lint,
+            no_docs,
+            no_resolution,
+            stdout,
+            with_provenance,
+            timeout,
+            pretty_depth,
+            output_template,
+            emit,
+            fail_fast,
+        } => {
+            debug!("Building IDL for program at: {:?}", path);
+            
+            let idl = build_with_timeout(
+                path.clone(),
+                !no_resolution,
+                *skip_lint,
+                *no_docs,
+                timeout.map(std::time::Duration::from_secs),
+            )?;
+            
+            // Serialize the IDL to JSON with pretty printing
+            let mut idl_json = anchor_lang_idl::serde_json::to_string_pretty(&idl)
+                .context("Failed to serialize IDL to JSON")?;
+            if let Some(depth) = pretty_depth {
+                idl_json = limit_pretty_depth(&idl_json, *depth);
+            }
+            
+            if *stdout {
+                println!("{}", idl_json);
+                return Ok(());
+            }
+
+            // Determine output path
+            let output_path = match output {
+                Some(path) => path.clone(),
+                None => match output_template {
+                    Some(template) => {
+                        let program_dir = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                        let stem = program_dir.file_name().unwrap_or_default().to_string_lossy();
+                        render_output_template(template, &idl, &stem)?
+                    }
+                    None => {
+                        let program_name = &idl.metadata.name;
+                        PathBuf::from(format!("{}.json", program_name))
+                    }
+                },
+            };
+            
+            // Write the IDL and any other requested artifacts
+            emit_artifacts(&idl, &idl_json, &output_path, emit, *fail_fast)?;
+
+            if *with_provenance {
+                let provenance = provenance::collect(&idl, path)?;
+                let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+                let provenance_path = output_path.with_file_name(format!("{}.provenance.json", stem));
+                write_output(&provenance_path, serde_json::to_string_pretty(&provenance)?)
+                    .with_context(|| format!("Failed to write provenance to {:?}", provenance_path))?;
+                info!("Provenance saved to {:?}", provenance_path);
+            }
+        }
+        
+        Commands::Convert {
+            input,
+            output,
+            stdout,
+            to,
+            layout_hash,
+            only,
+            exclude,
+            select,
+            flatten_composites,
+            prefix_composites,
+            pretty_depth,
+            preserve_order,
+            report,
+            strip_address,
+            output_template,
+        } => {
+            debug!("Converting IDL from: {:?}", input);
+            
+            // Read the input IDL file
+            let idl_bytes = read_input(input)
+                .with_context(|| format!("Failed to read IDL file at {:?}", input))?;
+            
+            // Convert the IDL; the parsed input is kept for the report and --preserve-order
+            let (raw, normalized, mut converted_idl) = parse_idl_normalized(&idl_bytes)
+                .context("Failed to convert IDL")?;
+
+            // Taken before the optional passes below, so it only describes the conversion
+            let conversion_report = report
+                .then(|| conversion_report(&raw, &converted_idl, normalized));
+
+            if !select.is_empty() {
+                select_instructions(&mut converted_idl, select)?;
+            }
+
+            if *flatten_composites {
+                for instruction in &mut converted_idl.instructions {
+                    let accounts = std::mem::take(&mut instruction.accounts);
+                    instruction.accounts = flatten_composite_accounts(accounts, *prefix_composites);
+
+                    let mut seen = std::collections::HashSet::new();
+                    for account in flatten_accounts(&instruction.accounts) {
+                        if !seen.insert(account.name.as_str()) {
+                            warn!(
+                                "Flattening '{}' left two accounts named '{}'; pass --prefix-composites to keep them apart",
+                                instruction.name, account.name
+                            );
+                        }
+                    }
+                }
+            }
+
+            if !only.is_empty() || !exclude.is_empty() {
+                let keep = |section| {
+                    if only.is_empty() {
+                        !exclude.contains(&section)
+                    } else {
+                        only.contains(&section)
+                    }
+                };
+                filter_sections(&mut converted_idl, keep);
+                for name in split::missing_types(&converted_idl)? {
+                    warn!("Type '{}' is referenced but was filtered out of the output", name);
+                }
+            }
+
+            if *strip_address {
+                clear_address(&mut converted_idl);
+                warn!("The program address was stripped; the IDL won't pass validate until an address is set again (or with --require-address=false)");
+            }
+            
+            // Serialize the converted IDL to JSON with pretty printing
+            let idl_json = if *to == ConvertTarget::Codama {
+                if *layout_hash || *preserve_order {
+                    return Err(anyhow::anyhow!(
+                        "--layout-hash and --preserve-order only apply to Anchor output, not --to codama"
+                    ));
+                }
+                let root = codama::root_node(&converted_idl)
+                    .context("Failed to map the IDL to Codama nodes")?;
+                serde_json::to_string_pretty(&root)
+            } else if *preserve_order {
+                let layout_hashes = layout_hash.then(|| account_layout_hashes(&converted_idl));
+                to_json_in_input_order(&mut converted_idl, &raw, &idl_bytes, layout_hashes)
+            } else if *layout_hash {
+                let mut idl_value = serde_json::to_value(&converted_idl)
+                    .context("Failed to serialize converted IDL to JSON")?;
+                idl_value["metadata"]["layoutHashes"] =
+                    account_layout_hashes(&converted_idl).into();
+                serde_json::to_string_pretty(&idl_value)
+            } else {
+                anchor_lang_idl::serde_json::to_string_pretty(&converted_idl)
+            }
+            .context("Failed to serialize converted IDL to JSON")?;
+            let idl_json = match pretty_depth {
+                Some(depth) => limit_pretty_depth(&idl_json, *depth),
+                None => idl_json,
+            };
+
+            if let Some(lines) = conversion_report {
+                eprintln!("Conversion report:");
+                for line in lines {
+                    eprintln!("  {}", line);
+                }
+            }
+
+            if *stdout {
+                println!("{}", idl_json);
+                return Ok(());
+            }
+            
+            // Determine output path
+            let output_path = match output {
+                Some(path) => path.clone(),
+                None => {
+                    let input_stem = input.file_stem().unwrap_or_default();
+                    match output_template {
+                        Some(template) => {
+                            let input_stem = input_stem.to_string_lossy();
+                            let file_name =
+                                render_output_template(template, &converted_idl, &input_stem)?;
+                            input.with_file_name(file_name)
+                        }
+                        None => {
+                            let mut output_path = input.with_file_name(input_stem);
+                            output_path.set_extension(match to {
+                                ConvertTarget::Anchor => "converted.json",
+                                ConvertTarget::Codama => "codama.json",
+                            });
+                            output_path
+                        }
+                    }
+                }
+            };
+            
+            // Write the converted IDL to the output file
+            write_output(&output_path, idl_json)
+                .with_context(|| format!("Failed to write converted IDL to {:?}", output_path))?;
+            
+            info!("Successfully converted IDL and saved to {:?}", output_path);
+        }
+        
+        Commands::Validate {
+            input,
+            examples_dir,
+            strict,
+            require_address,
+            self_check,
+            allow_empty_discriminator,
+            verify_discriminator_uniqueness,
+            discriminator_sections,
+            verify_discriminators,
+            namespaces,
+            disc_prefix,
+            disc_case,
+            consistent_accounts,
+            allow_empty_instructions,
+            max_type_depth,
+            layout_baseline,
+            min_spec,
+            max_spec,
+            count_only,
+            format,
+            fail_on_warning,
+            fix,
+            fix_unsafe,
+            output,
+        } => {
+            let discriminator_sections: &[DiscriminatorSection] = if *verify_discriminator_uniqueness {
+                discriminator_sections
+            } else {
+                &[]
+            };
+            let layout_baseline = match layout_baseline {
+                Some(baseline) => {
+                    let bytes = read_input(baseline)
+                        .with_context(|| format!("Failed to read baseline IDL at {:?}", baseline))?;
+                    Some(
+                        parse_idl(&bytes)
+                            .context("Failed to parse baseline IDL")?,
+                    )
                 }
-            },
-            IdlInstructionAccountItem::Composite(composite) => {
-                println!("{}{}:", indent, composite.name);
-                display_accounts(&composite.accounts, depth + 1);
+                None => None,
+            };
+            let options = ValidateOptions {
+                strict: *strict,
+                require_address: *require_address,
+                self_check: *self_check,
+                allow_empty_discriminator: *allow_empty_discriminator,
+                consistent_accounts: *consistent_accounts,
+                allow_empty_instructions: *allow_empty_instructions,
+                max_type_depth: *max_type_depth,
+                layout_baseline: layout_baseline.as_ref(),
+                verify_discriminators: *verify_discriminators,
+                namespaces,
+                disc_prefix: disc_prefix.as_deref(),
+                disc_case: *disc_case,
+                discriminator_sections,
+                min_spec: *min_spec,
+                max_spec: *max_spec,
+                format: *format,
+                fail_on_warning: *fail_on_warning,
+            };
+
+            if *fix || *fix_unsafe {
+                if input.is_dir() {
+                    return Err(anyhow::anyhow!("--fix requires --input to be a single IDL file"));
+                }
+                let output = output.as_ref().unwrap_or(input);
+                fix_idl(input, output, *fix_unsafe)?;
+                validate_idl(output, options)?;
+                return Ok(());
+            } else if output.is_some() {
+                return Err(anyhow::anyhow!("--output is only used together with --fix"));
+            }
+
+            if *count_only {
+                count_sections(input, *format)?;
+                return Ok(());
+            } else if *format == OutputFormat::Json && input.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "--format json requires --input to be a single IDL file"
+                ));
+            }
+
+            if input.is_dir() {
+                validate_dir(input, *examples_dir, options)?;
+            } else if *examples_dir {
+                return Err(anyhow::anyhow!(
+                    "--examples-dir requires --input to be a directory"
+                ));
+            } else {
+                validate_idl(input, options)?;
+            }
+        }
+        
+        Commands::Instructions {
+            input,
+            names_only,
+            signatures,
+            indices,
+            truncate_docs,
+            offset,
+            limit,
+            sort,
+            reverse,
+            group_by,
+            expand_args,
+            format,
+        } => {
+            if *signatures {
+                display_signatures(input, *format)?;
+            } else if *indices {
+                display_account_indices(input, *format)?;
+            } else if *format == OutputFormat::Json {
+                return Err(anyhow::anyhow!(
+                    "--format json is only supported together with --signatures or --indices"
+                ));
+            } else {
+                let options = ListOptions {
+                    names_only: *names_only,
+                    truncate_docs: *truncate_docs,
+                    offset: *offset,
+                    limit: *limit,
+                    sort: *sort,
+                    reverse: *reverse,
+                    group_by: *group_by,
+                    expand_args: *expand_args,
+                };
+                display_instructions(input, &options)?;
+            }
+        }
+
+        Commands::Events { input } => {
+            display_events(input)?;
+        }
+
+        Commands::Snapshot { input, baseline, update } => {
+            snapshot_idl(input, baseline, *update)?;
+        }
+
+        Commands::Prepare { input, out_dir } => {
+            prepare_idl(input, out_dir)?;
+        }
+
+        Commands::FromElf { path, output } => {
+            extract_elf_idl(path, output.as_ref())?;
+        }
+
+        Commands::Split { input, output_dir } => {
+            split_idl(input, output_dir)?;
+        }
+
+        Commands::Merge {
+            interface,
+            types,
+            output,
+        } => {
+            merge_idl(interface, types.as_ref(), output.as_ref())?;
+        }
+
+        Commands::Query {
+            input,
+            pointer,
+            raw,
+        } => {
+            query_idl(input, pointer, *raw)?;
+        }
+
+        Commands::Usage { input, ty } => {
+            display_usage(input, ty.as_ref())?;
+        }
+
+        Commands::Constants {
+            input,
+            group_digits,
+        } => {
+            display_constants(input, *group_digits)?;
+        }
+
+        Commands::Stats { input, format } => {
+            idl_stats(input, *format)?;
+        }
+
+        Commands::Whois {
+            input,
+            discriminator,
+            prefix,
+        } => {
+            whois(input, discriminator, *prefix)?;
+        }
+
+        Commands::Diff {
+            old,
+            new,
+            diff_format,
+            check_only,
+            ignore,
+        } => {
+            diff_idls(old, new, *diff_format, *check_only, ignore, cli.verbose)?;
+        }
+
+        Commands::Pda {
+            input,
+            instruction,
+            account,
+            args,
+            account_keys,
+        } => {
+            compute_pda(input, instruction, account, args, account_keys)?;
+        }
+
+        Commands::VerifyDeployed { input, address, rpc } => {
+            verify_deployed(input, address.as_ref(), &rpc.client())?;
+        }
+
+        Commands::CheckRegistry {
+            input,
+            registry_url,
+            program,
+            retries,
+            retry_delay,
+        } => {
+            check_registry(
+                input,
+                registry_url,
+                program,
+                *retries,
+                std::time::Duration::from_millis(*retry_delay),
+            )?;
+        }
+
+        Commands::FetchAll {
+            addresses,
+            rpc,
+            output_dir,
+            concurrency,
+        } => {
+            fetch_all(addresses, &rpc.client(), output_dir, *concurrency)?;
+        }
+
+        Commands::Generate {
+            input,
+            lang,
+            output,
+            namespace,
+        } => {
+            generate_code(input, *lang, output.as_ref(), namespace.as_deref())?;
+        }
+
+        Commands::Bench {
+            input,
+            iterations,
+            convert,
+        } => {
+            bench(input, *iterations, *convert)?;
+        }
+
+        Commands::CheckAddress { idl, program_path } => {
+            check_address(idl, program_path)?;
+        }
+    }
+
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // A fresh directory under the system temp dir, unique to this process and test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dls-anchor-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_json(dir: &Path, name: &str, value: &serde_json::Value) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, serde_json::to_vec_pretty(value).unwrap()).unwrap();
+        path
+    }
+
+    // The defaults `validate` runs with when no flags are passed
+    fn validate_options() -> ValidateOptions<'static> {
+        ValidateOptions {
+            strict: false,
+            require_address: true,
+            self_check: false,
+            allow_empty_discriminator: false,
+            consistent_accounts: false,
+            allow_empty_instructions: false,
+            max_type_depth: None,
+            layout_baseline: None,
+            verify_discriminators: false,
+            namespaces: &[],
+            disc_prefix: None,
+            disc_case: DiscriminatorCase::AsIs,
+            discriminator_sections: &[],
+            min_spec: None,
+            max_spec: None,
+            format: OutputFormat::Text,
+            fail_on_warning: false,
+        }
+    }
+
+    // Runs every validation check on `idl` and returns the diagnostics as (code, message)
+    fn check(
+        name: &str,
+        idl: &serde_json::Value,
+        options: ValidateOptions<'_>,
+    ) -> Vec<(String, String)> {
+        let dir = temp_dir(name);
+        let path = write_json(&dir, "idl.json", idl);
+        let mut diagnostics = Diagnostics::for_path(path.display());
+        check_idl(&path, options, &mut diagnostics).unwrap();
+        diagnostics.to_json()["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| {
+                let field = |name: &str| d[name].as_str().unwrap().to_string();
+                (field("code"), field("message"))
+            })
+            .collect()
+    }
+
+    fn codes(diagnostics: &[(String, String)]) -> Vec<&str> {
+        diagnostics.iter().map(|(code, _)| code.as_str()).collect()
+    }
+
+    fn parse(value: serde_json::Value) -> anchor_lang_idl::types::Idl {
+        serde_json::from_value(value).unwrap()
+    }
+
+    // `sample_idl` with its instructions replaced by argless ones with these names, in order
+    fn with_instructions(names: &[&str]) -> anchor_lang_idl::types::Idl {
+        let mut idl = sample_idl();
+        idl["instructions"] = names
+            .iter()
+            .map(|name| {
+                json!({ "name": name, "discriminator": vec![0u8; 8], "accounts": [], "args": [] })
+            })
+            .collect();
+        parse(idl)
+    }
+
+    // A pre-0.30 IDL for the same program as `sample_idl`
+    fn legacy_idl() -> serde_json::Value {
+        json!({
+            "version": "0.1.0",
+            "name": "demo",
+            "instructions": [{
+                "name": "initialize",
+                "accounts": [
+                    { "name": "payer", "isMut": true, "isSigner": true },
+                    { "name": "state", "isMut": true, "isSigner": false }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "bump", "type": "u8" }
+                ]
+            }],
+            "accounts": [{
+                "name": "State",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "owner", "type": "publicKey" },
+                    { "name": "count", "type": "u64" }
+                ] }
+            }],
+            "metadata": { "address": "11111111111111111111111111111112" }
+        })
+    }
+
+    fn sample_idl() -> serde_json::Value {
+        json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "initialize",
+                "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                "accounts": [
+                    { "name": "payer", "writable": true, "signer": true },
+                    { "name": "state", "writable": true }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "bump", "type": "u8" }
+                ]
+            }],
+            "accounts": [{ "name": "State", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] }],
+            "types": [{
+                "name": "State",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "owner", "type": "pubkey" },
+                    { "name": "count", "type": "u64" }
+                ] }
+            }]
+        })
+    }
+
+    #[test]
+    fn fix_applies_safe_fixes_and_is_idempotent() {
+        let dir = temp_dir("fix");
+        let mut messy = sample_idl();
+        messy["instructions"][0]["docs"] = json!(["  Sets things up  "]);
+        messy["instructions"][0]["args"][1]["type"] = json!("U8");
+        messy["types"].as_array_mut().unwrap().push(json!({
+            "name": "Unused",
+            "type": { "kind": "struct", "fields": [] }
+        }));
+        let input = write_json(&dir, "idl.json", &messy);
+
+        fix_idl(&input, &input, false).unwrap();
+        let fixed: serde_json::Value = serde_json::from_slice(&fs::read(&input).unwrap()).unwrap();
+        assert_eq!(fixed["instructions"][0]["docs"], json!(["Sets things up"]));
+        assert_eq!(fixed["instructions"][0]["args"][1]["type"], "u8");
+        assert_eq!(fixed["types"].as_array().unwrap().len(), 1);
+
+        let once = fs::read(&input).unwrap();
+        fix_idl(&input, &input, false).unwrap();
+        assert_eq!(fs::read(&input).unwrap(), once);
+    }
+
+    #[test]
+    fn fix_refuses_to_rewrite_a_legacy_idl_in_place() {
+        let dir = temp_dir("fix-legacy");
+        let legacy = json!({
+            "version": "0.1.0",
+            "name": "demo",
+            "instructions": [{ "name": "init", "accounts": [], "args": [] }],
+            "metadata": { "address": "11111111111111111111111111111112" }
+        });
+        let input = write_json(&dir, "legacy.json", &legacy);
+        let error = fix_idl(&input, &input, false).unwrap_err();
+        assert!(error.to_string().contains("legacy IDL"));
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&fs::read(&input).unwrap()).unwrap(),
+            legacy
+        );
+
+        let output = dir.join("fixed.json");
+        fix_idl(&input, &output, false).unwrap();
+        let fixed: serde_json::Value = serde_json::from_slice(&fs::read(&output).unwrap()).unwrap();
+        assert_eq!(fixed["metadata"]["spec"], "0.1.0");
+    }
+
+    #[test]
+    fn check_only_passes_additive_changes_and_fails_breaking_ones() {
+        let dir = temp_dir("check-only");
+        let old = write_json(&dir, "old.json", &sample_idl());
+
+        let mut additive = sample_idl();
+        additive["constants"] = json!([{ "name": "MAX", "type": "u8", "value": "8" }]);
+        let additive = write_json(&dir, "additive.json", &additive);
+        diff_idls(&old, &additive, DiffFormat::Human, true, &[], false).unwrap();
+
+        let mut reordered = sample_idl();
+        reordered["instructions"][0]["args"].as_array_mut().unwrap().reverse();
+        let reordered = write_json(&dir, "reordered.json", &reordered);
+        let error = diff_idls(&old, &reordered, DiffFormat::Human, true, &[], false).unwrap_err();
+        assert!(error.to_string().contains("breaking change(s)"));
+    }
+
+    #[test]
+    fn verify_deployed_reports_reordered_accounts() {
+        let dir = temp_dir("verify-deployed");
+        let local = write_json(&dir, "local.json", &sample_idl());
+        let deployed = serde_json::to_vec(&sample_idl()).unwrap();
+
+        let server = rpc::mock::serve(vec![(200, rpc::mock::idl_account(&deployed))]);
+        let client = rpc::Client::new(&server.url, 0, std::time::Duration::ZERO);
+        verify_deployed(&local, None, &client).unwrap();
+        let requests = server.requests();
+        assert!(requests[0].line.starts_with("POST "));
+        assert!(requests[0].body.contains("getAccountInfo"));
+
+        let mut reordered = sample_idl();
+        reordered["instructions"][0]["accounts"].as_array_mut().unwrap().reverse();
+        let local = write_json(&dir, "reordered.json", &reordered);
+        let server = rpc::mock::serve(vec![(200, rpc::mock::idl_account(&deployed))]);
+        let client = rpc::Client::new(&server.url, 0, std::time::Duration::ZERO);
+        let error = verify_deployed(&local, None, &client).unwrap_err();
+        assert!(error.to_string().contains("drifted"));
+        server.requests();
+    }
+
+    #[test]
+    fn check_registry_against_a_mock_registry() {
+        let dir = temp_dir("check-registry");
+        let local = write_json(&dir, "local.json", &sample_idl());
+        let published = sample_idl().to_string();
+
+        let check = |path: &PathBuf, url: &str, retries| {
+            check_registry(path, url, "demo", retries, std::time::Duration::ZERO)
+        };
+        let server = rpc::mock::serve(vec![(200, published.clone())]);
+        check(&local, &server.url, 0).unwrap();
+        assert_eq!(server.requests()[0].line, "GET /programs/demo/idl HTTP/1.1");
+
+        // A transient failure is retried after the configured delay
+        let server = rpc::mock::serve(vec![(503, String::new()), (200, published.clone())]);
+        check(&local, &server.url, 1).unwrap();
+        assert_eq!(server.requests().len(), 2);
+
+        let mut reordered = sample_idl();
+        reordered["instructions"][0]["args"].as_array_mut().unwrap().reverse();
+        let drifted = write_json(&dir, "drifted.json", &reordered);
+        let server = rpc::mock::serve(vec![(200, published)]);
+        let error = check(&drifted, &server.url, 0).unwrap_err();
+        assert!(error.to_string().contains("drifted"));
+        server.requests();
+
+        let server = rpc::mock::serve(vec![(404, String::new())]);
+        let error = check(&local, &server.url, 0).unwrap_err();
+        assert!(error.to_string().contains("has no IDL published"));
+        server.requests();
+
+        // Nothing listens on a port whose listener was just dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let error = check(&local, &format!("http://127.0.0.1:{}", port), 0).unwrap_err();
+        assert!(error.to_string().contains("could not be reached"));
+    }
+
+    #[test]
+    fn verify_deployed_reports_a_missing_idl() {
+        let dir = temp_dir("verify-deployed-missing");
+        let local = write_json(&dir, "local.json", &sample_idl());
+        let empty = json!({ "jsonrpc": "2.0", "id": 1, "result": { "value": null } });
+        let server = rpc::mock::serve(vec![(200, empty.to_string())]);
+        let client = rpc::Client::new(&server.url, 0, std::time::Duration::ZERO);
+        let error = verify_deployed(&local, None, &client).unwrap_err();
+        assert!(error.to_string().contains("no IDL published"));
+        server.requests();
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file_and_keeps_its_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("write-atomic");
+        let path = dir.join("out.json");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&path, "new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "temp file left behind");
+    }
+
+    #[test]
+    fn failed_write_atomic_leaves_the_target_untouched() {
+        let dir = temp_dir("write-atomic-failed");
+        // Renaming a file over a non-empty directory fails after the temp file is complete
+        let path = dir.join("out.json");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("keep"), "old").unwrap();
+
+        assert!(write_atomic(&path, "new").is_err());
+
+        assert_eq!(fs::read_to_string(path.join("keep")).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "temp file left behind");
+    }
+
+    #[test]
+    fn input_over_the_size_limit_is_refused_before_reading() {
+        let dir = temp_dir("max-input-bytes");
+        let path = dir.join("big.json");
+        fs::write(&path, vec![b' '; 64]).unwrap();
+
+        let error = read_input_limited(&path, 16).unwrap_err();
+        assert!(error.to_string().contains("over the --max-input-bytes limit of 16"));
+        assert_eq!(read_input_limited(&path, 64).unwrap().len(), 64);
+    }
+
+    #[test]
+    fn max_input_bytes_is_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "dls-anchor",
+            "validate",
+            "--input",
+            "idl.json",
+            "--max-input-bytes",
+            "16",
+        ])
+        .unwrap();
+        assert_eq!(cli.max_input_bytes, 16);
+    }
+
+    #[test]
+    fn log_format_is_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "dls-anchor",
+            "convert",
+            "--input",
+            "idl.json",
+            "--log-format",
+            "json",
+        ])
+        .unwrap();
+        assert!(matches!(cli.log_format, LogFormat::Json));
+    }
+
+    #[test]
+    fn section_limits_fire_before_conversion() {
+        // Entries that aren't valid instructions or types: reaching conversion would fail
+        // differently, so the limit error shows the guard ran first
+        let huge = vec![json!({}); DEFAULT_MAX_INSTRUCTIONS + 1];
+        let mut idl = sample_idl();
+        idl["instructions"] = json!(huge);
+        let error = parse_idl(&serde_json::to_vec(&idl).unwrap()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "IDL has {} instructions, over the --max-instructions limit of {}",
+                DEFAULT_MAX_INSTRUCTIONS + 1,
+                DEFAULT_MAX_INSTRUCTIONS
+            )
+        );
+
+        let mut idl = sample_idl();
+        idl["types"] = json!(vec![json!({}); DEFAULT_MAX_TYPES + 1]);
+        let error = parse_idl(&serde_json::to_vec(&idl).unwrap()).unwrap_err();
+        assert!(error.to_string().contains("over the --max-types limit"));
+
+        assert!(parse_idl(&serde_json::to_vec(&sample_idl()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn section_limits_are_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "dls-anchor",
+            "validate",
+            "--input",
+            "idl.json",
+            "--max-instructions",
+            "2",
+            "--max-types",
+            "3",
+        ])
+        .unwrap();
+        assert_eq!(cli.max_instructions, 2);
+        assert_eq!(cli.max_types, 3);
+    }
+
+    #[test]
+    fn validation_reports_every_error_and_summarizes_them() {
+        let mut idl = sample_idl();
+        idl["address"] = json!("");
+        idl["instructions"][0]["discriminator"] = json!([]);
+
+        let diagnostics = check("validate-all-errors", &idl, validate_options());
+        assert!(codes(&diagnostics).contains(&"missing-address"));
+        assert!(codes(&diagnostics).contains(&"empty-discriminator"));
+
+        let path = write_json(&temp_dir("validate-summary"), "idl.json", &idl);
+        let error = validate_idl(&path, validate_options()).unwrap_err();
+        assert_eq!(error.to_string(), "IDL validation failed with 2 error(s)");
+    }
+
+    #[test]
+    fn a_single_validation_error_is_returned_as_is() {
+        let mut idl = sample_idl();
+        idl["address"] = json!("");
+
+        let path = write_json(&temp_dir("validate-single"), "idl.json", &idl);
+        let error = validate_idl(&path, validate_options()).unwrap_err();
+        assert_eq!(error.to_string(), "IDL is missing program address");
+    }
+
+    #[test]
+    fn args_named_like_their_accounts_are_reported_whatever_their_type() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"] = json!([
+            { "name": "state", "type": "u64" },
+            { "name": "payer", "type": "pubkey" }
+        ]);
+
+        let diagnostics = check("shadowed-accounts", &idl, validate_options());
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|(_, message)| message.as_str())
+            .collect();
+        for arg in ["state", "payer"] {
+            let expected = format!(
+                "arg '{}' in 'initialize' has the same name as one of its accounts",
+                arg
+            );
+            assert!(messages.contains(&expected.as_str()), "{:?}", messages);
+        }
+        assert!(!messages
+            .iter()
+            .any(|message| message.contains("should it be in the accounts list")));
+    }
+
+    #[test]
+    fn pubkey_args_named_like_accounts_get_a_hint() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"] = json!([
+            { "name": "payer_key", "type": "pubkey" },
+            { "name": "state_count", "type": "pubkey" },
+            { "name": "payer_amount", "type": "u64" }
+        ]);
+        let idl: anchor_lang_idl::types::Idl = serde_json::from_value(idl).unwrap();
+
+        assert_eq!(
+            pubkey_arg_accounts(&idl),
+            ["pubkey arg 'payer_key' in 'initialize' is named like account 'payer'; \
+              should it be in the accounts list?"]
+        );
+    }
+
+    #[test]
+    fn stats_over_a_directory_writes_a_line_per_idl_and_an_error_line_for_bad_files() {
+        let dir = temp_dir("stats-dir");
+        write_json(&dir, "a_valid.json", &sample_idl());
+        fs::write(dir.join("b_broken.json"), "{ not json").unwrap();
+        write_json(&dir, "c_legacy.json", &legacy_idl());
+        fs::write(dir.join("notes.txt"), "skipped").unwrap();
+
+        let mut out = Vec::new();
+        stats_dir(&dir, &mut out).unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["file"], "a_valid.json");
+        assert_eq!(lines[0]["name"], "demo");
+        assert_eq!(lines[0]["version"], "0.1.0");
+        assert_eq!(lines[0]["spec"], "0.1.0");
+        assert_eq!(lines[0]["instructions"], 1);
+        assert_eq!(lines[0]["max_type_depth"], 1);
+        assert!(lines[0].get("error").is_none());
+        assert_eq!(lines[1]["file"], "b_broken.json");
+        assert!(lines[1]["error"].is_string());
+        assert!(lines[1].get("name").is_none());
+        // The spec is the one the input declared, not the converted IDL's
+        assert_eq!(lines[2]["file"], "c_legacy.json");
+        assert_eq!(lines[2]["spec"], "legacy");
+    }
+
+    #[test]
+    fn emit_writes_every_artifact_and_reports_the_paths() {
+        let dir = temp_dir("emit");
+        let idl: anchor_lang_idl::types::Idl = serde_json::from_value(sample_idl()).unwrap();
+        let output = dir.join("demo.json");
+
+        let written = emit_artifacts(
+            &idl,
+            "{}",
+            &output,
+            &[
+                EmitFormat::Json,
+                EmitFormat::Proto,
+                EmitFormat::Openapi,
+                EmitFormat::AnchorAccounts,
+                EmitFormat::Zod,
+            ],
+            false,
+        )
+        .unwrap();
+
+        let names: Vec<String> = written
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            ["demo.json", "demo.proto", "demo.openapi.json", "demo.accounts.rs", "demo.ts"]
+        );
+        assert_eq!(fs::read_to_string(&output).unwrap(), "{}");
+        assert!(fs::read_to_string(dir.join("demo.ts")).unwrap().contains("z.object"));
+    }
+
+    #[test]
+    fn emit_keeps_going_past_a_failed_generator_unless_fail_fast() {
+        // proto3 can't nest containers, so only the proto generator fails
+        let mut value = sample_idl();
+        value["instructions"][0]["args"][0]["type"] = json!({ "vec": { "option": "u64" } });
+        let idl: anchor_lang_idl::types::Idl = serde_json::from_value(value).unwrap();
+        let emit = [EmitFormat::Proto, EmitFormat::Zod];
+
+        let dir = temp_dir("emit-keep-going");
+        let error = emit_artifacts(&idl, "{}", &dir.join("demo.json"), &emit, false).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to emit 1 of 2 artifact(s)");
+        assert!(!dir.join("demo.proto").exists());
+        assert!(dir.join("demo.ts").exists());
+
+        let dir = temp_dir("emit-fail-fast");
+        let error = emit_artifacts(&idl, "{}", &dir.join("demo.json"), &emit, true).unwrap_err();
+        assert!(error.to_string().contains("demo.proto"));
+        assert!(!dir.join("demo.ts").exists());
+    }
+
+    #[test]
+    fn snapshot_matches_its_baseline_until_the_idl_changes() {
+        let dir = temp_dir("snapshot");
+        let mut idl = sample_idl();
+        let close = json!({
+            "name": "close",
+            "discriminator": [98, 165, 201, 177, 108, 65, 206, 96],
+            "accounts": [{ "name": "state", "writable": true }],
+            "args": []
+        });
+        idl["instructions"].as_array_mut().unwrap().push(close);
+        let input = write_json(&dir, "idl.json", &idl);
+        let baseline = dir.join("baseline.json");
+
+        assert!(snapshot_idl(&input, &baseline, false).is_err(), "missing baseline");
+        snapshot_idl(&input, &baseline, true).unwrap();
+        snapshot_idl(&input, &baseline, false).unwrap();
+
+        // Instructions are matched by name, so their order doesn't matter
+        idl["instructions"].as_array_mut().unwrap().reverse();
+        let reordered = write_json(&dir, "reordered.json", &idl);
+        snapshot_idl(&reordered, &baseline, false).unwrap();
+
+        idl["instructions"][1]["args"][0]["type"] = json!("u32");
+        let changed = write_json(&dir, "changed.json", &idl);
+        let error = snapshot_idl(&changed, &baseline, false).unwrap_err();
+        assert_eq!(error.to_string(), "IDL does not match baseline (1 change(s))");
+    }
+
+    #[test]
+    fn examples_dir_round_trips_current_idls_and_flags_ones_that_change() {
+        let dir = temp_dir("examples-dir");
+        let current = write_json(&dir, "current.json", &sample_idl());
+        let legacy = write_json(&dir, "legacy.json", &legacy_idl());
+
+        check_round_trip(&current).unwrap();
+        assert!(check_round_trip(&legacy).is_err());
+
+        validate_dir(&dir, false, validate_options()).unwrap();
+        let error = validate_dir(&dir, true, validate_options()).unwrap_err();
+        assert_eq!(error.to_string(), "1 check(s) failed across 2 IDL(s)");
+    }
+
+    #[test]
+    fn json_logs_are_json_lines_at_the_selected_level() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Buffer {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(bytes)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        for (verbose, expected) in [(false, vec!["built"]), (true, vec!["parsing", "built"])] {
+            let buffer = Buffer(Arc::default());
+            let writer = buffer.clone();
+            let dispatch = log_dispatch(LogFormat::Json, verbose, move || writer.clone());
+            tracing::dispatcher::with_default(&dispatch, || {
+                debug!("parsing");
+                info!(path = "idl.json", "built");
+            });
+
+            let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+            let events: Vec<serde_json::Value> = output
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            let messages: Vec<&str> = events
+                .iter()
+                .map(|event| event["fields"]["message"].as_str().unwrap())
+                .collect();
+            assert_eq!(messages, expected);
+            assert_eq!(events.last().unwrap()["fields"]["path"], "idl.json");
+        }
+    }
+
+    #[test]
+    fn events_with_missing_field_types_are_flagged() {
+        let mut idl = sample_idl();
+        idl["events"] = json!([
+            { "name": "Deposited", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] },
+            { "name": "Missing", "discriminator": [8, 7, 6, 5, 4, 3, 2, 1] }
+        ]);
+        idl["types"].as_array_mut().unwrap().push(json!({
+            "name": "Deposited",
+            "type": { "kind": "struct", "fields": [
+                { "name": "amount", "type": "u64" },
+                { "name": "vault", "type": { "option": { "defined": { "name": "Vault" } } } }
+            ] }
+        }));
+
+        let diagnostics = check("event-fields", &idl, validate_options());
+        let (_, message) = diagnostics
+            .iter()
+            .find(|(code, _)| code == "unserializable-event")
+            .expect("unserializable events are an error");
+        assert_eq!(
+            message,
+            "Events with fields that can't be serialized: \
+             'Deposited' (field 'vault' references unresolved type 'Vault'); \
+             'Missing' (type definition is missing)"
+        );
+    }
+
+    #[test]
+    fn placeholder_program_ids_warn_and_fail_under_strict() {
+        let mut idl = sample_idl();
+        idl["address"] = json!("11111111111111111111111111111111");
+
+        let diagnostics = check("placeholder", &idl, validate_options());
+        assert_eq!(
+            diagnostics,
+            [(
+                "placeholder-address".to_string(),
+                "IDL address 11111111111111111111111111111111 is a known placeholder program ID; \
+                 was the program ID set before building?"
+                    .to_string()
+            )]
+        );
+
+        let path = write_json(&temp_dir("placeholder-strict"), "idl.json", &idl);
+        let options = ValidateOptions {
+            strict: true,
+            ..validate_options()
+        };
+        assert!(validate_idl(&path, options).is_err());
+        assert!(validate_idl(&path, validate_options()).is_ok());
+    }
+
+    #[test]
+    fn long_doc_lines_are_truncated_at_the_boundary() {
+        assert_eq!(truncate_doc("Deposit tokens", 14), "Deposit tokens");
+        assert_eq!(truncate_doc("Deposit tokens", 13), "Deposit token…");
+        // Whitespace at the cut isn't kept before the ellipsis
+        assert_eq!(truncate_doc("Deposit tokens", 8), "Deposit…");
+        // Counted in characters, not bytes
+        assert_eq!(truncate_doc("Dépôt de jetons", 5), "Dépôt…");
+        assert_eq!(truncate_doc("Deposit tokens", 0), "Deposit tokens");
+
+        // Account and expanded field docs go through the same cut as instruction docs
+        let idl = parse(json!({
+            "address": "11111111111111111111111111111111",
+            "metadata": { "name": "docs", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "deposit",
+                "discriminator": [0, 0, 0, 0, 0, 0, 0, 0],
+                "accounts": [{ "name": "vault", "writable": true, "docs": ["Vault holding deposits"] }],
+                "args": [{ "name": "params", "type": { "defined": { "name": "Params" } } }]
+            }],
+            "types": [{
+                "name": "Params",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "amount", "docs": ["Amount to deposit"], "type": "u64" }
+                ] }
+            }]
+        }));
+        let instruction = &idl.instructions[0];
+        let mut lines = Vec::new();
+        account_lines(&instruction.accounts, 1, 5, &mut lines);
+        assert_eq!(lines, ["      vault (writable)", "        Vault…"]);
+        let mut lines = Vec::new();
+        expand_type(&idl.types, &instruction.args[0].ty, 0, 6, &mut Vec::new(), &mut lines);
+        assert_eq!(lines, ["amount: u64  // Amount…"]);
+    }
+
+    #[test]
+    fn layout_hash_follows_field_order_but_not_docs() {
+        let hash = |idl: serde_json::Value| account_layout_hashes(&parse(idl))["State"].clone();
+        let original = hash(sample_idl());
+
+        let mut documented = sample_idl();
+        documented["types"][0]["docs"] = json!(["Program state"]);
+        documented["types"][0]["type"]["fields"][0]["docs"] = json!(["Who owns it"]);
+        assert_eq!(hash(documented), original);
+
+        let mut reordered = sample_idl();
+        reordered["types"][0]["type"]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .reverse();
+        assert_ne!(hash(reordered), original);
+    }
+
+    #[test]
+    fn paging_windows_the_listing_and_describes_it_in_the_footer() {
+        assert_eq!(page_window(10, 0, None), (0, 10));
+        assert_eq!(page_footer(10, 0, None), None);
+
+        assert_eq!(page_window(10, 0, Some(3)), (0, 3));
+        assert_eq!(page_footer(10, 0, Some(3)).unwrap(), "Showing 1-3 of 10");
+
+        // The last page is cut short at the end of the list
+        assert_eq!(page_window(10, 8, Some(5)), (8, 10));
+        assert_eq!(page_footer(10, 8, Some(5)).unwrap(), "Showing 9-10 of 10");
+
+        assert_eq!(page_window(10, 4, None), (4, 10));
+        assert_eq!(page_footer(10, 4, None).unwrap(), "Showing 5-10 of 10");
+
+        assert_eq!(page_window(10, 10, Some(2)), (10, 10));
+        assert_eq!(
+            page_footer(10, 12, Some(2)).unwrap(),
+            "No instructions in range (offset 12 of 10)"
+        );
+        assert_eq!(page_window(10, 2, Some(usize::MAX)), (2, 10));
+    }
+
+    #[test]
+    fn check_address_compares_the_idl_with_declare_id() {
+        let dir = temp_dir("check-address");
+        let idl = write_json(&dir, "idl.json", &sample_idl());
+        let src = dir.join("program").join("src");
+        fs::create_dir_all(&src).unwrap();
+        let program = dir.join("program");
+
+        // A commented-out ID ahead of the real one is ignored
+        fs::write(
+            src.join("lib.rs"),
+            "// declare_id!(\"Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS\");\n\
+             declare_id!(\"11111111111111111111111111111112\");\n",
+        )
+        .unwrap();
+        check_address(&idl, &program).unwrap();
+
+        fs::write(
+            src.join("lib.rs"),
+            "declare_id!(\"Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS\");\n",
+        )
+        .unwrap();
+        let error = check_address(&idl, &program).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("declare_id!: Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"));
+    }
+
+    #[test]
+    fn instructions_sort_by_name_and_reverse() {
+        let idl = with_instructions(&["withdraw", "deposit", "initialize"]);
+        let names = |sort, reverse| -> Vec<String> {
+            sort_instructions(&idl.instructions, sort, reverse)
+                .iter()
+                .map(|ix| ix.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(InstructionSort::Name, false), ["deposit", "initialize", "withdraw"]);
+        assert_eq!(names(InstructionSort::Name, true), ["withdraw", "initialize", "deposit"]);
+        assert_eq!(names(InstructionSort::Source, false), ["withdraw", "deposit", "initialize"]);
+    }
+
+    #[test]
+    fn empty_discriminators_fail_unless_allowed() {
+        // A pre-discriminator IDL: current layout, but nothing carries a discriminator
+        let mut idl = sample_idl();
+        idl["instructions"][0]["discriminator"] = json!([]);
+        idl["accounts"][0]["discriminator"] = json!([]);
+        let path = write_json(&temp_dir("empty-discriminator"), "idl.json", &idl);
+
+        let error = validate_idl(&path, validate_options()).unwrap_err();
+        assert_eq!(error.to_string(), "IDL validation failed with 2 error(s)");
+
+        let options = ValidateOptions {
+            allow_empty_discriminator: true,
+            ..validate_options()
+        };
+        validate_idl(&path, options).unwrap();
+        let diagnostics = check("empty-discriminator-allowed", &idl, options);
+        assert_eq!(codes(&diagnostics), ["empty-discriminator", "empty-discriminator"]);
+    }
+
+    #[test]
+    fn count_only_skips_validation_but_still_needs_a_parse() {
+        let dir = temp_dir("count-only");
+        // Full validation rejects this, but the counts don't depend on an address
+        let mut idl = sample_idl();
+        idl["address"] = json!("");
+        let unaddressed = write_json(&dir, "unaddressed.json", &idl);
+        assert!(validate_idl(&unaddressed, validate_options()).is_err());
+        count_sections(&unaddressed, OutputFormat::Json).unwrap();
+
+        let broken = dir.join("broken.json");
+        fs::write(&broken, r#"{ "instructions": 3 }"#).unwrap();
+        let error = count_sections(&broken, OutputFormat::Text).unwrap_err();
+        assert!(error.to_string().starts_with("IDL validation failed: "));
+    }
+
+    #[test]
+    fn instructions_group_by_name_prefix_in_order_of_first_appearance() {
+        let idl = with_instructions(&[
+            "user_deposit",
+            "admin_pause",
+            "user_withdraw",
+            "initialize",
+            "admin_resume",
+        ]);
+        let instructions = sort_instructions(&idl.instructions, InstructionSort::Source, false);
+        let grouped: Vec<(String, String)> =
+            group_instructions(instructions, Some(GroupBy::Prefix))
+                .into_iter()
+                .map(|(group, ix)| (group.unwrap(), ix.name.clone()))
+                .collect();
+
+        let expected = [
+            ("user", "user_deposit"),
+            ("user", "user_withdraw"),
+            ("admin", "admin_pause"),
+            ("admin", "admin_resume"),
+            ("initialize", "initialize"),
+        ];
+        assert_eq!(
+            grouped,
+            expected.map(|(group, name)| (group.to_string(), name.to_string()))
+        );
+    }
+
+    #[test]
+    fn legacy_primitive_spellings_convert_to_canonical_names() {
+        use anchor_lang_idl::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+
+        let mut idl = legacy_idl();
+        idl["instructions"][0]["args"][0]["type"] = json!("U64");
+        idl["accounts"][0]["type"]["fields"][0]["type"] = json!("PublicKey");
+        idl["accounts"][0]["type"]["fields"][1]["type"] = json!({ "vec": "public_key" });
+
+        let (_, substitutions, idl) =
+            parse_idl_normalized(&serde_json::to_vec(&idl).unwrap()).unwrap();
+        assert_eq!(substitutions, 3);
+
+        assert_eq!(idl.instructions[0].args[0].ty, IdlType::U64);
+        let IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Named(fields)),
+        } = &idl.types[0].ty
+        else {
+            panic!("State should be a struct with named fields");
+        };
+        assert_eq!(fields[0].ty, IdlType::Pubkey);
+        assert_eq!(fields[1].ty, IdlType::Vec(Box::new(IdlType::Pubkey)));
+
+        // `publicKey` is already what the legacy parser expects, so it isn't counted
+        assert_eq!(normalize_primitives(&mut legacy_idl()), 0);
+    }
+
+    #[test]
+    fn pda_seeds_from_missing_or_optional_accounts_are_reported() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["accounts"] = json!([
+            { "name": "payer", "writable": true, "signer": true },
+            { "name": "referrer", "optional": true },
+            {
+                "name": "state",
+                "writable": true,
+                "pda": { "seeds": [
+                    { "kind": "const", "value": [115, 116, 97, 116, 101] },
+                    { "kind": "account", "path": "payer" },
+                    { "kind": "account", "path": "vault.owner" },
+                    { "kind": "account", "path": "referrer" }
+                ] }
             }
+        ]);
+
+        let (errors, warnings) = pda_seed_problems(&parse(idl));
+        assert_eq!(
+            errors,
+            ["PDA 'state' in 'initialize' seeds from account 'vault.owner', which isn't in the instruction"]
+        );
+        assert_eq!(
+            warnings,
+            ["PDA 'state' in 'initialize' seeds from optional account 'referrer'; \
+              derivation fails when it's omitted"]
+        );
+    }
+
+    #[test]
+    fn u64_constants_are_checked_against_their_type() {
+        let check_value = |value: &str| {
+            let constant: anchor_lang_idl::types::IdlConst =
+                serde_json::from_value(json!({ "name": "MAX", "type": "u64", "value": value }))
+                    .unwrap();
+            check_constant(&constant)
+        };
+
+        assert!(matches!(check_value("18446744073709551615"), ConstantCheck::Valid));
+        assert!(matches!(check_value("1_000_000u64"), ConstantCheck::Valid));
+        assert!(matches!(check_value("60 * 60"), ConstantCheck::NotLiteral));
+        assert!(matches!(
+            check_value("18446744073709551616"),
+            ConstantCheck::Invalid(reason) if reason == "'18446744073709551616' is out of range for u64"
+        ));
+        assert!(matches!(check_value("-1"), ConstantCheck::Invalid(_)));
+
+        assert_eq!(group_digits("1000000"), "1,000,000");
+        assert_eq!(group_digits("-100"), "-100");
+
+        let mut idl = sample_idl();
+        idl["constants"] = json!([
+            { "name": "MAX_DEPOSIT", "type": "u64", "value": "1_000_000" },
+            { "name": "FEE", "type": "u8", "value": "300" }
+        ]);
+        let diagnostics = check("constants", &idl, validate_options());
+        assert_eq!(
+            diagnostics,
+            [(
+                "invalid-constant".to_string(),
+                "Constants with values that don't match their type: \
+                 'FEE' ('300' is out of range for u8)"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn accounts_writable_in_one_instruction_and_readonly_in_another_warn() {
+        let mut idl = sample_idl();
+        let instructions = idl["instructions"].as_array_mut().unwrap();
+        instructions.push(json!({
+            "name": "read",
+            "discriminator": [1, 1, 1, 1, 1, 1, 1, 1],
+            "accounts": [{ "name": "state" }, { "name": "payer", "writable": true, "signer": true }],
+            "args": []
+        }));
+        instructions.push(json!({
+            "name": "peek",
+            "discriminator": [2, 2, 2, 2, 2, 2, 2, 2],
+            "accounts": [{ "name": "state" }],
+            "args": []
+        }));
+
+        assert_eq!(
+            inconsistent_accounts(&parse(idl.clone())),
+            ["account 'state' is writable in initialize but read-only in read, peek"]
+        );
+
+        // Only reported with --consistent-accounts, and only as a warning
+        assert!(check("consistent-off", &idl, validate_options()).is_empty());
+        let options = ValidateOptions {
+            consistent_accounts: true,
+            ..validate_options()
+        };
+        assert_eq!(
+            codes(&check("consistent-on", &idl, options)),
+            ["inconsistent-account"]
+        );
+        let path = write_json(&temp_dir("consistent-valid"), "idl.json", &idl);
+        validate_idl(&path, options).unwrap();
+    }
+
+    #[test]
+    fn colliding_account_and_event_discriminators_are_reported() {
+        let mut idl = sample_idl();
+        // Shares the State account's discriminator
+        idl["events"] = json!([
+            { "name": "Updated", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] }
+        ]);
+        idl["types"].as_array_mut().unwrap().push(json!({
+            "name": "Updated",
+            "type": { "kind": "struct", "fields": [{ "name": "count", "type": "u64" }] }
+        }));
+        let idl = parse(idl);
+
+        use DiscriminatorSection::{Accounts, Events, Instructions};
+        assert_eq!(
+            discriminator_collisions(&idl, &[Accounts, Events]),
+            ["account 'State' and event 'Updated' share bytes [216, 146, 107, 94, 104, 75, 182, 177]"]
+        );
+        // Sections that aren't cross-checked don't collide
+        assert!(discriminator_collisions(&idl, &[Accounts, Instructions]).is_empty());
+        assert!(discriminator_collisions(&idl, &[]).is_empty());
+    }
+
+    #[test]
+    fn a_discriminator_that_prefixes_another_collides() {
+        let mut idl = sample_idl();
+        idl["events"] = json!([{ "name": "Short", "discriminator": [216, 146] }]);
+        idl["types"].as_array_mut().unwrap().push(json!({
+            "name": "Short",
+            "type": { "kind": "struct", "fields": [] }
+        }));
+
+        let sections = [DiscriminatorSection::Accounts, DiscriminatorSection::Events];
+        assert_eq!(
+            discriminator_collisions(&parse(idl), &sections),
+            ["account 'State' and event 'Short' share bytes [216, 146]"]
+        );
+    }
+
+    #[test]
+    fn only_types_keeps_the_types_and_the_program_identity() {
+        let mut idl = parse(sample_idl());
+        filter_sections(&mut idl, |section| section == IdlSection::Types);
+
+        assert!(idl.instructions.is_empty());
+        assert!(idl.accounts.is_empty());
+        assert_eq!(idl.types.len(), 1);
+        assert_eq!(idl.address, "11111111111111111111111111111112");
+        assert_eq!(idl.metadata.name, "demo");
+        // Nothing left refers to a type, so nothing dangles
+        assert!(split::missing_types(&idl).unwrap().is_empty());
+    }
+
+    #[test]
+    fn excluding_types_leaves_their_references_dangling() {
+        let mut idl = parse(sample_idl());
+        filter_sections(&mut idl, |section| section != IdlSection::Types);
+
+        assert!(idl.types.is_empty());
+        assert_eq!(idl.instructions.len(), 1);
+        assert_eq!(split::missing_types(&idl).unwrap(), ["State"]);
+    }
+
+    #[test]
+    fn unknown_and_out_of_range_specs_are_reported() {
+        let spec_diagnostics = |spec: &str, options: ValidateOptions<'_>| {
+            let mut idl = sample_idl();
+            idl["metadata"]["spec"] = spec.into();
+            let mut diagnostics = Diagnostics::for_path("idl.json");
+            check_spec(&idl, options, &mut diagnostics);
+            diagnostics.to_json()["diagnostics"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| {
+                    let field = |name: &str| d[name].as_str().unwrap().to_string();
+                    (field("severity"), field("message"))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(spec_diagnostics("0.1.0", validate_options()).is_empty());
+        assert_eq!(
+            spec_diagnostics("9.0.0", validate_options()),
+            [(
+                "warning".to_string(),
+                "IDL spec 9.0.0 is newer than the newest supported spec 0.1.0; parsing may be incomplete"
+                    .to_string()
+            )]
+        );
+        assert_eq!(
+            spec_diagnostics("next", validate_options()),
+            [(
+                "warning".to_string(),
+                "IDL spec 'next' is not recognized (supported: 0.1.0); parsing may be incomplete"
+                    .to_string()
+            )]
+        );
+
+        let options = ValidateOptions {
+            min_spec: Some(parse_spec_version("0.2").unwrap()),
+            ..validate_options()
+        };
+        assert_eq!(
+            spec_diagnostics("0.1.0", options),
+            [("error".to_string(), "IDL spec 0.1.0 is below --min-spec 0.2.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn struct_args_expand_into_their_fields() {
+        let mut idl = sample_idl();
+        idl["types"].as_array_mut().unwrap().extend([
+            json!({
+                "name": "Params",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "id", "type": "u64" },
+                    { "name": "inner", "type": { "option": { "defined": { "name": "Inner" } } } }
+                ] }
+            }),
+            json!({
+                "name": "Inner",
+                "type": { "kind": "struct", "fields": [{ "name": "flag", "type": "bool" }] }
+            }),
+            json!({
+                "name": "Node",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "next", "type": { "vec": { "defined": { "name": "Node" } } } }
+                ] }
+            }),
+        ]);
+        let idl = parse(idl);
+        let expand = |name: &str| {
+            let ty = anchor_lang_idl::types::IdlType::Defined {
+                name: name.to_string(),
+                generics: Vec::new(),
+            };
+            let mut lines = Vec::new();
+            expand_type(&idl.types, &ty, 0, 0, &mut Vec::new(), &mut lines);
+            lines
+        };
+
+        assert_eq!(expand("Params"), ["id: u64", "inner: Option<Inner>", "  flag: bool"]);
+        // A type that contains itself is marked rather than expanded forever
+        assert_eq!(expand("Node"), ["next: Vec<Node>", "  (recursive Node)"]);
+    }
+
+    #[test]
+    fn json_paths_translate_to_pointers_that_resolve() {
+        assert_eq!(
+            json_path_to_pointer("$.instructions[0]['name']").unwrap(),
+            "/instructions/0/name"
+        );
+        assert_eq!(json_path_to_pointer("$['a/b'].c~d").unwrap(), "/a~1b/c~0d");
+        assert_eq!(json_path_to_pointer("$").unwrap(), "");
+        for invalid in ["instructions", "$.", "$[x]", "$['open", "$..name"] {
+            assert!(json_path_to_pointer(invalid).is_err(), "{}", invalid);
         }
+
+        let idl = sample_idl();
+        let pointer = json_path_to_pointer("$.instructions[0].args[1].name").unwrap();
+        assert_eq!(idl.pointer(&pointer), Some(&json!("bump")));
+        assert_eq!(idl.pointer("/types/0/type/fields/0/type"), Some(&json!("pubkey")));
     }
-}
 
-/// Format an IDL type for display
-fn format_type(ty: &anchor_lang_idl::types::IdlType) -> String {
-    use anchor_lang_idl::types::{IdlArrayLen, IdlGenericArg, IdlType};
-    
-    match ty {
-        IdlType::Bool => "bool".into(),
-        IdlType::U8 => "u8".into(),
-        IdlType::I8 => "i8".into(),
-        IdlType::U16 => "u16".into(),
-        IdlType::I16 => "i16".into(),
-        IdlType::U32 => "u32".into(),
-        IdlType::I32 => "i32".into(),
-        IdlType::F32 => "f32".into(),
-        IdlType::U64 => "u64".into(),
-        IdlType::I64 => "i64".into(),
-        IdlType::F64 => "f64".into(),
-        IdlType::U128 => "u128".into(),
-        IdlType::I128 => "i128".into(),
-        IdlType::U256 => "u256".into(),
-        IdlType::I256 => "i256".into(),
-        IdlType::Bytes => "bytes".into(),
-        IdlType::String => "string".into(),
-        IdlType::Pubkey => "pubkey".into(),
-        IdlType::Option(inner) => format!("Option<{}>", format_type(inner)),
-        IdlType::Vec(inner) => format!("Vec<{}>", format_type(inner)),
-        IdlType::Array(inner, len) => match len {
-            IdlArrayLen::Value(size) => format!("[{}; {}]", format_type(inner), size),
-            IdlArrayLen::Generic(name) => format!("[{}; {}]", format_type(inner), name),
-        },
-        IdlType::Defined { name, generics } => {
-            if generics.is_empty() {
-                name.clone()
-            } else {
-                let generic_strs: Vec<String> = generics
-                    .iter()
-                    .map(|g| match g {
-                        IdlGenericArg::Type { ty } => format_type(ty),
-                        IdlGenericArg::Const { value } => value.clone(),
-                    })
-                    .collect();
-                format!("{}<{}>", name, generic_strs.join(", "))
-            }
+    #[test]
+    fn usage_lists_every_place_a_shared_type_is_named() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "config", "type": { "defined": { "name": "Config" } } }));
+        idl["types"][0]["type"]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({
+                "name": "config",
+                "type": { "option": { "defined": { "name": "Config" } } }
+            }));
+        idl["types"].as_array_mut().unwrap().extend([
+            json!({
+                "name": "Config",
+                "type": { "kind": "struct", "fields": [{ "name": "fee", "type": "u16" }] }
+            }),
+            json!({
+                "name": "Unused",
+                "type": { "kind": "struct", "fields": [] }
+            }),
+        ]);
+        let usage = type_usage(&parse(idl));
+        let sites = |name: &str| -> Vec<(&str, &str)> {
+            usage[name]
+                .iter()
+                .map(|(kind, site)| (kind.label(), site.as_str()))
+                .collect()
+        };
+
+        assert_eq!(
+            sites("Config"),
+            [("args", "initialize.config"), ("account fields", "State.config")]
+        );
+        assert_eq!(sites("State"), [("accounts", "State")]);
+        assert!(!usage.contains_key("Unused"));
+    }
+
+    #[test]
+    fn a_build_slower_than_the_timeout_is_abandoned() {
+        use std::time::Duration;
+
+        let slow_build = || {
+            std::thread::sleep(Duration::from_secs(5));
+            Ok("idl")
+        };
+        let started = std::time::Instant::now();
+        assert!(run_with_timeout(slow_build, Duration::from_millis(50)).is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let quick_build = || Ok("idl");
+        assert_eq!(
+            run_with_timeout(quick_build, Duration::from_secs(5)).unwrap().unwrap(),
+            "idl"
+        );
+        // A build that panics reports an error instead of hanging until the timeout
+        let panicking_build = || -> Result<&str> { panic!("build crashed") };
+        assert!(run_with_timeout(panicking_build, Duration::from_secs(5)).unwrap().is_err());
+    }
+
+    #[test]
+    fn prepare_writes_a_current_spec_idl_under_idls() {
+        let dir = temp_dir("prepare");
+        let input = write_json(&dir, "legacy.json", &legacy_idl());
+        let out_dir = dir.join("crate");
+
+        prepare_idl(&input, &out_dir).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_slice(&fs::read(out_dir.join("idls/demo.json")).unwrap()).unwrap();
+        // The shape declare_program! reads: top-level address, spec metadata, discriminators
+        assert_eq!(written["address"], "11111111111111111111111111111112");
+        assert_eq!(written["metadata"]["spec"], "0.1.0");
+        assert_eq!(
+            written["instructions"][0]["discriminator"],
+            json!([175, 175, 109, 31, 13, 152, 155, 237])
+        );
+        assert_eq!(written["types"][0]["type"]["fields"][0]["type"], "pubkey");
+        serde_json::from_value::<anchor_lang_idl::types::Idl>(written).unwrap();
+
+        // An IDL that fails validation isn't written
+        let mut no_address = sample_idl();
+        no_address["address"] = "".into();
+        no_address["metadata"]["name"] = "other".into();
+        let input = write_json(&dir, "no-address.json", &no_address);
+        assert!(prepare_idl(&input, &out_dir).is_err());
+        assert!(!out_dir.join("idls/other.json").exists());
+    }
+
+    #[test]
+    fn args_named_like_a_nested_account_are_reported_as_warnings() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["accounts"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "vault", "accounts": [{ "name": "authority" }] }));
+        idl["instructions"][0]["args"][1]["name"] = "authority".into();
+
+        let diagnostics = check("shadowed-nested-account", &idl, validate_options());
+        assert_eq!(
+            diagnostics,
+            [(
+                "arg-shadows-account".to_string(),
+                "arg 'authority' in 'initialize' has the same name as one of its accounts".to_string()
+            )]
+        );
+        let path = write_json(&temp_dir("shadowed-nested-valid"), "idl.json", &idl);
+        validate_idl(&path, validate_options()).unwrap();
+    }
+
+    #[test]
+    fn composite_accounts_flatten_with_optional_prefixes() {
+        use anchor_lang_idl::types::IdlInstructionAccountItem;
+
+        let mut idl = sample_idl();
+        idl["instructions"][0]["accounts"] = json!([
+            { "name": "payer", "writable": true, "signer": true },
+            { "name": "vault", "accounts": [
+                { "name": "authority" },
+                { "name": "token", "relations": ["authority"], "pda": {
+                    "seeds": [{ "kind": "account", "path": "authority" }]
+                } },
+                { "name": "inner", "accounts": [{ "name": "mint" }] }
+            ] }
+        ]);
+        let accounts = parse(idl).instructions.remove(0).accounts;
+        let names = |accounts: &[IdlInstructionAccountItem]| -> Vec<String> {
+            accounts
+                .iter()
+                .map(|item| match item {
+                    IdlInstructionAccountItem::Single(account) => account.name.clone(),
+                    IdlInstructionAccountItem::Composite(_) => unreachable!(),
+                })
+                .collect()
+        };
+
+        let flat = flatten_composite_accounts(accounts.clone(), false);
+        assert_eq!(names(&flat), ["payer", "authority", "token", "mint"]);
+
+        let prefixed = flatten_composite_accounts(accounts, true);
+        assert_eq!(
+            names(&prefixed),
+            ["payer", "vault_authority", "vault_token", "vault_inner_mint"]
+        );
+        // References inside the group follow the rename
+        let IdlInstructionAccountItem::Single(token) = &prefixed[2] else {
+            unreachable!()
+        };
+        assert_eq!(token.relations, ["vault_authority"]);
+        let value = serde_json::to_value(token).unwrap();
+        assert_eq!(value["pda"]["seeds"][0]["path"], "vault_authority");
+    }
+
+    #[test]
+    fn byte_constants_render_as_hex_with_a_text_preview() {
+        let bytes = constant_bytes("b\"vault\"").unwrap();
+        assert_eq!(render_bytes(&bytes), "0x7661756c74 (\"vault\")");
+        assert_eq!(constant_bytes("[118, 97u8, 117]").unwrap(), b"vau");
+        assert_eq!(constant_bytes("b\"a\\x00\\n\"").unwrap(), [b'a', 0, b'\n']);
+
+        // Control characters and invalid UTF-8 get hex only
+        assert_eq!(render_bytes(&[b'a', 0]), "0x6100");
+        assert_eq!(render_bytes(&[0xff, 0xfe]), "0xfffe");
+        assert_eq!(render_bytes(&[]), "0x");
+
+        for not_bytes in ["\"vault\"", "[1, 256]", "b\"\\é\"", "42"] {
+            assert_eq!(constant_bytes(not_bytes), None, "{}", not_bytes);
         }
-        IdlType::Generic(name) => name.clone(),
-        // wildcard pattern for any new types added in the future
-        _ => format!("<unknown type>"),
     }
-}
 
-// Validates an IDL file against specification
-fn validate_idl(path: &PathBuf) -> Result<()> {
-    debug!("Validating IDL at: {:?}", path);
-    
-    // Read the IDL file
-    let idl_bytes = fs::read(path)
-        .with_context(|| format!("Failed to read IDL file at {:?}", path))?;
-    
-    // Try to parse it as the current IDL format
-    let idl_result = anchor_lang_idl::convert::convert_idl(&idl_bytes);
-    
-    match idl_result {
-        Ok(idl) => {
-            // validation
-            if idl.address.is_empty() {
-                return Err(anyhow::anyhow!("IDL is missing program address"));
-            }
-            
-            if idl.metadata.name.is_empty() {
-                return Err(anyhow::anyhow!("IDL is missing program name"));
-            }
-            
-            if idl.metadata.version.is_empty() {
-                return Err(anyhow::anyhow!("IDL is missing version"));
-            }
-            
-            // Check for empty discriminators
-            for account in &idl.accounts {
-                if account.discriminator.is_empty() {
-                    return Err(anyhow::anyhow!(
-                        "Account '{}' has an empty discriminator", 
-                        account.name
-                    ));
-                }
-            }
-            
-            for instruction in &idl.instructions {
-                if instruction.discriminator.is_empty() {
-                    return Err(anyhow::anyhow!(
-                        "Instruction '{}' has an empty discriminator", 
-                        instruction.name
-                    ));
-                }
-            }
-            
-            for event in &idl.events {
-                if event.discriminator.is_empty() {
-                    return Err(anyhow::anyhow!(
-                        "Event '{}' has an empty discriminator", 
-                        event.name
-                    ));
-                }
-            }
-            
-            info!("IDL validation successful!");
-            info!("Program: {}", idl.metadata.name);
-            info!("Version: {}", idl.metadata.version);
-            info!("Accounts: {}", idl.accounts.len());
-            info!("Instructions: {}", idl.instructions.len());
-            info!("Types: {}", idl.types.len());
-            
-            Ok(())
-        },
-        Err(e) => {
-            Err(anyhow::anyhow!("IDL validation failed: {}", e))
+    #[test]
+    fn reordered_zero_copy_fields_fail_against_the_baseline() {
+        let mut baseline = sample_idl();
+        baseline["types"][0]["serialization"] = "bytemuck".into();
+        let mut reordered = baseline.clone();
+        reordered["types"][0]["type"]["fields"] = json!([
+            { "name": "count", "type": "u64" },
+            { "name": "added", "type": "u8" },
+            { "name": "owner", "type": "pubkey" }
+        ]);
+        let baseline = parse(baseline);
+
+        let options = ValidateOptions {
+            layout_baseline: Some(&baseline),
+            ..validate_options()
+        };
+        assert_eq!(
+            check("zero-copy-reordered", &reordered, options),
+            [(
+                "field-reorder".to_string(),
+                "Zero-copy layouts changed: type 'State' fields were owner, count and are now count, owner"
+                    .to_string()
+            )]
+        );
+
+        // Appending a field keeps the order of the existing ones
+        let mut appended = sample_idl();
+        appended["types"][0]["serialization"] = "bytemuck".into();
+        appended["types"][0]["type"]["fields"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "added", "type": "u8" }));
+        assert!(reordered_zero_copy_fields(&baseline, &parse(appended)).is_empty());
+
+        // Only zero-copy types are checked; borsh layout changes are left to `diff`
+        let mut borsh = reordered;
+        borsh["types"][0]["serialization"] = "borsh".into();
+        assert!(reordered_zero_copy_fields(&baseline, &parse(borsh)).is_empty());
+    }
+
+    #[test]
+    fn a_stripped_idl_only_validates_without_require_address() {
+        let mut idl = sample_idl();
+        idl["metadata"]["deployments"] = json!({ "devnet": "11111111111111111111111111111112" });
+        let mut idl = parse(idl);
+        clear_address(&mut idl);
+
+        assert!(idl.address.is_empty());
+        assert!(idl.metadata.deployments.is_none());
+        let stripped = serde_json::to_value(&idl).unwrap();
+        assert_eq!(stripped["instructions"], sample_idl()["instructions"]);
+
+        let path = write_json(&temp_dir("strip-address"), "idl.json", &stripped);
+        assert!(validate_idl(&path, validate_options()).is_err());
+        let options = ValidateOptions {
+            require_address: false,
+            ..validate_options()
+        };
+        validate_idl(&path, options).unwrap();
+    }
+
+    // First 8 bytes of sha256 over `preimage`, as Anchor derives discriminators
+    fn discriminator(preimage: &str) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(preimage.as_bytes())[..8].to_vec()
+    }
+
+    #[test]
+    fn instruction_discriminators_verify_against_their_namespace() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["discriminator"] = discriminator("state:initialize").into();
+        let mismatches = |namespaces: &[&str], raw: &serde_json::Value| {
+            let namespaces: Vec<InstructionNamespace> = namespaces
+                .iter()
+                .map(|value| parse_instruction_namespace(value).unwrap())
+                .collect();
+            let idl: anchor_lang_idl::types::Idl = serde_json::from_value(raw.clone()).unwrap();
+            discriminator_mismatches(&idl, raw, &namespaces, None, DiscriminatorCase::AsIs)
+        };
+
+        // `global` is assumed, which doesn't match
+        let problems = mismatches(&[], &idl);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("instruction 'initialize' is "), "{}", problems[0]);
+        assert!(problems[0].ends_with("from \"global:initialize\""), "{}", problems[0]);
+
+        assert!(mismatches(&["state"], &idl).is_empty());
+        assert!(mismatches(&["initialize=state"], &idl).is_empty());
+        // A per-instruction entry wins over the default one
+        assert!(mismatches(&["initialize=state", "other"], &idl).is_empty());
+        assert_eq!(mismatches(&["other=state"], &idl).len(), 1);
+
+        // A `namespace` field the IDL carries is used when no flag names the instruction
+        idl["instructions"][0]["namespace"] = "state".into();
+        assert!(mismatches(&[], &idl).is_empty());
+
+        assert!(parse_instruction_namespace("initialize=").is_err());
+        assert!(parse_instruction_namespace("a:b").is_err());
+    }
+
+    #[test]
+    fn output_templates_fill_in_name_version_and_stem() {
+        let idl = parse(sample_idl());
+        assert_eq!(
+            render_output_template("{name}-{version}.json", &idl, "input").unwrap(),
+            PathBuf::from("demo-0.1.0.json")
+        );
+        assert_eq!(
+            render_output_template("out/{stem}.idl.json", &idl, "program").unwrap(),
+            PathBuf::from("out/program.idl.json")
+        );
+        let dated = render_output_template("{date}.json", &idl, "input").unwrap();
+        let date = dated.file_stem().unwrap().to_str().unwrap();
+        assert_eq!(date.len(), 10);
+        assert_eq!(date.matches('-').count(), 2);
+
+        for invalid in ["{name", "{author}.json", "{name}/", "{stem}.json"] {
+            assert!(render_output_template(invalid, &idl, "..").is_err(), "{}", invalid);
         }
+        let mut nested = idl.clone();
+        nested.metadata.name = "../escape".to_string();
+        assert!(render_output_template("{name}.json", &nested, "input").is_err());
     }
-}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+    #[test]
+    fn instructions_without_accounts_or_args_get_a_warning() {
+        let mut idl = sample_idl();
+        idl["instructions"].as_array_mut().unwrap().extend([
+            json!({
+                "name": "noop",
+                "discriminator": [1, 0, 0, 0, 0, 0, 0, 0],
+                "accounts": [],
+                "args": []
+            }),
+            json!({
+                "name": "ping",
+                "discriminator": [2, 0, 0, 0, 0, 0, 0, 0],
+                "accounts": [],
+                "args": [{ "name": "nonce", "type": "u8" }]
+            }),
+        ]);
 
-    // logging based n verbosity flag
-    let log_level = if cli.verbose { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(format!("anchor_idl_cli={},anchor_lang_idl={}", log_level, log_level))
-        .init();
+        assert_eq!(
+            check("empty-instruction", &idl, validate_options()),
+            [(
+                "empty-instruction".to_string(),
+                "Instruction 'noop' takes no accounts and no args; pass --allow-empty-instructions if that's intended"
+                    .to_string()
+            )]
+        );
+        let options = ValidateOptions {
+            allow_empty_instructions: true,
+            ..validate_options()
+        };
+        assert!(check("empty-instruction-allowed", &idl, options).is_empty());
+    }
 
-    match &cli.command {
-        Commands::Build {
-            path,
-            output,
-            skip_lint,
-            no_docs,
-            no_resolution,
-        } => {
-            debug!("Building IDL for program at: {:?}", path);
-            
-            // Directly call the build_idl function - using #[allow(deprecated)] to avoid warnings
-            #[allow(deprecated)]
-            let idl = anchor_lang_idl::build::build_idl(
-                path,
-                !no_resolution,
-                *skip_lint,
-                *no_docs
-            ).context("Failed to build IDL")?;
-            
-            // Serialize the IDL to JSON with pretty printing
-            let idl_json = anchor_lang_idl::serde_json::to_string_pretty(&idl)
-                .context("Failed to serialize IDL to JSON")?;
-            
-            // Determine output path
-            let output_path = match output {
-                Some(path) => path.clone(),
-                None => {
-                    let program_name = &idl.metadata.name;
-                    PathBuf::from(format!("{}.json", program_name))
-                }
+    #[test]
+    fn conversion_report_counts_what_conversion_changed() {
+        let mut legacy = legacy_idl();
+        legacy["instructions"][0]["name"] = "initializeState".into();
+        legacy["events"] = json!([{
+            "name": "Updated",
+            "fields": [{ "name": "count", "type": "u64", "index": false }]
+        }]);
+        legacy["custom"] = json!({ "note": "kept by nobody" });
+        let (raw, normalized, idl) =
+            parse_idl_normalized(&serde_json::to_vec(&legacy).unwrap()).unwrap();
+
+        assert_eq!(
+            conversion_report(&raw, &idl, normalized),
+            [
+                "input format: legacy (no metadata.spec)",
+                "inline account/event types hoisted: 2",
+                "discriminators computed: 3",
+                "primitive names normalized: 0",
+                "instructions renamed: 1 (initializeState -> initialize_state)",
+                "input keys not carried over: custom",
+            ]
+        );
+
+        // A current-spec IDL converts as is
+        let report = conversion_report(&sample_idl(), &parse(sample_idl()), 0);
+        assert_eq!(report[0], "input format: spec 0.1.0");
+        assert_eq!(
+            &report[1..3],
+            ["inline account/event types hoisted: 0", "discriminators computed: 0"]
+        );
+        assert_eq!(report[5], "input keys not carried over: none");
+    }
+
+    #[test]
+    fn a_cached_parse_is_reused_until_the_content_changes() {
+        let cache_dir = temp_dir("parse-cache").join("cache");
+        let bytes = serde_json::to_vec(&legacy_idl()).unwrap();
+        let value = || serde_json::from_slice::<serde_json::Value>(&bytes).unwrap();
+
+        // A miss converts and stores one entry
+        let idl = convert_cached(&value(), &bytes, &cache_dir).unwrap();
+        assert_eq!(idl.instructions[0].name, "initialize");
+        let entries: Vec<PathBuf> = fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries.len(), 1);
+
+        // A hit is served from the entry, which is edited here to tell it apart from a reparse
+        let mut cached: serde_json::Value =
+            serde_json::from_slice(&fs::read(&entries[0]).unwrap()).unwrap();
+        cached["metadata"]["name"] = "from_cache".into();
+        fs::write(&entries[0], serde_json::to_vec(&cached).unwrap()).unwrap();
+        let hit = convert_cached(&value(), &bytes, &cache_dir).unwrap();
+        assert_eq!(hit.metadata.name, "from_cache");
+
+        // An unreadable entry only costs a reparse
+        fs::write(&entries[0], "{ not json").unwrap();
+        assert_eq!(convert_cached(&value(), &bytes, &cache_dir).unwrap().metadata.name, "demo");
+
+        // Different content gets its own entry
+        let other = serde_json::to_vec(&sample_idl()).unwrap();
+        let other_value = serde_json::from_slice(&other).unwrap();
+        convert_cached(&other_value, &other, &cache_dir).unwrap();
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn require_address_defaults_on_and_can_be_turned_off() {
+        let require_address = |args: &[&str]| {
+            let cli = Cli::try_parse_from(
+                ["dls-anchor", "validate", "--input", "idl.json"].iter().chain(args),
+            )
+            .unwrap();
+            let Commands::Validate { require_address, .. } = cli.command else {
+                unreachable!()
             };
-            
-            // Write the IDL to the output file
-            fs::write(&output_path, idl_json)
-                .with_context(|| format!("Failed to write IDL to {:?}", output_path))?;
-            
-            info!("Successfully built IDL and saved to {:?}", output_path);
+            require_address
+        };
+        assert!(require_address(&[]));
+        assert!(!require_address(&["--require-address=false"]));
+        assert!(require_address(&["--require-address", "true"]));
+
+        let mut idl = sample_idl();
+        idl["address"] = "".into();
+        assert_eq!(
+            check("address-required", &idl, validate_options()),
+            [("missing-address".to_string(), "IDL is missing program address".to_string())]
+        );
+        // Without the requirement the missing address is only noted, even with --strict
+        let options = ValidateOptions {
+            require_address: false,
+            strict: true,
+            ..validate_options()
+        };
+        assert_eq!(
+            codes(&check("address-optional", &idl, options)),
+            ["missing-address"]
+        );
+        let path = write_json(&temp_dir("address-optional-valid"), "idl.json", &idl);
+        validate_idl(&path, options).unwrap();
+    }
+
+    #[test]
+    fn preserve_order_follows_the_input_keys_and_items() {
+        // Written by hand: serde_json would sort the keys
+        let input = r#"{
+            "instructions": [
+                { "name": "zeta", "discriminator": [2, 0, 0, 0, 0, 0, 0, 0],
+                  "accounts": [], "args": [] },
+                { "name": "alpha", "discriminator": [1, 0, 0, 0, 0, 0, 0, 0],
+                  "accounts": [], "args": [] }
+            ],
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "address": "11111111111111111111111111111112"
+        }"#;
+        let mut idl = parse_idl(input.as_bytes()).unwrap();
+        // Sorted the other way first, as a pass such as --sort would leave them
+        idl.instructions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let raw = serde_json::from_str(input).unwrap();
+        let out = to_json_in_input_order(&mut idl, &raw, input.as_bytes(), None).unwrap();
+        assert_eq!(top_level_keys(out.as_bytes()), ["instructions", "metadata", "address"]);
+        let names: Vec<&str> = idl.instructions.iter().map(|ix| ix.name.as_str()).collect();
+        assert_eq!(names, ["zeta", "alpha"]);
+        assert!(out.find("\"zeta\"").unwrap() < out.find("\"alpha\"").unwrap());
+
+        // Input already in the usual order comes out exactly as plain output would
+        let plain = anchor_lang_idl::serde_json::to_string_pretty(&parse(sample_idl())).unwrap();
+        let mut idl = parse(sample_idl());
+        let raw = serde_json::from_str(&plain).unwrap();
+        assert_eq!(to_json_in_input_order(&mut idl, &raw, plain.as_bytes(), None).unwrap(), plain);
+    }
+
+    #[test]
+    fn arg_seeds_of_types_without_seed_bytes_are_reported() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"] = json!([
+            { "name": "flag", "type": "bool" },
+            { "name": "name", "type": "string" },
+            { "name": "params", "type": { "defined": { "name": "Params" } } }
+        ]);
+        idl["instructions"][0]["accounts"][1]["pda"] = json!({ "seeds": [
+            { "kind": "arg", "path": "flag" },
+            { "kind": "arg", "path": "name" },
+            { "kind": "arg", "path": "params.id" },
+            { "kind": "arg", "path": "params.ratio" },
+            { "kind": "arg", "path": "params.missing" }
+        ] });
+        idl["types"].as_array_mut().unwrap().push(json!({
+            "name": "Params",
+            "type": { "kind": "struct", "fields": [
+                { "name": "id", "type": "u64" },
+                { "name": "ratio", "type": "f64" }
+            ] }
+        }));
+
+        assert_eq!(
+            arg_seed_type_problems(&parse(idl.clone())),
+            [
+                "PDA 'state' in 'initialize' seeds from arg 'flag' of type bool, \
+                 which can't be used as a seed",
+                "PDA 'state' in 'initialize' seeds from arg 'params.ratio' of type f64, \
+                 which can't be used as a seed",
+                "PDA 'state' in 'initialize' seeds from 'params.missing', \
+                 which doesn't name a field of arg 'params' (Params)",
+            ]
+        );
+        let diagnostics = check("arg-seed-types", &idl, validate_options());
+        assert_eq!(codes(&diagnostics), ["arg-seed-type"; 3]);
+    }
+
+    #[test]
+    fn pretty_depth_writes_deeper_levels_compactly() {
+        let json = serde_json::to_string_pretty(&json!({
+            "a": { "b": [1, 2], "c": "x, y: {z}" },
+            "empty": {}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            limit_pretty_depth(&json, 1),
+            "{\n  \"a\": {\"b\":[1,2],\"c\":\"x, y: {z}\"},\n  \"empty\": {}\n}"
+        );
+        assert_eq!(
+            limit_pretty_depth(&json, 2),
+            "{\n  \"a\": {\n    \"b\": [1,2],\n    \"c\": \"x, y: {z}\"\n  },\n  \"empty\": {}\n}"
+        );
+        assert_eq!(
+            limit_pretty_depth(&json, 0),
+            "{\"a\":{\"b\":[1,2],\"c\":\"x, y: {z}\"},\"empty\":{}}"
+        );
+        // Deep enough to cover everything gives back plain pretty output
+        assert_eq!(limit_pretty_depth(&json, 10), json);
+    }
+
+    #[test]
+    fn whois_finds_the_account_a_discriminator_belongs_to() {
+        let idl = parse(sample_idl());
+        let state = "account State (0xd8926b5e684bb6b1)";
+
+        // The same bytes as comma-separated decimal and hex, then base58
+        let forms = [
+            "216, 146, 107, 94, 104, 75, 182, 177",
+            "0xd8926b5e684bb6b1",
+            "d8926b5e684bb6b1",
+        ];
+        for discriminator in forms {
+            let bytes = parse_discriminator(discriminator).unwrap();
+            assert_eq!(discriminator_matches(&idl, &bytes, false), [state], "{}", discriminator);
         }
-        
-        Commands::Convert { input, output } => {
-            debug!("Converting IDL from: {:?}", input);
-            
-            // Read the input IDL file
-            let idl_bytes = fs::read(input)
-                .with_context(|| format!("Failed to read IDL file at {:?}", input))?;
-            
-            // Convert the IDL
-            let converted_idl = anchor_lang_idl::convert::convert_idl(&idl_bytes)
-                .context("Failed to convert IDL")?;
-            
-            // Serialize the converted IDL to JSON with pretty printing
-            let idl_json = anchor_lang_idl::serde_json::to_string_pretty(&converted_idl)
-                .context("Failed to serialize converted IDL to JSON")?;
-            
-            // Determine output path
-            let output_path = match output {
-                Some(path) => path.clone(),
-                None => {
-                    let input_stem = input.file_stem().unwrap_or_default();
-                    let mut output_path = input.with_file_name(input_stem);
-                    output_path.set_extension("converted.json");
-                    output_path
+        let base58 = bs58::encode([216, 146, 107, 94, 104, 75, 182, 177]).into_string();
+        let bytes = parse_discriminator(&base58).unwrap();
+        assert_eq!(discriminator_matches(&idl, &bytes, false), [state]);
+
+        // A prefix only matches with --prefix
+        let prefix = parse_discriminator("216,146").unwrap();
+        assert!(discriminator_matches(&idl, &prefix, false).is_empty());
+        assert_eq!(discriminator_matches(&idl, &prefix, true), [state]);
+
+        assert!(parse_discriminator("1, 256").is_err());
+    }
+
+    #[test]
+    fn events_list_their_fields_resolved_from_types() {
+        // Legacy events declare their fields inline; conversion moves them into `types`
+        let mut legacy = legacy_idl();
+        legacy["events"] = json!([{
+            "name": "Deposited",
+            "fields": [
+                { "name": "owner", "type": "publicKey", "index": false },
+                { "name": "amount", "type": { "option": "u64" }, "index": false }
+            ]
+        }]);
+        let idl = parse_idl(&serde_json::to_vec(&legacy).unwrap()).unwrap();
+
+        let mut out = Vec::new();
+        write_events(&idl, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let listing = &out[out.find("1. Deposited").unwrap()..];
+        let discriminator = render_bytes(&idl.events[0].discriminator);
+        assert_eq!(
+            listing,
+            format!(
+                "1. Deposited\n   Discriminator: {}\n   Fields:\n     owner (pubkey)\n     \
+                 amount (Option<u64>)\n",
+                discriminator
+            )
+        );
+        assert!(out.contains("\nEvents (1):\n"));
+    }
+
+    #[test]
+    fn input_format_decides_how_an_extensionless_file_is_read() {
+        let dir = temp_dir("input-format");
+        let path = dir.join("idl");
+        fs::write(&path, serde_json::to_vec(&legacy_idl()).unwrap()).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&read_input(&path).unwrap()).unwrap();
+
+        check_input_format(&value, InputFormat::LegacyJson).unwrap();
+        let error = check_input_format(&value, InputFormat::Json).unwrap_err();
+        assert!(error.to_string().starts_with("Input has no metadata.spec"));
+        check_input_format(&sample_idl(), InputFormat::Json).unwrap();
+        assert!(check_input_format(&sample_idl(), InputFormat::LegacyJson).is_err());
+        assert!(check_input_format(&json!([]), InputFormat::Json).is_err());
+
+        let cli = Cli::try_parse_from([
+            "dls-anchor",
+            "validate",
+            "--input",
+            "idl",
+            "--input-format",
+            "legacy-json",
+        ])
+        .unwrap();
+        assert!(cli.input_format == Some(InputFormat::LegacyJson));
+    }
+
+    #[test]
+    fn diff_ignore_version_hides_only_the_version_bump() {
+        let mut bumped = sample_idl();
+        bumped["metadata"]["version"] = "0.2.0".into();
+        bumped["instructions"][0]["docs"] = json!(["Sets things up"]);
+        let canonical = |idl: serde_json::Value| diff::canonicalize(&parse(idl)).unwrap();
+        let changed = |ignore: &[DiffIgnore]| -> Vec<String> {
+            let (mut old, mut new) = (canonical(sample_idl()), canonical(bumped.clone()));
+            for aspect in ignore {
+                aspect.strip(&mut old);
+                aspect.strip(&mut new);
+            }
+            diff::diff(&old, &new)
+                .iter()
+                .map(|change| change.path().to_string())
+                .collect()
+        };
+
+        assert_eq!(changed(&[]).len(), 2);
+        assert_eq!(changed(&[DiffIgnore::Version]), ["instructions[initialize].docs"]);
+        assert!(changed(&[DiffIgnore::Version, DiffIgnore::Docs]).is_empty());
+
+        assert!(DiffIgnore::Version.covers("metadata.version"));
+        assert!(!DiffIgnore::Version.covers("metadata.name"));
+        assert!(DiffIgnore::Metadata.covers("metadata.name"));
+        assert!(DiffIgnore::Docs.covers("types[State].docs[0]"));
+    }
+
+    #[test]
+    fn account_index_json_includes_pda_seeds() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["accounts"][1]["relations"] = json!(["payer"]);
+        idl["instructions"][0]["accounts"][1]["pda"] = json!({ "seeds": [
+            { "kind": "const", "value": [115, 116, 97, 116, 101] },
+            { "kind": "account", "path": "payer" },
+            { "kind": "arg", "path": "amount" }
+        ] });
+        let idl = parse(idl);
+        let accounts = flatten_accounts(&idl.instructions[0].accounts);
+
+        assert_eq!(
+            account_json(1, accounts[1]),
+            json!({
+                "index": 1,
+                "name": "state",
+                "writable": true,
+                "signer": false,
+                "optional": false,
+                "address": null,
+                "relations": ["payer"],
+                "pda": {
+                    "seeds": [
+                        { "kind": "const", "hex": "7374617465", "bytes": [115, 116, 97, 116, 101] },
+                        { "kind": "account", "path": "payer" },
+                        { "kind": "arg", "path": "amount" }
+                    ],
+                    "program": null
                 }
-            };
-            
-            // Write the converted IDL to the output file
-            fs::write(&output_path, idl_json)
-                .with_context(|| format!("Failed to write converted IDL to {:?}", output_path))?;
-            
-            info!("Successfully converted IDL and saved to {:?}", output_path);
-        }
-        
-        Commands::Validate { input } => {
-            validate_idl(input)?;
-        }
-        
-        Commands::Instructions { input, names_only } => {
-            display_instructions(input, *names_only)?;
-        }
+            })
+        );
+        assert_eq!(account_json(0, accounts[0])["pda"], serde_json::Value::Null);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn selecting_instructions_prunes_what_only_the_others_use() {
+        let struct_type = |name: &str, fields: serde_json::Value| {
+            json!({ "name": name, "type": { "kind": "struct", "fields": fields } })
+        };
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "config", "type": { "defined": { "name": "Config" } } }));
+        idl["instructions"][0]["accounts"][1]["pda"] = json!({ "seeds": [
+            { "kind": "account", "path": "vault", "account": "Vault" }
+        ] });
+        idl["instructions"].as_array_mut().unwrap().push(json!({
+            "name": "close",
+            "discriminator": [98, 165, 201, 177, 108, 65, 206, 96],
+            "accounts": [],
+            "args": [{ "name": "reason", "type": { "defined": { "name": "Reason" } } }]
+        }));
+        idl["accounts"] = json!([
+            { "name": "State", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] },
+            { "name": "Vault", "discriminator": [211, 8, 232, 43, 2, 152, 117, 119] }
+        ]);
+        idl["events"] = json!([{ "name": "Closed", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }]);
+        idl["errors"] = json!([{ "code": 6000, "name": "Unauthorized" }]);
+        idl["types"].as_array_mut().unwrap().extend([
+            struct_type(
+                "Vault",
+                json!([{ "name": "mint", "type": { "defined": { "name": "Mint" } } }]),
+            ),
+            struct_type("Mint", json!([{ "name": "decimals", "type": "u8" }])),
+            struct_type("Config", json!([{ "name": "fee", "type": "u16" }])),
+            struct_type("Reason", json!([{ "name": "code", "type": "u8" }])),
+            struct_type("Closed", json!([])),
+        ]);
+        let mut idl = parse(idl);
+
+        select_instructions(&mut idl, &["initialize".to_string()]).unwrap();
+        let names = |items: Vec<&String>| -> Vec<String> { items.into_iter().cloned().collect() };
+        let instructions = names(idl.instructions.iter().map(|ix| &ix.name).collect());
+        assert_eq!(instructions, ["initialize"]);
+        // Vault stays for the seed that names it, and pulls in the Mint it contains; State is
+        // only named by its account type, not by a seed or an arg
+        let accounts = names(idl.accounts.iter().map(|account| &account.name).collect());
+        assert_eq!(accounts, ["Vault"]);
+        let mut types = names(idl.types.iter().map(|def| &def.name).collect());
+        types.sort();
+        assert_eq!(types, ["Config", "Mint", "Vault"]);
+        assert!(idl.events.is_empty());
+        assert_eq!(idl.errors.len(), 1);
+        assert!(split::missing_types(&idl).unwrap().is_empty());
+
+        let error = select_instructions(&mut idl, &["close".to_string()]).unwrap_err();
+        assert_eq!(error.to_string(), "Instruction 'close' is not in the IDL");
+    }
+
+    #[test]
+    fn a_custom_disc_prefix_and_case_verify_every_discriminator() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["name"] = "init_state".into();
+        idl["instructions"][0]["discriminator"] = discriminator("myapp:initState").into();
+        idl["accounts"][0]["discriminator"] = discriminator("myapp:state").into();
+        let raw = idl.clone();
+        let idl = parse(idl);
+        let mismatches = |prefix, case| discriminator_mismatches(&idl, &raw, &[], prefix, case);
+
+        assert!(mismatches(Some("myapp"), DiscriminatorCase::Camel).is_empty());
+        // The default preimages are `global:init_state` and `account:State`
+        assert_eq!(mismatches(None, DiscriminatorCase::AsIs).len(), 2);
+        let problems = mismatches(Some("myapp"), DiscriminatorCase::Snake);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].ends_with("from \"myapp:init_state\""), "{}", problems[0]);
+    }
+
+    #[test]
+    fn deeply_nested_option_vec_option_types_are_reported() {
+        let mut idl = sample_idl();
+        idl["instructions"][0]["args"][0]["type"] =
+            json!({ "option": { "vec": { "option": { "vec": "u8" } } } });
+        idl["types"][0]["type"]["fields"][1]["type"] = json!({ "vec": { "option": "u64" } });
+
+        let parsed = parse(idl.clone());
+        assert_eq!(type_depth(&parsed.instructions[0].args[0].ty), 5);
+        assert_eq!(
+            deep_types(&parsed, 4),
+            ["arg 'amount' in 'initialize' has type Option<Vec<Option<Vec<u8>>>> nested 5 deep, \
+              over --max-type-depth 4"]
+        );
+        // At the limit is fine, and the 3-deep field never trips it
+        assert!(deep_types(&parsed, 5).is_empty());
+        assert_eq!(deep_types(&parsed, 2).len(), 2);
+
+        assert!(check("deep-types-off", &idl, validate_options()).is_empty());
+        let options = ValidateOptions {
+            max_type_depth: Some(4),
+            ..validate_options()
+        };
+        assert_eq!(codes(&check("deep-types-on", &idl, options)), ["type-depth"]);
+    }
+}
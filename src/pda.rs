@@ -0,0 +1,365 @@
+use anchor_lang_idl::types::{
+    Idl, IdlDefinedFields, IdlInstruction, IdlInstructionAccount, IdlPda, IdlSeed, IdlType,
+    IdlTypeDefTy,
+};
+use anyhow::{anyhow, Context, Result};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+// Runtime limits enforced by `create_program_address`
+const MAX_SEEDS: usize = 16;
+const MAX_SEED_LEN: usize = 32;
+
+/// Caller-provided values for seeds that can't be read from the IDL itself
+pub struct SeedInputs {
+    pub args: HashMap<String, String>,
+    pub accounts: HashMap<String, String>,
+}
+
+/// A program derived address together with its bump
+pub struct DerivedPda {
+    pub address: String,
+    pub bump: u8,
+    pub program_id: String,
+}
+
+/// Derive the PDA of `account` in `instruction`, resolving every seed from the IDL or `inputs`
+pub fn derive_pda(
+    idl: &Idl,
+    instruction_name: &str,
+    account_name: &str,
+    inputs: &SeedInputs,
+) -> Result<DerivedPda> {
+    let instruction = idl
+        .instructions
+        .iter()
+        .find(|ix| ix.name == instruction_name)
+        .ok_or_else(|| anyhow!("Instruction '{}' not found in IDL", instruction_name))?;
+
+    let accounts = crate::flatten_accounts(&instruction.accounts);
+    let account = accounts
+        .iter()
+        .find(|acc| acc.name == account_name)
+        .ok_or_else(|| {
+            anyhow!(
+                "Account '{}' not found in instruction '{}'",
+                account_name,
+                instruction_name
+            )
+        })?;
+    let pda = account.pda.as_ref().ok_or_else(|| {
+        anyhow!(
+            "Account '{}' in instruction '{}' is not a PDA",
+            account_name,
+            instruction_name
+        )
+    })?;
+
+    let mut seeds = Vec::new();
+    let mut unresolved = Vec::new();
+    for seed in &pda.seeds {
+        match resolve_seed(idl, instruction, &accounts, seed, inputs) {
+            Ok(Some(bytes)) => seeds.push(bytes),
+            Ok(None) => unresolved.push(describe_missing(seed)),
+            Err(e) => unresolved.push(format!("{} ({:#})", describe_seed(seed), e)),
+        }
+    }
+
+    // A PDA may be derived from a program other than the one described by the IDL
+    let program_id = match &pda.program {
+        Some(seed) => match resolve_seed(idl, instruction, &accounts, seed, inputs) {
+            Ok(Some(bytes)) => Some(bytes),
+            Ok(None) => {
+                unresolved.push(format!("program {}", describe_missing(seed)));
+                None
+            }
+            Err(e) => {
+                unresolved.push(format!("program {} ({:#})", describe_seed(seed), e));
+                None
+            }
+        },
+        None => Some(
+            decode_pubkey(&idl.address)
+                .context("Invalid program address in IDL")?
+                .to_vec(),
+        ),
+    };
+
+    if !unresolved.is_empty() {
+        return Err(anyhow!(
+            "Could not resolve {} seed(s) for '{}': {}",
+            unresolved.len(),
+            account_name,
+            unresolved.join("; ")
+        ));
+    }
+
+    let program_id: [u8; 32] = program_id
+        .unwrap_or_default()
+        .try_into()
+        .map_err(|_| anyhow!("PDA program ID must be 32 bytes"))?;
+
+    if seeds.len() > MAX_SEEDS {
+        return Err(anyhow!(
+            "PDA has {} seeds but at most {} are allowed",
+            seeds.len(),
+            MAX_SEEDS
+        ));
+    }
+    if let Some(seed) = seeds.iter().find(|seed| seed.len() > MAX_SEED_LEN) {
+        return Err(anyhow!(
+            "Seed of {} bytes exceeds the maximum seed length of {}",
+            seed.len(),
+            MAX_SEED_LEN
+        ));
+    }
+
+    // Anchor never puts the bump in `seeds`, so every seed is hashed and the canonical bump searched for
+    let (address, bump) = find_program_address(&seeds, &program_id)
+        .ok_or_else(|| anyhow!("No viable bump seed found for '{}'", account_name))?;
+
+    Ok(DerivedPda {
+        address: bs58::encode(address).into_string(),
+        bump,
+        program_id: bs58::encode(program_id).into_string(),
+    })
+}
+
+/// The last seed's byte, when the PDA's seeds end in a one-byte constant after other seeds
+///
+/// Anchor leaves the bump out of the seeds, but a hand-written IDL may list a fixed, stored bump
+/// that way. It's only a hint for display; derivation still treats it as an ordinary seed.
+pub fn stored_bump(pda: &IdlPda) -> Option<u8> {
+    match pda.seeds.last() {
+        Some(IdlSeed::Const(seed)) if seed.value.len() == 1 && pda.seeds.len() > 1 => {
+            Some(seed.value[0])
+        }
+        _ => None,
+    }
+}
+
+/// Find the canonical (highest) bump that yields an off-curve address, as `Pubkey::find_program_address` does
+pub fn find_program_address(seeds: &[Vec<u8>], program_id: &[u8; 32]) -> Option<([u8; 32], u8)> {
+    (0..=u8::MAX)
+        .rev()
+        .find_map(|bump| create_program_address(seeds, bump, program_id).map(|hash| (hash, bump)))
+}
+
+// The address for one bump, or `None` if it lands on the curve and so isn't a valid PDA
+fn create_program_address(seeds: &[Vec<u8>], bump: u8, program_id: &[u8; 32]) -> Option<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    for seed in seeds {
+        hasher.update(seed);
+    }
+    hasher.update([bump]);
+    hasher.update(program_id);
+    hasher.update(b"ProgramDerivedAddress");
+    let hash: [u8; 32] = hasher.finalize().into();
+
+    CompressedEdwardsY(hash).decompress().is_none().then_some(hash)
+}
+
+// Returns `Ok(None)` when the seed needs a value the caller didn't provide
+fn resolve_seed(
+    idl: &Idl,
+    instruction: &IdlInstruction,
+    accounts: &[&IdlInstructionAccount],
+    seed: &IdlSeed,
+    inputs: &SeedInputs,
+) -> Result<Option<Vec<u8>>> {
+    match seed {
+        IdlSeed::Const(seed) => Ok(Some(seed.value.clone())),
+        IdlSeed::Arg(seed) => {
+            let Some(value) = inputs.args.get(&seed.path) else {
+                return Ok(None);
+            };
+            let ty = arg_type(idl, instruction, &seed.path)
+                .ok_or_else(|| anyhow!("no instruction arg matches path '{}'", seed.path))?;
+            encode_seed_value(ty, value).map(Some)
+        }
+        IdlSeed::Account(seed) => {
+            if let Some(value) = inputs.accounts.get(&seed.path) {
+                return decode_pubkey(value).map(|key| Some(key.to_vec()));
+            }
+            // Accounts with a fixed address (e.g. programs) need no input
+            match accounts.iter().find(|acc| acc.name == seed.path) {
+                Some(IdlInstructionAccount {
+                    address: Some(address),
+                    ..
+                }) => decode_pubkey(address).map(|key| Some(key.to_vec())),
+                _ => Ok(None),
+            }
+        }
+    }
+}
+
+// Resolves a dotted arg path (e.g. `params.id`) through struct-typed args
+fn arg_type<'a>(idl: &'a Idl, instruction: &'a IdlInstruction, path: &str) -> Option<&'a IdlType> {
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let mut ty = &instruction.args.iter().find(|arg| arg.name == first)?.ty;
+
+    for segment in segments {
+        let IdlType::Defined { name, .. } = ty else {
+            return None;
+        };
+        let type_def = idl.types.iter().find(|def| &def.name == name)?;
+        let IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Named(fields)),
+        } = &type_def.ty
+        else {
+            return None;
+        };
+        ty = &fields.iter().find(|field| field.name == segment)?.ty;
+    }
+
+    Some(ty)
+}
+
+// Encodes a user-supplied value the way the program would pass it as seed bytes
+fn encode_seed_value(ty: &IdlType, value: &str) -> Result<Vec<u8>> {
+    let bytes = match ty {
+        IdlType::Bool => vec![value.parse::<bool>().context("expected true or false")? as u8],
+        IdlType::U8 => value.parse::<u8>()?.to_le_bytes().to_vec(),
+        IdlType::I8 => value.parse::<i8>()?.to_le_bytes().to_vec(),
+        IdlType::U16 => value.parse::<u16>()?.to_le_bytes().to_vec(),
+        IdlType::I16 => value.parse::<i16>()?.to_le_bytes().to_vec(),
+        IdlType::U32 => value.parse::<u32>()?.to_le_bytes().to_vec(),
+        IdlType::I32 => value.parse::<i32>()?.to_le_bytes().to_vec(),
+        IdlType::U64 => value.parse::<u64>()?.to_le_bytes().to_vec(),
+        IdlType::I64 => value.parse::<i64>()?.to_le_bytes().to_vec(),
+        IdlType::U128 => value.parse::<u128>()?.to_le_bytes().to_vec(),
+        IdlType::I128 => value.parse::<i128>()?.to_le_bytes().to_vec(),
+        IdlType::String => value.as_bytes().to_vec(),
+        IdlType::Pubkey => decode_pubkey(value)?.to_vec(),
+        IdlType::Bytes | IdlType::Vec(_) | IdlType::Array(_, _) => match value.strip_prefix("0x") {
+            Some(hex) => decode_hex(hex)?,
+            None => value.as_bytes().to_vec(),
+        },
+        other => {
+            return Err(anyhow!(
+                "type {} can't be used as a seed",
+                crate::format_type(other)
+            ))
+        }
+    };
+    Ok(bytes)
+}
+
+pub fn decode_pubkey(value: &str) -> Result<[u8; 32]> {
+    let bytes = bs58::decode(value)
+        .into_vec()
+        .with_context(|| format!("'{}' is not valid base58", value))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("'{}' is not a 32-byte public key", value))
+}
+
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(anyhow!("hex value must have an even number of digits"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).context("invalid hex digit"))
+        .collect()
+}
+
+fn describe_seed(seed: &IdlSeed) -> String {
+    match seed {
+        IdlSeed::Const(seed) => format!("const seed {:?}", seed.value),
+        IdlSeed::Arg(seed) => format!("arg '{}'", seed.path),
+        IdlSeed::Account(seed) => format!("account '{}'", seed.path),
+    }
+}
+
+fn describe_missing(seed: &IdlSeed) -> String {
+    match seed {
+        IdlSeed::Arg(seed) => format!("arg '{}' (pass --arg {}=<value>)", seed.path, seed.path),
+        IdlSeed::Account(seed) => format!(
+            "account '{}' (pass --account {}=<pubkey>)",
+            seed.path, seed.path
+        ),
+        IdlSeed::Const(_) => describe_seed(seed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl_with_seeds(seeds: serde_json::Value) -> Idl {
+        serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "init",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [{ "name": "vault", "writable": true, "pda": { "seeds": seeds } }],
+                "args": [{ "name": "id", "type": "u64" }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn no_inputs() -> SeedInputs {
+        SeedInputs {
+            args: HashMap::new(),
+            accounts: HashMap::new(),
+        }
+    }
+
+    fn program_id() -> [u8; 32] {
+        decode_pubkey("11111111111111111111111111111112").unwrap()
+    }
+
+    #[test]
+    fn constant_seeds_derive_the_canonical_bump() {
+        // Known answer from Solana's `findProgramAddressSync([b"helloWorld"], SystemProgram)`
+        let mut idl = idl_with_seeds(json!([
+            { "kind": "const", "value": b"helloWorld".to_vec() }
+        ]));
+        idl.address = "11111111111111111111111111111111".to_string();
+        let derived = derive_pda(&idl, "init", "vault", &no_inputs()).unwrap();
+
+        assert_eq!(derived.address, "46GZzzetjCURsdFPb7rcnspbEMnCBXe9kpjrsZAkKb6X");
+        assert_eq!(derived.bump, 254);
+        // Canonical: bump 255 lands on the curve
+        let system_program = decode_pubkey(&idl.address).unwrap();
+        assert!(create_program_address(&[b"helloWorld".to_vec()], 255, &system_program).is_none());
+    }
+
+    #[test]
+    fn trailing_one_byte_constant_is_an_ordinary_seed() {
+        let idl = idl_with_seeds(json!([
+            { "kind": "const", "value": [118, 97, 117, 108, 116] },
+            { "kind": "const", "value": [1] }
+        ]));
+        let derived = derive_pda(&idl, "init", "vault", &no_inputs()).unwrap();
+
+        let seeds = [b"vault".to_vec(), vec![1]];
+        let (address, bump) = find_program_address(&seeds, &program_id()).unwrap();
+        assert_eq!(derived.address, bs58::encode(address).into_string());
+        assert_eq!(derived.bump, bump);
+        let accounts = crate::flatten_accounts(&idl.instructions[0].accounts);
+        assert_eq!(stored_bump(accounts[0].pda.as_ref().unwrap()), Some(1));
+    }
+
+    #[test]
+    fn arg_seeds_are_encoded_little_endian() {
+        let idl = idl_with_seeds(json!([{ "kind": "arg", "path": "id" }]));
+        let inputs = SeedInputs {
+            args: HashMap::from([("id".to_string(), "258".to_string())]),
+            accounts: HashMap::new(),
+        };
+        let derived = derive_pda(&idl, "init", "vault", &inputs).unwrap();
+        let (address, _) =
+            find_program_address(&[258u64.to_le_bytes().to_vec()], &program_id()).unwrap();
+        assert_eq!(derived.address, bs58::encode(address).into_string());
+
+        let error = derive_pda(&idl, "init", "vault", &no_inputs()).err().unwrap();
+        assert!(error.to_string().contains("pass --arg id=<value>"));
+    }
+}
@@ -0,0 +1,92 @@
+use anchor_lang_idl::types::Idl;
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Describe where an IDL built from `program_path` came from
+///
+/// `build_idl` doesn't expose source spans, so each instruction and type is located by searching
+/// the program's sources for its definition; entries that can't be found are `null`.
+pub fn collect(idl: &Idl, program_path: &Path) -> Result<Value> {
+    let program_path = program_path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve program path {:?}", program_path))?;
+
+    let mut files = Vec::new();
+    collect_sources(&program_path.join("src"), &mut files)?;
+    files.sort();
+    let sources: Vec<(PathBuf, String)> = files
+        .into_iter()
+        .filter_map(|file| fs::read_to_string(&file).ok().map(|source| (file, source)))
+        .collect();
+
+    let locate = |keywords: &[&str], name: &str| -> Value {
+        for (file, source) in &sources {
+            let line = source.lines().position(|line| {
+                keywords
+                    .iter()
+                    .any(|keyword| declares(line, &format!("{} {}", keyword, name)))
+            });
+            if let Some(line) = line {
+                let relative = file.strip_prefix(&program_path).unwrap_or(file);
+                return json!({ "file": relative.display().to_string(), "line": line + 1 });
+            }
+        }
+        Value::Null
+    };
+
+    let instructions: Map<String, Value> = idl
+        .instructions
+        .iter()
+        .map(|ix| (ix.name.clone(), locate(&["fn"], &ix.name)))
+        .collect();
+    let types: Map<String, Value> = idl
+        .types
+        .iter()
+        .map(|def| (def.name.clone(), locate(&["struct", "enum", "type"], &def.name)))
+        .collect();
+
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    Ok(json!({
+        "programPath": program_path.display().to_string(),
+        "builtAt": built_at,
+        "generator": format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        "locatedBy": "name-search",
+        "instructions": instructions,
+        "types": types,
+    }))
+}
+
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("Failed to read program sources at {:?}", dir))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_sources(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// True when `line` contains `declaration` as a whole item name, e.g. `fn init` but not `fn init_pool`
+fn declares(line: &str, declaration: &str) -> bool {
+    let code = line.split("//").next().unwrap_or_default();
+    code.match_indices(declaration).any(|(idx, _)| {
+        let before = code[..idx].chars().next_back();
+        let after = code[idx + declaration.len()..].chars().next();
+        !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+    })
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
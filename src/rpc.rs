@@ -0,0 +1,369 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::time::Duration;
+use tracing::debug;
+
+// Seed Anchor uses to derive the IDL account from the program's signer PDA
+const IDL_SEED: &str = "anchor:idl";
+
+// discriminator (8) + authority (32) + data length (4)
+const IDL_ACCOUNT_HEADER_LEN: usize = 8 + 32 + 4;
+
+/// Address of the account where Anchor publishes a program's IDL
+pub fn idl_address(program_id: &[u8; 32]) -> Result<[u8; 32]> {
+    let (base, _) = crate::pda::find_program_address(&[], program_id)
+        .ok_or_else(|| anyhow!("Failed to derive the IDL base address"))?;
+
+    // Equivalent to `Pubkey::create_with_seed(&base, "anchor:idl", program_id)`
+    let mut hasher = Sha256::new();
+    hasher.update(base);
+    hasher.update(IDL_SEED);
+    hasher.update(program_id);
+    Ok(hasher.finalize().into())
+}
+
+/// How settled the data an account fetch sees must be
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    /// Name of the level in the RPC request config
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+/// A JSON-RPC endpoint, with retry settings for transient failures
+pub struct Client {
+    url: String,
+    retries: u32,
+    retry_delay: Duration,
+    commitment: Commitment,
+}
+
+impl Client {
+    /// `retry_delay` is the wait before the first retry; it doubles with each further attempt
+    pub fn new(url: &str, retries: u32, retry_delay: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            retries,
+            retry_delay,
+            commitment: Commitment::Confirmed,
+        }
+    }
+
+    /// Commitment level for account fetches; `Confirmed` unless set
+    pub fn with_commitment(mut self, commitment: Commitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    /// Fetch and decompress the IDL published on-chain for a program, or `None` if there isn't one
+    pub fn fetch_idl(&self, program_id: &str) -> Result<Option<Vec<u8>>> {
+        let program_key =
+            crate::pda::decode_pubkey(program_id).context("Invalid program address")?;
+        let address = bs58::encode(idl_address(&program_key)?).into_string();
+        debug!("Fetching IDL account {} for program {}", address, program_id);
+
+        let Some(data) = self.get_account_data(&address)? else {
+            return Ok(None);
+        };
+
+        if data.len() < IDL_ACCOUNT_HEADER_LEN {
+            return Err(anyhow!(
+                "IDL account {} is too small ({} bytes) to hold an IDL",
+                address,
+                data.len()
+            ));
+        }
+
+        let len_bytes: [u8; 4] = data[40..IDL_ACCOUNT_HEADER_LEN].try_into()?;
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        let compressed = data
+            .get(IDL_ACCOUNT_HEADER_LEN..IDL_ACCOUNT_HEADER_LEN + len)
+            .ok_or_else(|| anyhow!("IDL account {} data is truncated", address))?;
+
+        let mut idl = Vec::new();
+        flate2::read::ZlibDecoder::new(compressed)
+            .read_to_end(&mut idl)
+            .context("Failed to decompress on-chain IDL")?;
+
+        Ok(Some(idl))
+    }
+
+    // Calls `getAccountInfo` and returns the decoded account data, or `None` if the account doesn't exist
+    fn get_account_data(&self, address: &str) -> Result<Option<Vec<u8>>> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getAccountInfo",
+            "params": [
+                address,
+                { "encoding": "base64", "commitment": self.commitment.as_str() },
+            ],
+        });
+
+        let response = self.call(&request)?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("RPC error: {}", error));
+        }
+
+        let value = &response["result"]["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+
+        let encoded = value["data"][0]
+            .as_str()
+            .ok_or_else(|| anyhow!("RPC response is missing account data"))?;
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("Failed to decode account data")?;
+
+        Ok(Some(data))
+    }
+
+    /// GET `<url>/<path>` and return the response body, or `None` if the server answers 404
+    ///
+    /// Other HTTP errors and transport failures keep the underlying `ureq::Error` as their source,
+    /// so callers can tell an unreachable server from one that refused the request.
+    pub fn get(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), path);
+        let response = match self.send(ureq::get(&url), None) {
+            Ok(response) => response,
+            Err(e) if matches!(e.downcast_ref(), Some(ureq::Error::Status(404, _))) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        };
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .with_context(|| format!("Failed to read the response from {}", url))?;
+        Ok(Some(body))
+    }
+
+    fn call(&self, request: &serde_json::Value) -> Result<serde_json::Value> {
+        self.send(ureq::post(&self.url), Some(request))
+            .with_context(|| format!("RPC request to {} failed", self.url))?
+            .into_json()
+            .context("Failed to parse RPC response")
+    }
+
+    // Sends a request, retrying with exponential backoff on transient failures only; the error
+    // is the final `ureq::Error`
+    fn send(
+        &self,
+        request: ureq::Request,
+        body: Option<&serde_json::Value>,
+    ) -> Result<ureq::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = match body {
+                Some(body) => request.clone().send_json(body.clone()),
+                None => request.clone().call(),
+            };
+            match result {
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    let delay = self
+                        .retry_delay
+                        .saturating_mul(2u32.saturating_pow(attempt));
+                    attempt += 1;
+                    debug!(
+                        "Request to {} failed ({}); retry {}/{} in {:?}",
+                        request.url(),
+                        e,
+                        attempt,
+                        self.retries,
+                        delay
+                    );
+                    std::thread::sleep(delay);
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+}
+
+// Rate limiting, server errors and dropped connections or timeouts are worth retrying
+fn is_transient(error: &ureq::Error) -> bool {
+    match error {
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+        ureq::Error::Transport(transport) => matches!(
+            transport.kind(),
+            ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io
+        ),
+    }
+}
+
+/// A local HTTP server for tests that answers each connection with the next canned response
+#[cfg(test)]
+pub mod mock {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    pub struct Server {
+        pub url: String,
+        handle: JoinHandle<Vec<Request>>,
+    }
+
+    /// What the server received: the request line (`POST / HTTP/1.1`) and the body
+    pub struct Request {
+        pub line: String,
+        pub body: String,
+    }
+
+    /// Serve `(status, body)` responses in order, one per connection, then stop
+    pub fn serve(responses: Vec<(u16, String)>) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+                requests.push(Request {
+                    line: line.trim().to_string(),
+                    body: String::from_utf8(request_body).unwrap(),
+                });
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        Server { url, handle }
+    }
+
+    impl Server {
+        /// Wait for every response to be served and return the requests received
+        pub fn requests(self) -> Vec<Request> {
+            self.handle.join().unwrap()
+        }
+    }
+
+    /// A `getAccountInfo` result holding `idl` the way Anchor stores it: header, then zlib data
+    pub fn idl_account(idl: &[u8]) -> String {
+        use base64::Engine;
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(idl).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut data = vec![0u8; 40];
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(compressed);
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "value": {
+                    "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                },
+            },
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: &str = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+
+    #[test]
+    fn retries_transient_errors_until_the_fetch_succeeds() {
+        let server = mock::serve(vec![
+            (503, String::new()),
+            (503, String::new()),
+            (200, mock::idl_account(b"{}")),
+        ]);
+        let client = Client::new(&server.url, 3, Duration::from_millis(1));
+
+        let idl = client.fetch_idl(PROGRAM_ID).unwrap();
+
+        assert_eq!(idl.as_deref(), Some(&b"{}"[..]));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_once_retries_are_exhausted() {
+        let server = mock::serve(vec![(503, String::new()), (503, String::new())]);
+        let client = Client::new(&server.url, 1, Duration::from_millis(1));
+
+        assert!(client.fetch_idl(PROGRAM_ID).is_err());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn does_not_retry_a_client_error_or_a_missing_idl() {
+        let server = mock::serve(vec![(400, String::new())]);
+        let client = Client::new(&server.url, 3, Duration::from_millis(1));
+        assert!(client.fetch_idl(PROGRAM_ID).is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        let missing = r#"{"jsonrpc":"2.0","id":1,"result":{"value":null}}"#;
+        let server = mock::serve(vec![(200, missing.to_string())]);
+        let client = Client::new(&server.url, 3, Duration::from_millis(1));
+        assert_eq!(client.fetch_idl(PROGRAM_ID).unwrap(), None);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn account_fetches_send_the_commitment() {
+        let missing = r#"{"jsonrpc":"2.0","id":1,"result":{"value":null}}"#;
+        for (commitment, expected) in [
+            (None, "confirmed"),
+            (Some(Commitment::Finalized), "finalized"),
+        ] {
+            let server = mock::serve(vec![(200, missing.to_string())]);
+            let mut client = Client::new(&server.url, 0, Duration::ZERO);
+            if let Some(commitment) = commitment {
+                client = client.with_commitment(commitment);
+            }
+            client.fetch_idl(PROGRAM_ID).unwrap();
+
+            let request: serde_json::Value =
+                serde_json::from_str(&server.requests()[0].body).unwrap();
+            assert_eq!(request["method"], "getAccountInfo");
+            assert_eq!(request["params"][1]["commitment"], expected);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// JSON Schema for the Anchor IDL, bundled into the binary
+const IDL_SCHEMA: &str = include_str!("idl.schema.json");
+
+/// IDL spec version the bundled schema describes
+pub const SCHEMA_IDL_SPEC: &str = "0.1.0";
+
+/// A schema violation located by an RFC 6901 JSON pointer into the input
+pub struct SchemaError {
+    pub pointer: String,
+    pub message: String,
+}
+
+fn schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA
+        .get_or_init(|| serde_json::from_str(IDL_SCHEMA).expect("bundled IDL schema is valid JSON"))
+}
+
+/// Validate a JSON document against the bundled IDL schema
+///
+/// Only the keywords the bundled schema uses are supported: `$ref`, `type`, `enum`, `const`,
+/// `required`, `properties`, `additionalProperties`, `items`, `prefixItems`, `minItems`,
+/// `maxItems`, `minimum`, `maximum`, `anyOf` and `oneOf`.
+pub fn validate(instance: &Value) -> Vec<SchemaError> {
+    let root = schema();
+    let mut errors = Vec::new();
+    validate_node(root, root, instance, "", &mut errors);
+    errors
+}
+
+fn validate_node(
+    schema: &Value,
+    root: &Value,
+    instance: &Value,
+    pointer: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match resolve_ref(root, reference) {
+            Some(target) => validate_node(target, root, instance, pointer, errors),
+            None => push(
+                errors,
+                pointer,
+                format!("schema reference {} not found", reference),
+            ),
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|ty| has_type(instance, ty)) {
+            push(
+                errors,
+                pointer,
+                format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(instance)
+                ),
+            );
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(instance) {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            push(
+                errors,
+                pointer,
+                format!("expected one of {}, found {}", options.join(", "), instance),
+            );
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != instance {
+            push(
+                errors,
+                pointer,
+                format!("expected {}, found {}", expected, instance),
+            );
+        }
+    }
+
+    if let Some(number) = instance.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < min {
+                push(
+                    errors,
+                    pointer,
+                    format!("{} is less than the minimum of {}", instance, min),
+                );
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > max {
+                push(
+                    errors,
+                    pointer,
+                    format!("{} is greater than the maximum of {}", instance, max),
+                );
+            }
+        }
+    }
+
+    if let Value::Object(map) = instance {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !map.contains_key(key) {
+                    push(
+                        errors,
+                        pointer,
+                        format!("missing required property '{}'", key),
+                    );
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in map {
+            let child = format!("{}/{}", pointer, escape_pointer(key));
+            match properties.and_then(|props| props.get(key)) {
+                Some(property_schema) => {
+                    validate_node(property_schema, root, value, &child, errors)
+                }
+                None => {
+                    if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                        push(errors, &child, format!("unexpected property '{}'", key));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Value::Array(items) = instance {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                push(
+                    errors,
+                    pointer,
+                    format!("expected at least {} item(s), found {}", min, items.len()),
+                );
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if (items.len() as u64) > max {
+                push(
+                    errors,
+                    pointer,
+                    format!("expected at most {} item(s), found {}", max, items.len()),
+                );
+            }
+        }
+
+        let prefix = schema.get("prefixItems").and_then(Value::as_array);
+        for (idx, item) in items.iter().enumerate() {
+            let child = format!("{}/{}", pointer, idx);
+            match prefix.and_then(|prefix| prefix.get(idx)) {
+                Some(item_schema) => validate_node(item_schema, root, item, &child, errors),
+                None => {
+                    if let Some(item_schema) = schema.get("items") {
+                        validate_node(item_schema, root, item, &child, errors);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Array(branches)) = schema.get("anyOf") {
+        let results = evaluate_branches(branches, root, instance, pointer);
+        if !results.iter().any(Vec::is_empty) {
+            errors.extend(closest_branch(results, pointer));
+        }
+    }
+
+    if let Some(Value::Array(branches)) = schema.get("oneOf") {
+        let results = evaluate_branches(branches, root, instance, pointer);
+        match results.iter().filter(|errors| errors.is_empty()).count() {
+            0 => errors.extend(closest_branch(results, pointer)),
+            1 => {}
+            count => push(
+                errors,
+                pointer,
+                format!(
+                    "value matches {} alternatives but must match exactly one",
+                    count
+                ),
+            ),
+        }
+    }
+}
+
+fn push(errors: &mut Vec<SchemaError>, pointer: &str, message: String) {
+    errors.push(SchemaError {
+        pointer: if pointer.is_empty() {
+            "/".into()
+        } else {
+            pointer.into()
+        },
+        message,
+    });
+}
+
+fn evaluate_branches(
+    branches: &[Value],
+    root: &Value,
+    instance: &Value,
+    pointer: &str,
+) -> Vec<Vec<SchemaError>> {
+    branches
+        .iter()
+        .map(|branch| {
+            let mut branch_errors = Vec::new();
+            validate_node(branch, root, instance, pointer, &mut branch_errors);
+            branch_errors
+        })
+        .collect()
+}
+
+// When no alternative matches, report the one that came closest as the most likely intent:
+// a branch that got past the value itself and failed deeper is preferred over one that
+// rejected the value outright
+fn closest_branch(results: Vec<Vec<SchemaError>>, pointer: &str) -> Vec<SchemaError> {
+    let pointer = if pointer.is_empty() { "/" } else { pointer };
+    results
+        .into_iter()
+        .min_by_key(|errors| {
+            let shallow = errors.iter().filter(|e| e.pointer == pointer).count();
+            (shallow, errors.len())
+        })
+        .unwrap_or_default()
+}
+
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn has_type(instance: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "number" => instance.is_number(),
+        "integer" => instance.is_u64() || instance.is_i64(),
+        _ => false,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,158 @@
+use anchor_lang_idl::types::Idl;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// File name the interface half records for its types in `metadata.split.types`
+pub const TYPES_FILE: &str = "types.json";
+
+/// Split an IDL into its `types` section and the interface (everything else)
+///
+/// The interface notes where its types went in `metadata.split`, so `merge` can recombine the
+/// two without being told.
+pub fn split(idl: &Idl) -> Result<(Value, Value)> {
+    let mut interface = serde_json::to_value(idl)?;
+    let types = interface
+        .as_object_mut()
+        .and_then(|object| object.remove("types"))
+        .unwrap_or_else(|| json!([]));
+
+    interface["metadata"]["split"] = json!({ "types": TYPES_FILE });
+    Ok((interface, json!({ "types": types })))
+}
+
+/// Types file named by an interface's `metadata.split`, if it records one
+pub fn types_file(interface: &Value) -> Option<&str> {
+    interface["metadata"]["split"]["types"].as_str()
+}
+
+/// Recombine an interface with a types file, checking every defined type it uses is present
+pub fn merge(mut interface: Value, types: Value) -> Result<Idl> {
+    let Some(types) = types.get("types").filter(|types| types.is_array()) else {
+        return Err(anyhow!("Types file has no `types` array"));
+    };
+
+    let object = interface
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Interface file is not a JSON object"))?;
+    if object.contains_key("types") {
+        return Err(anyhow!("Interface file already has a `types` section"));
+    }
+    if let Some(metadata) = object.get_mut("metadata").and_then(Value::as_object_mut) {
+        metadata.remove("split");
+    }
+    object.insert("types".to_string(), types.clone());
+
+    let idl: Idl = serde_json::from_value(interface).context("Merged IDL is not valid")?;
+
+    let missing = missing_types(&idl)?;
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Types referenced by the interface are missing from the types file: {}",
+            missing.join(", ")
+        ));
+    }
+
+    Ok(idl)
+}
+
+/// Names of types the IDL uses but doesn't define, sorted
+pub fn missing_types(idl: &Idl) -> Result<Vec<String>> {
+    let defined: BTreeSet<&str> = idl.types.iter().map(|def| def.name.as_str()).collect();
+    // Accounts and events are laid out by the type definition with the same name
+    let mut referenced: BTreeSet<String> = idl
+        .accounts
+        .iter()
+        .map(|account| account.name.clone())
+        .chain(idl.events.iter().map(|event| event.name.clone()))
+        .collect();
+    defined_names(&serde_json::to_value(idl)?, &mut referenced);
+    Ok(referenced
+        .into_iter()
+        .filter(|name| !defined.contains(name.as_str()))
+        .collect())
+}
+
+// Collects the names in every `{ "defined": { "name": ... } }` type reference
+fn defined_names(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(name) = object
+                .get("defined")
+                .and_then(|defined| defined.get("name"))
+                .and_then(Value::as_str)
+            {
+                names.insert(name.to_string());
+            }
+            for value in object.values() {
+                defined_names(value, names);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                defined_names(item, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl() -> Idl {
+        serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "initialize",
+                "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                "accounts": [{ "name": "state", "writable": true }],
+                "args": [{ "name": "config", "type": { "defined": { "name": "Config" } } }]
+            }],
+            "accounts": [{ "name": "State", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] }],
+            "types": [
+                {
+                    "name": "Config",
+                    "type": { "kind": "struct", "fields": [{ "name": "fee", "type": "u16" }] }
+                },
+                {
+                    "name": "State",
+                    "type": { "kind": "struct", "fields": [
+                        { "name": "config", "type": { "defined": { "name": "Config" } } }
+                    ] }
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn split_then_merge_gives_back_the_original() {
+        let original = idl();
+        let (interface, types) = split(&original).unwrap();
+
+        assert!(interface.get("types").is_none());
+        assert_eq!(types_file(&interface), Some(TYPES_FILE));
+        assert_eq!(types["types"].as_array().unwrap().len(), 2);
+
+        let merged = merge(interface, types).unwrap();
+        assert_eq!(
+            crate::diff::canonicalize(&merged).unwrap(),
+            crate::diff::canonicalize(&original).unwrap()
+        );
+    }
+
+    #[test]
+    fn merge_refuses_a_types_file_missing_a_used_type() {
+        let (interface, mut types) = split(&idl()).unwrap();
+        types["types"].as_array_mut().unwrap().remove(0);
+
+        let error = merge(interface, types).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Types referenced by the interface are missing from the types file: Config"
+        );
+    }
+}
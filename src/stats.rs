@@ -0,0 +1,197 @@
+use anchor_lang_idl::types::{Idl, IdlDefinedFields, IdlType, IdlTypeDefTy};
+use serde_json::{Map, Value};
+
+/// One measurement of an IDL's size or shape
+pub struct Metric {
+    /// Snake-case name, used as the JSON key and, prefixed, as the Prometheus metric name
+    pub name: &'static str,
+    pub help: &'static str,
+    pub value: usize,
+}
+
+// Prefix that keeps the exported gauges in their own namespace
+const PROMETHEUS_PREFIX: &str = "anchor_idl_";
+
+/// Every metric `stats` reports, in output order
+pub fn collect(idl: &Idl) -> Vec<Metric> {
+    let metric = |name, help, value| Metric { name, help, value };
+    vec![
+        metric("instructions", "Instructions in the IDL", idl.instructions.len()),
+        metric("accounts", "Account types in the IDL", idl.accounts.len()),
+        metric("events", "Events in the IDL", idl.events.len()),
+        metric("errors", "Error codes in the IDL", idl.errors.len()),
+        metric("types", "Type definitions in the IDL", idl.types.len()),
+        metric("constants", "Constants in the IDL", idl.constants.len()),
+        metric(
+            "instruction_args",
+            "Arguments across all instructions",
+            idl.instructions.iter().map(|ix| ix.args.len()).sum(),
+        ),
+        metric(
+            "instruction_accounts",
+            "Accounts across all instructions, with composite groups flattened",
+            idl.instructions
+                .iter()
+                .map(|ix| crate::flatten_accounts(&ix.accounts).len())
+                .sum(),
+        ),
+        metric(
+            "max_type_depth",
+            "Deepest nesting of any arg or type field",
+            max_type_depth(idl),
+        ),
+    ]
+}
+
+// Deepest `type_depth` of any instruction arg, type field or alias; 0 when there are none
+fn max_type_depth(idl: &Idl) -> usize {
+    let mut types: Vec<&IdlType> = idl
+        .instructions
+        .iter()
+        .flat_map(|ix| ix.args.iter().map(|arg| &arg.ty))
+        .collect();
+
+    for def in &idl.types {
+        let fields = match &def.ty {
+            IdlTypeDefTy::Struct { fields } => fields.iter().collect(),
+            IdlTypeDefTy::Enum { variants } => variants
+                .iter()
+                .filter_map(|variant| variant.fields.as_ref())
+                .collect(),
+            IdlTypeDefTy::Type { alias } => {
+                types.push(alias);
+                Vec::new()
+            }
+        };
+        for fields in fields {
+            match fields {
+                IdlDefinedFields::Named(fields) => {
+                    types.extend(fields.iter().map(|field| &field.ty))
+                }
+                IdlDefinedFields::Tuple(tys) => types.extend(tys),
+            }
+        }
+    }
+
+    types.into_iter().map(crate::type_depth).max().unwrap_or(0)
+}
+
+/// `{ "name", "version", "spec", <metric>: n, ... }`; `spec` is the input's detected spec
+pub fn to_json(idl: &Idl, spec: &str, metrics: &[Metric]) -> Value {
+    let mut object = Map::new();
+    object.insert("name".into(), idl.metadata.name.clone().into());
+    object.insert("version".into(), idl.metadata.version.clone().into());
+    object.insert("spec".into(), spec.into());
+    for metric in metrics {
+        object.insert(metric.name.into(), metric.value.into());
+    }
+    Value::Object(object)
+}
+
+/// Prometheus text exposition: a HELP and TYPE line, then one gauge sample labeled with the program
+/// and the input's detected spec
+pub fn to_prometheus(idl: &Idl, spec: &str, metrics: &[Metric]) -> String {
+    let program = escape_label(&idl.metadata.name);
+    let spec = escape_label(spec);
+    let mut out = String::new();
+    for metric in metrics {
+        let name = format!("{}{}", PROMETHEUS_PREFIX, metric.name);
+        out.push_str(&format!("# HELP {} {}\n", name, metric.help));
+        out.push_str(&format!("# TYPE {} gauge\n", name));
+        out.push_str(&format!(
+            "{}{{program=\"{}\",spec=\"{}\"}} {}\n",
+            name, program, spec, metric.value
+        ));
+    }
+    out
+}
+
+// Label values escape backslash, double quote and newline
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl() -> Idl {
+        serde_json::from_value(json!({
+            "address": "11111111111111111111111111111112",
+            "metadata": { "name": "demo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "initialize",
+                "discriminator": [175, 175, 109, 31, 13, 152, 155, 237],
+                "accounts": [
+                    { "name": "payer", "writable": true, "signer": true },
+                    { "name": "group", "accounts": [{ "name": "a" }, { "name": "b" }] }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "tags", "type": { "vec": { "option": "string" } } }
+                ]
+            }],
+            "accounts": [{ "name": "State", "discriminator": [216, 146, 107, 94, 104, 75, 182, 177] }],
+            "types": [{
+                "name": "State",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "owner", "type": "pubkey" },
+                    { "name": "history", "type": { "vec": { "array": [{ "option": "u8" }, 4] } } }
+                ] }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn collects_counts_and_nesting() {
+        let stats = to_json(&idl(), "0.1.0", &collect(&idl()));
+        assert_eq!(
+            stats,
+            json!({
+                "name": "demo",
+                "version": "0.1.0",
+                "spec": "0.1.0",
+                "instructions": 1,
+                "accounts": 1,
+                "events": 0,
+                "errors": 0,
+                "types": 1,
+                "constants": 0,
+                "instruction_args": 2,
+                "instruction_accounts": 3,
+                "max_type_depth": 4,
+            })
+        );
+    }
+
+    #[test]
+    fn prometheus_output_has_help_type_and_a_program_label() {
+        let text = to_prometheus(&idl(), "legacy", &collect(&idl()));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            &lines[..3],
+            [
+                "# HELP anchor_idl_instructions Instructions in the IDL",
+                "# TYPE anchor_idl_instructions gauge",
+                "anchor_idl_instructions{program=\"demo\",spec=\"legacy\"} 1",
+            ]
+        );
+        assert!(lines.contains(&"anchor_idl_max_type_depth{program=\"demo\",spec=\"legacy\"} 4"));
+        // Every metric gets its three lines, and every name is a valid Prometheus identifier
+        assert_eq!(lines.len(), 3 * collect(&idl()).len());
+        for line in lines.iter().filter(|line| !line.starts_with('#')) {
+            let name = &line[..line.find('{').unwrap()];
+            assert!(name.chars().all(|c| c.is_ascii_lowercase() || c == '_'));
+        }
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}